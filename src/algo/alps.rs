@@ -0,0 +1,561 @@
+use std::sync::Arc;
+use std::marker::PhantomData;
+use std::time::{Duration, Instant};
+use rand::{self, Rng};
+use par_exec::{Executor, LocalContextBuilder, WorkAmount, JobIterBuild, ExecutorNewError, ExecutorJobError, JobExecuteError};
+
+use super::Algorithm;
+use super::super::pop::individual::IndividualManager;
+use super::super::pop::breed::BreedManager;
+use super::super::pop::repair::{Repair, RepairManager, RetrieveRepairManager};
+use super::super::pop::init::PopulationInit;
+use super::super::pop::init::limited;
+use super::super::pop::stats::{self, Collector, Generation, Objective};
+use super::super::set::{self, Set, SetManager};
+
+// common policy
+pub trait Policy {
+    // individual config
+    type Indiv: Clone + Send + Sync + 'static;
+    type Fit: Clone + Send + Sync + 'static;
+    type IndivME: Send + 'static;
+    type IndivM: IndividualManager<I = Self::Indiv, FI = Self::Fit, E = Self::IndivME>;
+    type BreedME: Send + 'static;
+    type BreedM: BreedManager<I = Self::Indiv, E = Self::BreedME>;
+    type RepairME: Send + 'static;
+    type RepairM: RepairManager<I = Self::Indiv, E = Self::RepairME>;
+
+    // population config (used for bottom layer seeding)
+    type PopSE: Send + 'static;
+    type PopS: Set<T = Self::Indiv, E = Self::PopSE> + Send + 'static;
+    type PopSME: Send + 'static;
+    type PopSM: SetManager<S = Self::PopS, E = Self::PopSME>;
+}
+
+pub struct LocalContext<P> where P: Policy {
+    indiv_manager: P::IndivM,
+    breed_manager: P::BreedM,
+    repair_manager: P::RepairM,
+    pop_set_manager: P::PopSM,
+}
+
+impl<P> LocalContext<P> where P: Policy {
+    pub fn new(indiv_manager: P::IndivM, breed_manager: P::BreedM, repair_manager: P::RepairM, pop_set_manager: P::PopSM) -> LocalContext<P> {
+        LocalContext {
+            indiv_manager: indiv_manager,
+            breed_manager: breed_manager,
+            repair_manager: repair_manager,
+            pop_set_manager: pop_set_manager,
+        }
+    }
+}
+
+impl<P> limited::RetrievePopulationManager for LocalContext<P> where P: Policy {
+    type PopM = P::PopSM;
+
+    fn retrieve(&mut self) -> &mut Self::PopM {
+        &mut self.pop_set_manager
+    }
+}
+
+impl<P> limited::RetrieveIndividualManager for LocalContext<P> where P: Policy {
+    type IM = P::IndivM;
+
+    fn retrieve(&mut self) -> &mut Self::IM {
+        &mut self.indiv_manager
+    }
+}
+
+impl<P> RetrieveRepairManager for LocalContext<P> where P: Policy {
+    type RM = P::RepairM;
+
+    fn retrieve(&mut self) -> &mut Self::RM {
+        &mut self.repair_manager
+    }
+}
+
+// algorithm policy
+pub trait APolicy {
+    type P: Policy;
+    type LCBuilder: LocalContextBuilder<LC = LocalContext<Self::P>>;
+    type Exec: Executor<LC = LocalContext<Self::P>>;
+    type PopInit: PopulationInit<Exec = Self::Exec, Indiv = <Self::P as Policy>::Indiv, Pop = <Self::P as Policy>::PopS>;
+    type InitWA: WorkAmount;
+    type EvalWA: WorkAmount;
+}
+
+pub struct PopInitPolicy<AP>(PhantomData<AP>) where AP: APolicy;
+impl<AP> limited::Policy for PopInitPolicy<AP> where AP: APolicy {
+    type LocalContext = LocalContext<AP::P>;
+    type Exec = AP::Exec;
+    type Indiv = <AP::P as Policy>::Indiv;
+    type IndivME = <AP::P as Policy>::IndivME;
+    type IndivM = <AP::P as Policy>::IndivM;
+    type RepairME = <AP::P as Policy>::RepairME;
+    type RepairM = <AP::P as Policy>::RepairM;
+    type PopE = <AP::P as Policy>::PopSE;
+    type Pop = <AP::P as Policy>::PopS;
+    type PopSME = <AP::P as Policy>::PopSME;
+    type PopSM = <AP::P as Policy>::PopSM;
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AgingScheme {
+    // age_gap * 1, 2, 3, 4, ..
+    Linear,
+    // age_gap * 1, 2, 3, 5, 8, ..
+    Fibonacci,
+    // age_gap * 1, 2, 4, 8, ..
+    Exponential,
+}
+
+impl AgingScheme {
+    // maximum age of individuals allowed to stay in the given layer
+    pub fn age_limit(&self, age_gap: usize, layer: usize) -> usize {
+        let factor = match *self {
+            AgingScheme::Linear =>
+                layer + 1,
+            AgingScheme::Fibonacci => {
+                let (mut a, mut b) = (1, 2);
+                for _ in 0 .. layer {
+                    let next = a + b;
+                    a = b;
+                    b = next;
+                }
+                a
+            },
+            AgingScheme::Exponential =>
+                1 << layer,
+        };
+        age_gap * factor
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Params {
+    pub layers: usize,
+    pub layer_size: usize,
+    pub age_gap: usize,
+    pub scheme: AgingScheme,
+    // amount of the best individuals copied unchanged to the next generation of each layer
+    pub elites: usize,
+    pub generations: usize,
+    // applied to bred offspring, initial population is repaired by `PopInit`
+    pub repair: Repair,
+}
+
+#[derive(Clone, Debug)]
+pub struct Member<I, FI> {
+    pub indiv: I,
+    pub fitness: FI,
+    pub age: usize,
+}
+
+pub struct Alps<AP> where AP: APolicy {
+    lc_builder: AP::LCBuilder,
+    pop_init: AP::PopInit,
+    params: Params,
+    better: fn(&<AP::P as Policy>::Fit, &<AP::P as Policy>::Fit) -> bool,
+}
+
+impl<AP> Alps<AP> where AP: APolicy {
+    pub fn new(lc_builder: AP::LCBuilder,
+               pop_init: AP::PopInit,
+               params: Params,
+               better: fn(&<AP::P as Policy>::Fit, &<AP::P as Policy>::Fit) -> bool)
+               -> Alps<AP>
+    {
+        Alps {
+            lc_builder: lc_builder,
+            pop_init: pop_init,
+            params: params,
+            better: better,
+        }
+    }
+
+    // turns the algorithm into one which also returns per generation statistics of all the layers,
+    // `value` maps fitness to a number
+    pub fn with_stats(self, value: fn(&<AP::P as Policy>::Fit) -> f64, objective: Objective) -> AlpsWithStats<AP> {
+        AlpsWithStats {
+            alps: self,
+            value: value,
+            objective: objective,
+        }
+    }
+}
+
+pub struct AlpsWithStats<AP> where AP: APolicy {
+    alps: Alps<AP>,
+    value: fn(&<AP::P as Policy>::Fit) -> f64,
+    objective: Objective,
+}
+
+#[derive(Debug)]
+pub enum EvalError<IME, BME, RME> {
+    IndividualManager(IME),
+    BreedManager(BME),
+    RepairManager(RME),
+}
+
+#[derive(Debug)]
+pub enum Error<ExecE, LCBE, InitE, PopE, IndivME, BreedME, RepairME> {
+    ExecutorStart(ExecutorNewError<ExecE, LCBE>),
+    PopulationInit(InitE),
+    Population(PopE),
+    Eval(ExecutorJobError<ExecE, JobExecuteError<EvalError<IndivME, BreedME, RepairME>, ()>>),
+    Stats(stats::Error<ExecE, set::vec::Error>),
+    NoLayers,
+    EmptyPopulation,
+}
+
+pub type ErrorAP<AP> where AP: APolicy = Error<
+    <AP::Exec as Executor>::E,
+    <AP::LCBuilder as LocalContextBuilder>::E,
+    <AP::PopInit as PopulationInit>::Err,
+    <AP::P as Policy>::PopSE,
+    <AP::P as Policy>::IndivME,
+    <AP::P as Policy>::BreedME,
+    <AP::P as Policy>::RepairME>;
+
+type Members<P> = Vec<Member<<P as Policy>::Indiv, <P as Policy>::Fit>>;
+
+// generates and evaluates a fresh bottom layer
+fn seed<AP>(pop_init: &AP::PopInit, repair: Repair, exec: &mut AP::Exec) -> Result<Members<AP::P>, ErrorAP<AP>> where
+    AP: APolicy,
+    <AP::Exec as Executor>::JIB: JobIterBuild<AP::InitWA>,
+    <AP::Exec as Executor>::JIB: JobIterBuild<AP::EvalWA>
+{
+    let population = try!(pop_init.init::<AP::InitWA>(exec).map_err(Error::PopulationInit));
+    let mut fresh = Vec::with_capacity(population.size());
+    for maybe_indiv in population.into_iter() {
+        fresh.push(try!(maybe_indiv.map_err(Error::Population)));
+    }
+    let fresh = Arc::new(fresh);
+    let fresh_job = fresh.clone();
+    let maybe_fits = try!(exec.try_execute_job(
+        AP::EvalWA::new(fresh.len()),
+        move |local_context, input_indices| {
+            let mut fits = Vec::new();
+            for index in input_indices {
+                let repaired = try!(repair.evaluate(&mut local_context.repair_manager, &fresh_job[index]).map_err(EvalError::RepairManager));
+                let fitness = try!(local_context.indiv_manager.fitness(repaired.as_ref().unwrap_or(&fresh_job[index])).map_err(EvalError::IndividualManager));
+                fits.push((index, fitness));
+            }
+            Ok(fits)
+        },
+        |_local_context, mut fits_a, fits_b| { fits_a.extend(fits_b); Ok(fits_a) })
+        .map_err(Error::Eval));
+    let mut fits = maybe_fits.unwrap_or_else(Vec::new);
+    fits.sort_by_key(|f| f.0);
+    Ok(IntoIterator::into_iter(fits)
+       .map(|(index, fitness)| Member { indiv: fresh[index].clone(), fitness: fitness, age: 0, })
+       .collect())
+}
+
+// breeds and evaluates offspring for every layer, parents are taken from the layer itself and the one below it
+fn breed<AP>(layers: Arc<Vec<Members<AP::P>>>, params: &Params, better: fn(&<AP::P as Policy>::Fit, &<AP::P as Policy>::Fit) -> bool, exec: &mut AP::Exec) ->
+    Result<Vec<(usize, Member<<AP::P as Policy>::Indiv, <AP::P as Policy>::Fit>)>, ErrorAP<AP>> where
+    AP: APolicy,
+    <AP::Exec as Executor>::JIB: JobIterBuild<AP::EvalWA>
+{
+    let layer_size = params.layer_size;
+    let repair = params.repair;
+    let maybe_offspring = try!(exec.try_execute_job(
+        AP::EvalWA::new(layers.len() * layer_size),
+        move |local_context, input_indices| {
+            let mut rng = rand::thread_rng();
+            let mut offspring = Vec::new();
+            for index in input_indices {
+                let layer = index / layer_size;
+                let pool_below = if layer == 0 { 0 } else { layers[layer - 1].len() };
+                let pool_size = pool_below + layers[layer].len();
+                if pool_size == 0 {
+                    continue;
+                }
+                let (parent_a, parent_b) = {
+                    let mut tournament = || {
+                        let (a, b) = (rng.gen_range(0, pool_size), rng.gen_range(0, pool_size));
+                        let a = if a < pool_below { &layers[layer - 1][a] } else { &layers[layer][a - pool_below] };
+                        let b = if b < pool_below { &layers[layer - 1][b] } else { &layers[layer][b - pool_below] };
+                        if better(&b.fitness, &a.fitness) { b } else { a }
+                    };
+                    (tournament(), tournament())
+                };
+                let child = try!(local_context.breed_manager.breed(&parent_a.indiv, &parent_b.indiv).map_err(EvalError::BreedManager));
+                let child = try!(repair.produce(&mut local_context.repair_manager, child).map_err(EvalError::RepairManager));
+                let repaired = try!(repair.evaluate(&mut local_context.repair_manager, &child).map_err(EvalError::RepairManager));
+                let fitness = try!(local_context.indiv_manager.fitness(repaired.as_ref().unwrap_or(&child)).map_err(EvalError::IndividualManager));
+                let age = if parent_a.age > parent_b.age { parent_a.age } else { parent_b.age } + 1;
+                offspring.push((layer, Member { indiv: child, fitness: fitness, age: age, }));
+            }
+            Ok(offspring)
+        },
+        |_local_context, mut offspring_a, offspring_b| { offspring_a.extend(offspring_b); Ok(offspring_a) })
+        .map_err(Error::Eval));
+    Ok(maybe_offspring.unwrap_or_else(Vec::new))
+}
+
+fn sort_members<I, FI>(members: &mut Vec<Member<I, FI>>, better: fn(&FI, &FI) -> bool) {
+    use std::cmp::Ordering;
+    members.sort_by(|a, b| if better(&a.fitness, &b.fitness) {
+        Ordering::Less
+    } else if better(&b.fitness, &a.fitness) {
+        Ordering::Greater
+    } else {
+        Ordering::Equal
+    });
+}
+
+// puts a member into a layer replacing its worst individual when the layer is full
+fn admit<I, FI>(layer: &mut Vec<Member<I, FI>>, member: Member<I, FI>, layer_size: usize, better: fn(&FI, &FI) -> bool) {
+    if layer.len() < layer_size {
+        layer.push(member);
+        return;
+    }
+    let mut worst: Option<usize> = None;
+    for i in 0 .. layer.len() {
+        worst = match worst {
+            Some(w) if !better(&layer[w].fitness, &layer[i].fitness) => Some(w),
+            _ => Some(i),
+        };
+    }
+    if let Some(w) = worst {
+        if better(&member.fitness, &layer[w].fitness) {
+            layer[w] = member;
+        }
+    }
+}
+
+// moves over-aged individuals of each layer (except the top one) up to the next layer
+fn promote<I, FI>(layers: &mut Vec<Vec<Member<I, FI>>>, params: &Params, better: fn(&FI, &FI) -> bool) {
+    for layer in 0 .. layers.len() - 1 {
+        let limit = params.scheme.age_limit(params.age_gap, layer);
+        let members = ::std::mem::replace(&mut layers[layer], Vec::new());
+        for member in members {
+            if member.age > limit {
+                admit(&mut layers[layer + 1], member, params.layer_size, better);
+            } else {
+                layers[layer].push(member);
+            }
+        }
+    }
+}
+
+impl<AP> Alps<AP> where
+    AP: APolicy,
+    <AP::Exec as Executor>::JIB: JobIterBuild<AP::InitWA>,
+    <AP::Exec as Executor>::JIB: JobIterBuild<AP::EvalWA>
+{
+    fn run_collecting(self, not_started_executor: AP::Exec, stats: Option<(fn(&<AP::P as Policy>::Fit) -> f64, Objective)>) ->
+        Result<(<AP::P as Policy>::Indiv, <AP::P as Policy>::Fit, Vec<Generation>), ErrorAP<AP>>
+    {
+        let params = self.params;
+        let better = self.better;
+        if params.layers == 0 {
+            return Err(Error::NoLayers);
+        }
+        let mut executor =
+            try!(not_started_executor.try_start(self.lc_builder).map_err(Error::ExecutorStart));
+
+        let mut layers: Vec<Members<AP::P>> = (0 .. params.layers).map(|_| Vec::new()).collect();
+        let mut best: Option<Member<<AP::P as Policy>::Indiv, <AP::P as Policy>::Fit>> = None;
+        let mut collector = stats.map(|(_, objective)| Collector::new(objective));
+        let (mut evaluations, mut eval_time) = (0, Duration::from_secs(0));
+        for generation in 0 .. params.generations + 1 {
+            // bottom layer is periodically replaced with fresh individuals, the old ones get a chance to move up
+            if generation == 0 || (params.age_gap != 0 && generation % params.age_gap == 0) {
+                let started = Instant::now();
+                let seeded = try!(seed::<AP>(&self.pop_init, params.repair, &mut executor));
+                evaluations += seeded.len();
+                eval_time += started.elapsed();
+                let previous = ::std::mem::replace(&mut layers[0], seeded);
+                if params.layers > 1 {
+                    for member in previous {
+                        admit(&mut layers[1], member, params.layer_size, better);
+                    }
+                }
+            }
+
+            for member in layers.iter().flat_map(|layer| layer.iter()) {
+                let improved = match best {
+                    None => true,
+                    Some(ref best_member) => better(&member.fitness, &best_member.fitness),
+                };
+                if improved {
+                    best = Some(member.clone());
+                }
+            }
+            if let (Some(ref mut collector), Some((value, _))) = (collector.as_mut(), stats) {
+                let fits: Vec<_> = layers.iter().flat_map(|layer| layer.iter()).enumerate().map(|(index, member)| (member.fitness.clone(), index)).collect();
+                try!(collector.record::<AP::EvalWA, _, _, _>(Arc::new(fits), value, evaluations, eval_time, &mut executor).map_err(Error::Stats));
+            }
+            evaluations = 0;
+            eval_time = Duration::from_secs(0);
+            if generation == params.generations {
+                break;
+            }
+
+            let snapshot = Arc::new(layers);
+            let started = Instant::now();
+            let offspring = try!(breed::<AP>(snapshot.clone(), &params, better, &mut executor));
+            evaluations += offspring.len();
+            eval_time += started.elapsed();
+            layers = match Arc::try_unwrap(snapshot) {
+                Ok(layers) => layers,
+                Err(shared) => (*shared).clone(),
+            };
+
+            let mut next_layers: Vec<Members<AP::P>> = Vec::with_capacity(params.layers);
+            for layer in layers.iter_mut() {
+                sort_members(layer, better);
+                layer.truncate(params.elites);
+                next_layers.push(layer.drain(..).map(|mut m| { m.age += 1; m }).collect());
+            }
+            for (layer, member) in offspring {
+                if next_layers[layer].len() < params.layer_size {
+                    next_layers[layer].push(member);
+                }
+            }
+            promote(&mut next_layers, &params, better);
+            layers = next_layers;
+        }
+
+        match best {
+            Some(member) => Ok((member.indiv, member.fitness, collector.map(Collector::into_series).unwrap_or_else(Vec::new))),
+            None => Err(Error::EmptyPopulation),
+        }
+    }
+}
+
+impl<AP> Algorithm for Alps<AP> where
+    AP: APolicy,
+    <AP::Exec as Executor>::JIB: JobIterBuild<AP::InitWA>,
+    <AP::Exec as Executor>::JIB: JobIterBuild<AP::EvalWA>
+{
+    type Exec = AP::Exec;
+    type Res = (<AP::P as Policy>::Indiv, <AP::P as Policy>::Fit);
+    type Err = ErrorAP<AP>;
+
+    fn run(self, not_started_executor: Self::Exec) -> Result<Self::Res, Self::Err> {
+        let (indiv, fitness, _) = try!(self.run_collecting(not_started_executor, None));
+        Ok((indiv, fitness))
+    }
+}
+
+impl<AP> Algorithm for AlpsWithStats<AP> where
+    AP: APolicy,
+    <AP::Exec as Executor>::JIB: JobIterBuild<AP::InitWA>,
+    <AP::Exec as Executor>::JIB: JobIterBuild<AP::EvalWA>
+{
+    type Exec = AP::Exec;
+    type Res = (<AP::P as Policy>::Indiv, <AP::P as Policy>::Fit, Vec<Generation>);
+    type Err = ErrorAP<AP>;
+
+    fn run(self, not_started_executor: Self::Exec) -> Result<Self::Res, Self::Err> {
+        self.alps.run_collecting(not_started_executor, Some((self.value, self.objective)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{self, Rng};
+    use par_exec::par::{ParallelExecutor, Alternately};
+    use super::super::Algorithm;
+    use super::super::super::set;
+    use super::super::super::pop::individual::IndividualManager;
+    use super::super::super::pop::breed::BreedManager;
+    use super::super::super::pop::init::limited::LimitedPopulationInit;
+    use super::super::super::pop::repair::{Repair, NoRepair};
+    use super::super::super::pop::stats::Objective;
+    use super::{Policy, APolicy, LocalContext, PopInitPolicy, Alps, Params, AgingScheme};
+
+    struct IndivManager;
+    impl IndividualManager for IndivManager {
+        type I = i64;
+        type FI = i64;
+        type E = ();
+
+        fn generate(&mut self, _index: usize) -> Result<Self::I, Self::E> {
+            Ok(rand::thread_rng().gen_range(-100, 100))
+        }
+
+        fn fitness(&mut self, indiv: &Self::I) -> Result<Self::FI, Self::E> {
+            Ok((*indiv - 300).abs())
+        }
+    }
+
+    struct BreedAndShift;
+    impl BreedManager for BreedAndShift {
+        type I = i64;
+        type E = ();
+
+        fn breed(&mut self, parent_a: &Self::I, parent_b: &Self::I) -> Result<Self::I, Self::E> {
+            let mut rng = rand::thread_rng();
+            Ok(if rng.gen() { *parent_a } else { *parent_b } + rng.gen_range(-10, 11))
+        }
+    }
+
+    struct TestPolicy;
+    impl Policy for TestPolicy {
+        type Indiv = i64;
+        type Fit = i64;
+        type IndivME = ();
+        type IndivM = IndivManager;
+        type BreedME = ();
+        type BreedM = BreedAndShift;
+        type RepairME = ();
+        type RepairM = NoRepair<i64>;
+        type PopSE = set::vec::Error;
+        type PopS = Vec<i64>;
+        type PopSME = ();
+        type PopSM = set::vec::Manager<i64>;
+    }
+
+    struct TestAPolicy;
+    impl APolicy for TestAPolicy {
+        type P = TestPolicy;
+        type LCBuilder = fn() -> LocalContext<TestPolicy>;
+        type Exec = ParallelExecutor<LocalContext<TestPolicy>>;
+        type PopInit = LimitedPopulationInit<PopInitPolicy<TestAPolicy>>;
+        type InitWA = Alternately;
+        type EvalWA = Alternately;
+    }
+
+    fn make_local_context() -> LocalContext<TestPolicy> {
+        LocalContext::new(IndivManager, BreedAndShift, NoRepair::new(), set::vec::Manager::new())
+    }
+
+    fn better(a: &i64, b: &i64) -> bool {
+        a < b
+    }
+
+    fn value(fitness: &i64) -> f64 {
+        *fitness as f64
+    }
+
+    #[test]
+    fn age_limits() {
+        let limits = |scheme: AgingScheme| (0 .. 6).map(|layer| scheme.age_limit(5, layer)).collect::<Vec<_>>();
+        assert_eq!(limits(AgingScheme::Linear), vec![5, 10, 15, 20, 25, 30]);
+        assert_eq!(limits(AgingScheme::Fibonacci), vec![5, 10, 15, 25, 40, 65]);
+        assert_eq!(limits(AgingScheme::Exponential), vec![5, 10, 20, 40, 80, 160]);
+    }
+
+    #[test]
+    fn alps() {
+        let exec: ParallelExecutor<_> = Default::default();
+        let params = Params { layers: 4, layer_size: 32, age_gap: 5, scheme: AgingScheme::Fibonacci, elites: 2, generations: 300, repair: Repair::WriteBack, };
+        let alps: Alps<TestAPolicy> = Alps::new(make_local_context as fn() -> LocalContext<TestPolicy>, LimitedPopulationInit::new(32), params, better);
+        assert_eq!(alps.run(exec).unwrap(), (300, 0));
+
+        let exec: ParallelExecutor<_> = Default::default();
+        let alps: Alps<TestAPolicy> = Alps::new(make_local_context as fn() -> LocalContext<TestPolicy>, LimitedPopulationInit::new(32), params, better);
+        let (best, fitness, series) = alps.with_stats(value, Objective::Minimize).run(exec).unwrap();
+        assert_eq!((best, fitness), (300, 0));
+
+        assert_eq!(series.len(), 301);
+        assert_eq!(series.last().unwrap().best_so_far, 0.0);
+        assert!(series.windows(2).all(|pair| pair[1].best_so_far <= pair[0].best_so_far && pair[1].evaluations > pair[0].evaluations));
+        assert!(series.iter().all(|g| g.fitness.min <= g.fitness.median && g.fitness.median <= g.fitness.max));
+    }
+}
@@ -0,0 +1,400 @@
+use rand::{self, Rng};
+use par_exec::{Executor, LocalContextBuilder, WorkAmount, JobIterBuild, ExecutorNewError, ExecutorJobError, JobExecuteError};
+
+use super::Algorithm;
+use super::super::pop::individual::IndividualManager;
+use super::super::pop::neighbour::NeighbourManager;
+
+pub trait RetrieveIndividualManager {
+    type IM;
+
+    fn retrieve(&mut self) -> &mut Self::IM;
+}
+
+pub trait RetrieveNeighbourManager {
+    type NM;
+
+    fn retrieve(&mut self) -> &mut Self::NM;
+}
+
+// common policy: individual is a state and its fitness is an energy to minimize
+pub trait Policy {
+    type Indiv: Clone + Send + Sync + 'static;
+    type Fit: Copy + Into<f64> + Send + Sync + 'static;
+    type IndivME: Send + 'static;
+    type IndivM: IndividualManager<I = Self::Indiv, FI = Self::Fit, E = Self::IndivME>;
+    type NeighME: Send + 'static;
+    type NeighM: NeighbourManager<I = Self::Indiv, E = Self::NeighME>;
+}
+
+pub struct LocalContext<P> where P: Policy {
+    indiv_manager: P::IndivM,
+    neighbour_manager: P::NeighM,
+}
+
+impl<P> LocalContext<P> where P: Policy {
+    pub fn new(indiv_manager: P::IndivM, neighbour_manager: P::NeighM) -> LocalContext<P> {
+        LocalContext {
+            indiv_manager: indiv_manager,
+            neighbour_manager: neighbour_manager,
+        }
+    }
+}
+
+impl<P> RetrieveIndividualManager for LocalContext<P> where P: Policy {
+    type IM = P::IndivM;
+
+    fn retrieve(&mut self) -> &mut Self::IM {
+        &mut self.indiv_manager
+    }
+}
+
+impl<P> RetrieveNeighbourManager for LocalContext<P> where P: Policy {
+    type NM = P::NeighM;
+
+    fn retrieve(&mut self) -> &mut Self::NM {
+        &mut self.neighbour_manager
+    }
+}
+
+pub trait CoolingSchedule {
+    fn initial(&self) -> f64;
+    // invoked after each epoch with the share of accepted moves in it
+    fn next(&mut self, epoch: usize, temperature: f64, acceptance_rate: f64) -> f64;
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Geometric {
+    pub initial: f64,
+    pub alpha: f64,
+}
+
+impl CoolingSchedule for Geometric {
+    fn initial(&self) -> f64 {
+        self.initial
+    }
+
+    fn next(&mut self, _epoch: usize, temperature: f64, _acceptance_rate: f64) -> f64 {
+        temperature * self.alpha
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Linear {
+    pub initial: f64,
+    pub delta: f64,
+    pub min: f64,
+}
+
+impl CoolingSchedule for Linear {
+    fn initial(&self) -> f64 {
+        self.initial
+    }
+
+    fn next(&mut self, _epoch: usize, temperature: f64, _acceptance_rate: f64) -> f64 {
+        let next = temperature - self.delta;
+        if next < self.min { self.min } else { next }
+    }
+}
+
+// cools down while acceptance rate is above the target and heats up while it is below
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Adaptive {
+    pub initial: f64,
+    pub target_acceptance: f64,
+    pub alpha: f64,
+}
+
+impl CoolingSchedule for Adaptive {
+    fn initial(&self) -> f64 {
+        self.initial
+    }
+
+    fn next(&mut self, _epoch: usize, temperature: f64, acceptance_rate: f64) -> f64 {
+        if acceptance_rate > self.target_acceptance {
+            temperature * self.alpha
+        } else if acceptance_rate < self.target_acceptance {
+            temperature / self.alpha
+        } else {
+            temperature
+        }
+    }
+}
+
+// restarts the underlying schedule from `reheat * initial` temperature every `period` epochs
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Reheating<S> where S: CoolingSchedule {
+    pub schedule: S,
+    pub period: usize,
+    pub reheat: f64,
+}
+
+impl<S> CoolingSchedule for Reheating<S> where S: CoolingSchedule {
+    fn initial(&self) -> f64 {
+        self.schedule.initial()
+    }
+
+    fn next(&mut self, epoch: usize, temperature: f64, acceptance_rate: f64) -> f64 {
+        if self.period != 0 && (epoch + 1) % self.period == 0 {
+            self.schedule.initial() * self.reheat
+        } else {
+            self.schedule.next(epoch, temperature, acceptance_rate)
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum AnnealError<NME, IME> {
+    NeighbourManager(NME),
+    IndividualManager(IME),
+}
+
+pub type AnnealErrorP<P> where P: Policy = AnnealError<P::NeighME, P::IndivME>;
+
+#[derive(Clone)]
+pub struct Chain<I, FI> {
+    pub state: I,
+    pub energy: FI,
+    pub best_state: I,
+    pub best_energy: FI,
+}
+
+impl<I, FI> Chain<I, FI> where I: Clone, FI: Copy + Into<f64> {
+    pub fn new(state: I, energy: FI) -> Chain<I, FI> {
+        Chain {
+            best_state: state.clone(),
+            best_energy: energy,
+            state: state,
+            energy: energy,
+        }
+    }
+}
+
+pub fn start_chain<P, LC>(local_context: &mut LC, index: usize) -> Result<Chain<P::Indiv, P::Fit>, AnnealErrorP<P>> where
+    P: Policy,
+    LC: RetrieveIndividualManager<IM = P::IndivM>
+{
+    let indiv_manager = <LC as RetrieveIndividualManager>::retrieve(local_context);
+    let state = try!(indiv_manager.generate(index).map_err(AnnealError::IndividualManager));
+    let energy = try!(indiv_manager.fitness(&state).map_err(AnnealError::IndividualManager));
+    Ok(Chain::new(state, energy))
+}
+
+// performs one Metropolis move at the given temperature, returns true if the move is accepted
+pub fn metropolis<P, LC, R>(local_context: &mut LC, chain: &mut Chain<P::Indiv, P::Fit>, temperature: f64, attempt: usize, rng: &mut R) ->
+    Result<bool, AnnealErrorP<P>> where
+    P: Policy,
+    LC: RetrieveIndividualManager<IM = P::IndivM> + RetrieveNeighbourManager<NM = P::NeighM>,
+    R: Rng
+{
+    let candidate = {
+        let neighbour_manager = <LC as RetrieveNeighbourManager>::retrieve(local_context);
+        match try!(neighbour_manager.neighbour(&chain.state, attempt).map_err(AnnealError::NeighbourManager)) {
+            Some(candidate) => candidate,
+            None => return Ok(false),
+        }
+    };
+    let candidate_energy = {
+        let indiv_manager = <LC as RetrieveIndividualManager>::retrieve(local_context);
+        try!(indiv_manager.fitness(&candidate).map_err(AnnealError::IndividualManager))
+    };
+
+    let delta = candidate_energy.into() - chain.energy.into();
+    let accept = delta <= 0.0 || (temperature > 0.0 && rng.gen::<f64>() < (-delta / temperature).exp());
+    if accept {
+        if candidate_energy.into() < chain.best_energy.into() {
+            chain.best_state = candidate.clone();
+            chain.best_energy = candidate_energy;
+        }
+        chain.state = candidate;
+        chain.energy = candidate_energy;
+    }
+    Ok(accept)
+}
+
+// algorithm policy
+pub trait APolicy {
+    type P: Policy;
+    type LCBuilder: LocalContextBuilder<LC = LocalContext<Self::P>>;
+    type Exec: Executor<LC = LocalContext<Self::P>>;
+    type Schedule: CoolingSchedule + Clone + Send + Sync + 'static;
+    type ChainsWA: WorkAmount;
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Params {
+    // amount of independent chains run in parallel, chain `i` starts from `IndividualManager::generate(i)`
+    pub chains: usize,
+    pub epochs: usize,
+    pub epoch_steps: usize,
+}
+
+pub struct SimulatedAnnealing<AP> where AP: APolicy {
+    lc_builder: AP::LCBuilder,
+    schedule: AP::Schedule,
+    params: Params,
+}
+
+impl<AP> SimulatedAnnealing<AP> where AP: APolicy {
+    pub fn new(lc_builder: AP::LCBuilder, schedule: AP::Schedule, params: Params) -> SimulatedAnnealing<AP> {
+        SimulatedAnnealing {
+            lc_builder: lc_builder,
+            schedule: schedule,
+            params: params,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Error<ExecE, LCBE, NME, IME> {
+    ExecutorStart(ExecutorNewError<ExecE, LCBE>),
+    Anneal(ExecutorJobError<ExecE, JobExecuteError<AnnealError<NME, IME>, ()>>),
+    NoChains,
+}
+
+pub type ErrorAP<AP> where AP: APolicy = Error<
+    <AP::Exec as Executor>::E,
+    <AP::LCBuilder as LocalContextBuilder>::E,
+    <AP::P as Policy>::NeighME,
+    <AP::P as Policy>::IndivME>;
+
+impl<AP> Algorithm for SimulatedAnnealing<AP> where
+    AP: APolicy,
+    <AP::Exec as Executor>::JIB: JobIterBuild<AP::ChainsWA>
+{
+    type Exec = AP::Exec;
+    type Res = (<AP::P as Policy>::Indiv, <AP::P as Policy>::Fit);
+    type Err = ErrorAP<AP>;
+
+    fn run(self, not_started_executor: Self::Exec) -> Result<Self::Res, Self::Err> {
+        let mut executor =
+            try!(not_started_executor.try_start(self.lc_builder).map_err(Error::ExecutorStart));
+        let schedule = self.schedule;
+        let params = self.params;
+        match executor.try_execute_job(
+            AP::ChainsWA::new(params.chains),
+            move |local_context, input_indices| {
+                let mut rng = rand::thread_rng();
+                let mut best: Option<(<AP::P as Policy>::Indiv, <AP::P as Policy>::Fit)> = None;
+                for index in input_indices {
+                    let mut chain = try!(start_chain::<AP::P, _>(local_context, index));
+                    let mut schedule = schedule.clone();
+                    let mut temperature = schedule.initial();
+                    for epoch in 0 .. params.epochs {
+                        let mut accepted = 0;
+                        for step in 0 .. params.epoch_steps {
+                            if try!(metropolis::<AP::P, _, _>(local_context, &mut chain, temperature, epoch * params.epoch_steps + step, &mut rng)) {
+                                accepted += 1;
+                            }
+                        }
+                        let acceptance_rate = if params.epoch_steps == 0 { 0.0 } else { accepted as f64 / params.epoch_steps as f64 };
+                        temperature = schedule.next(epoch, temperature, acceptance_rate);
+                    }
+                    best = pick_best(best, Some((chain.best_state, chain.best_energy)));
+                }
+                Ok(best)
+            },
+            |_local_context, best_a, best_b| Ok(pick_best(best_a, best_b)))
+        {
+            Ok(Some(Some(best))) => Ok(best),
+            Ok(Some(None)) | Ok(None) => Err(Error::NoChains),
+            Err(e) => Err(Error::Anneal(e)),
+        }
+    }
+}
+
+pub fn pick_best<I, FI>(best_a: Option<(I, FI)>, best_b: Option<(I, FI)>) -> Option<(I, FI)> where FI: Copy + Into<f64> {
+    match (best_a, best_b) {
+        (None, None) => None,
+        (Some(a), None) => Some(a),
+        (None, Some(b)) => Some(b),
+        (Some(a), Some(b)) => if b.1.into() < a.1.into() { Some(b) } else { Some(a) },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{self, Rng};
+    use par_exec::par::{ParallelExecutor, Alternately};
+    use super::super::Algorithm;
+    use super::super::super::pop::individual::IndividualManager;
+    use super::super::super::pop::neighbour::NeighbourManager;
+    use super::{Policy, APolicy, LocalContext, SimulatedAnnealing, Params};
+    use super::{CoolingSchedule, Geometric, Linear, Adaptive, Reheating};
+
+    struct IndivManager;
+    impl IndividualManager for IndivManager {
+        type I = i64;
+        type FI = f64;
+        type E = ();
+
+        fn generate(&mut self, index: usize) -> Result<Self::I, Self::E> {
+            Ok(index as i64 * 100)
+        }
+
+        fn fitness(&mut self, indiv: &Self::I) -> Result<Self::FI, Self::E> {
+            Ok(((*indiv - 42) * (*indiv - 42)) as f64)
+        }
+    }
+
+    struct RandomStep;
+    impl NeighbourManager for RandomStep {
+        type I = i64;
+        type E = ();
+
+        fn neighbour(&mut self, indiv: &Self::I, _attempt: usize) -> Result<Option<Self::I>, Self::E> {
+            Ok(Some(*indiv + rand::thread_rng().gen_range(-3, 4)))
+        }
+    }
+
+    struct TestPolicy;
+    impl Policy for TestPolicy {
+        type Indiv = i64;
+        type Fit = f64;
+        type IndivME = ();
+        type IndivM = IndivManager;
+        type NeighME = ();
+        type NeighM = RandomStep;
+    }
+
+    struct TestAPolicy;
+    impl APolicy for TestAPolicy {
+        type P = TestPolicy;
+        type LCBuilder = fn() -> LocalContext<TestPolicy>;
+        type Exec = ParallelExecutor<LocalContext<TestPolicy>>;
+        type Schedule = Reheating<Geometric>;
+        type ChainsWA = Alternately;
+    }
+
+    fn make_local_context() -> LocalContext<TestPolicy> {
+        LocalContext::new(IndivManager, RandomStep)
+    }
+
+    #[test]
+    fn schedules() {
+        let mut geometric = Geometric { initial: 100.0, alpha: 0.5, };
+        assert_eq!(geometric.next(0, 100.0, 0.0), 50.0);
+        let mut linear = Linear { initial: 10.0, delta: 4.0, min: 1.0, };
+        assert_eq!(linear.next(0, 10.0, 0.0), 6.0);
+        assert_eq!(linear.next(1, 2.0, 0.0), 1.0);
+        let mut adaptive = Adaptive { initial: 10.0, target_acceptance: 0.5, alpha: 0.5, };
+        assert_eq!(adaptive.next(0, 10.0, 0.9), 5.0);
+        assert_eq!(adaptive.next(0, 10.0, 0.1), 20.0);
+        let mut reheating = Reheating { schedule: geometric, period: 3, reheat: 0.5, };
+        assert_eq!(reheating.next(0, 100.0, 0.0), 50.0);
+        assert_eq!(reheating.next(1, 50.0, 0.0), 25.0);
+        assert_eq!(reheating.next(2, 25.0, 0.0), 50.0);
+    }
+
+    #[test]
+    fn anneal() {
+        let exec: ParallelExecutor<_> = Default::default();
+        let annealing: SimulatedAnnealing<TestAPolicy> = SimulatedAnnealing::new(
+            make_local_context as fn() -> LocalContext<TestPolicy>,
+            Reheating { schedule: Geometric { initial: 1000.0, alpha: 0.9, }, period: 50, reheat: 0.1, },
+            Params { chains: 8, epochs: 200, epoch_steps: 50, });
+        let (best, energy) = annealing.run(exec).unwrap();
+        assert_eq!(best, 42);
+        assert_eq!(energy, 0.0);
+    }
+}
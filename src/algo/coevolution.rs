@@ -0,0 +1,396 @@
+use std::sync::Arc;
+use std::marker::PhantomData;
+use rand::{self, Rng};
+use par_exec::{Executor, WorkAmount, JobIterBuild, ExecutorJobError, JobExecuteError};
+
+use super::super::pop::interaction::InteractionManager;
+use super::super::set::{Set, SetManager};
+use super::super::set::union;
+
+pub trait RetrieveFitsManager {
+    type FitsM;
+
+    fn retrieve(&mut self) -> &mut Self::FitsM;
+}
+
+pub trait RetrieveInteractionManager {
+    type IM;
+
+    fn retrieve(&mut self) -> &mut Self::IM;
+}
+
+pub trait Policy {
+    type LocalContext: RetrieveFitsManager<FitsM = Self::FitsM> + RetrieveInteractionManager<IM = Self::InterM>;
+    type Exec: Executor<LC = Self::LocalContext>;
+
+    type Indiv;
+    type Fit;
+    type InterME: Send + 'static;
+    type InterM: InteractionManager<I = Self::Indiv, FI = Self::Fit, E = Self::InterME>;
+
+    type PopE: Send + 'static;
+    type Pop: Set<T = Self::Indiv, E = Self::PopE> + Sync + Send + 'static;
+
+    type FitsE: Send + 'static;
+    type Fits: Set<T = (Self::Fit, usize), E = Self::FitsE> + Send + 'static;
+    type FitsME: Send + 'static;
+    type FitsM: SetManager<S = Self::Fits, E = Self::FitsME>;
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Opponents {
+    // every individual of the other species
+    RoundRobin,
+    // given amount of individuals drawn uniformly from the other species
+    Sampled(usize),
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Interaction {
+    // an individual is combined with representatives of the other species into a complete solution,
+    // participants are ordered by species index with the individual as a focal one
+    Cooperative,
+    // an individual (focal participant 0) plays against opponents (participant 1) from the other species
+    Competitive(Opponents),
+}
+
+pub struct Coevolution<P> where P: Policy {
+    species: Vec<Arc<P::Pop>>,
+    representatives: Arc<Vec<Vec<usize>>>,
+    representatives_count: usize,
+    interaction: Interaction,
+    _marker: PhantomData<P>,
+}
+
+#[derive(Debug)]
+pub enum FitnessError<PE, FE, FME, IE> {
+    Population(PE),
+    FitsSet(FE),
+    FitsSetManager(FME),
+    InteractionManager(IE),
+    NoInteractions { species: usize, index: usize, },
+}
+
+#[derive(Debug)]
+pub enum Error<ExecE, PopE, FitsE, FitsME, InterME> {
+    NoSpecies,
+    NoOutputFitnessValues,
+    Executor(ExecutorJobError<ExecE, JobExecuteError<FitnessError<PopE, FitsE, FitsME, InterME>, union::Error<FitsE, FitsME>>>),
+}
+
+pub type ErrorP<P> where P: Policy = Error<<P::Exec as Executor>::E, P::PopE, P::FitsE, P::FitsME, P::InterME>;
+
+impl<P> Coevolution<P> where P: Policy {
+    // initially the first individual of each species serves as its representative
+    pub fn new(species: Vec<P::Pop>, interaction: Interaction, representatives_count: usize) -> Coevolution<P> {
+        let representatives = species.iter()
+            .map(|pop| if pop.size() == 0 { vec![] } else { vec![0] })
+            .collect();
+        Coevolution {
+            species: IntoIterator::into_iter(species).map(Arc::new).collect(),
+            representatives: Arc::new(representatives),
+            representatives_count: representatives_count,
+            interaction: interaction,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn species(&self) -> &[Arc<P::Pop>] {
+        &self.species
+    }
+
+    pub fn representatives(&self) -> &[Vec<usize>] {
+        &self.representatives
+    }
+
+    // replaces population of the given species (for example with its offspring), the representatives are reset
+    pub fn replace_species(&mut self, species_index: usize, population: P::Pop) {
+        let mut representatives = (*self.representatives).clone();
+        representatives[species_index] = if population.size() == 0 { vec![] } else { vec![0] };
+        self.representatives = Arc::new(representatives);
+        self.species[species_index] = Arc::new(population);
+    }
+
+    // computes fits for each species and picks its best individuals as representatives for the next evaluation
+    pub fn evaluate<WA, F>(&mut self, exec: &mut P::Exec, better: F) -> Result<Vec<P::Fits>, ErrorP<P>>
+        where WA: WorkAmount, <P::Exec as Executor>::JIB: JobIterBuild<WA>, F: Fn(&P::Fit, &P::Fit) -> bool
+    {
+        let fits = try!(self.fit::<WA>(exec));
+        let representatives = fits.iter()
+            .map(|species_fits| best_indices(species_fits, self.representatives_count, &better))
+            .collect();
+        self.representatives = Arc::new(representatives);
+        Ok(fits)
+    }
+
+    pub fn fit<WA>(&self, exec: &mut P::Exec) -> Result<Vec<P::Fits>, ErrorP<P>>
+        where WA: WorkAmount, <P::Exec as Executor>::JIB: JobIterBuild<WA>
+    {
+        let species_count = self.species.len();
+        if species_count == 0 {
+            return Err(Error::NoSpecies);
+        }
+        let mut offsets = Vec::with_capacity(species_count);
+        let mut total = 0;
+        for pop in self.species.iter() {
+            offsets.push(total);
+            total += pop.size();
+        }
+
+        let species = Arc::new(self.species.clone());
+        let representatives = self.representatives.clone();
+        let interaction = self.interaction;
+        match exec.try_execute_job(
+            WA::new(total),
+            move |local_context, input_indices| {
+                let mut fitness_results = Vec::with_capacity(species_count);
+                {
+                    let set_manager = <P::LocalContext as RetrieveFitsManager>::retrieve(local_context);
+                    for _ in 0 .. species_count {
+                        fitness_results.push(try!(set_manager.make_set(None).map_err(FitnessError::FitsSetManager)));
+                    }
+                }
+                let inter_manager = <P::LocalContext as RetrieveInteractionManager>::retrieve(local_context);
+                let mut rng = rand::thread_rng();
+                let mut participants = Vec::with_capacity(species_count);
+                for flat_index in input_indices {
+                    let species_index = offsets.iter().rposition(|&offset| offset <= flat_index).unwrap_or(0);
+                    let index = flat_index - offsets[species_index];
+                    let indiv = try!(species[species_index].get(index).map_err(FitnessError::Population));
+                    let mut total_fitness = None;
+                    match interaction {
+                        Interaction::Cooperative => {
+                            let combinations = representatives.iter()
+                                .enumerate()
+                                .filter(|&(s, _)| s != species_index)
+                                .map(|(_, reprs)| reprs.len())
+                                .max()
+                                .unwrap_or(1);
+                            for combination in 0 .. combinations {
+                                participants.clear();
+                                let mut focal = 0;
+                                for (s, pop) in species.iter().enumerate() {
+                                    if s == species_index {
+                                        focal = participants.len();
+                                        participants.push(indiv);
+                                    } else {
+                                        let reprs = &representatives[s];
+                                        if reprs.is_empty() {
+                                            continue;
+                                        }
+                                        participants.push(try!(pop.get(reprs[combination % reprs.len()]).map_err(FitnessError::Population)));
+                                    }
+                                }
+                                let outcome = try!(inter_manager.interact(focal, &participants).map_err(FitnessError::InteractionManager));
+                                total_fitness = Some(match total_fitness {
+                                    None => outcome,
+                                    Some(total) => try!(inter_manager.combine(total, outcome).map_err(FitnessError::InteractionManager)),
+                                });
+                            }
+                        },
+                        Interaction::Competitive(opponents) => {
+                            let opponents_total = total - species[species_index].size();
+                            let rounds = match opponents {
+                                Opponents::RoundRobin => opponents_total,
+                                Opponents::Sampled(_) if opponents_total == 0 => 0,
+                                Opponents::Sampled(count) => count,
+                            };
+                            for round in 0 .. rounds {
+                                // pick an opponent position among all the other species individuals
+                                let mut position = match opponents {
+                                    Opponents::RoundRobin => round,
+                                    Opponents::Sampled(_) => rng.gen_range(0, opponents_total),
+                                };
+                                let mut opponent = None;
+                                for (s, pop) in species.iter().enumerate() {
+                                    if s == species_index {
+                                        continue;
+                                    }
+                                    if position < pop.size() {
+                                        opponent = Some(try!(pop.get(position).map_err(FitnessError::Population)));
+                                        break;
+                                    }
+                                    position -= pop.size();
+                                }
+                                if let Some(opponent) = opponent {
+                                    participants.clear();
+                                    participants.push(indiv);
+                                    participants.push(opponent);
+                                    let outcome = try!(inter_manager.interact(0, &participants).map_err(FitnessError::InteractionManager));
+                                    total_fitness = Some(match total_fitness {
+                                        None => outcome,
+                                        Some(total) => try!(inter_manager.combine(total, outcome).map_err(FitnessError::InteractionManager)),
+                                    });
+                                }
+                            }
+                        },
+                    }
+                    match total_fitness {
+                        Some(fitness) =>
+                            try!(fitness_results[species_index].add((fitness, index)).map_err(FitnessError::FitsSet)),
+                        None =>
+                            return Err(FitnessError::NoInteractions { species: species_index, index: index, }),
+                    }
+                }
+                Ok(fitness_results)
+            },
+            move |local_context, fits_a, fits_b| {
+                let set_manager = <P::LocalContext as RetrieveFitsManager>::retrieve(local_context);
+                let mut fits = Vec::with_capacity(fits_a.len());
+                for (species_fits_a, species_fits_b) in IntoIterator::into_iter(fits_a).zip(IntoIterator::into_iter(fits_b)) {
+                    fits.push(try!(union::union(set_manager, species_fits_a, species_fits_b)));
+                }
+                Ok(fits)
+            })
+        {
+            Ok(None) => Err(Error::NoOutputFitnessValues),
+            Ok(Some(fitness_results)) => Ok(fitness_results),
+            Err(e) => Err(Error::Executor(e)),
+        }
+    }
+}
+
+fn best_indices<S, FI, F>(fits: &S, count: usize, better: &F) -> Vec<usize> where
+    S: Set<T = (FI, usize)>,
+    F: Fn(&FI, &FI) -> bool
+{
+    let mut best: Vec<(&FI, usize)> = Vec::with_capacity(count + 1);
+    for i in 0 .. fits.size() {
+        if let Ok(&(ref fitness, index)) = fits.get(i) {
+            let position = best.iter().position(|&(best_fitness, _)| better(fitness, best_fitness)).unwrap_or(best.len());
+            if position < count {
+                best.insert(position, (fitness, index));
+                best.truncate(count);
+            }
+        }
+    }
+    IntoIterator::into_iter(best).map(|(_, index)| index).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use par_exec::Executor;
+    use par_exec::par::{ParallelExecutor, Alternately};
+    use super::super::super::set;
+    use super::super::super::pop::interaction::InteractionManager;
+    use super::{Policy, Coevolution, Interaction, Opponents, RetrieveFitsManager, RetrieveInteractionManager};
+
+    struct InterManager {
+        competitive: bool,
+    }
+
+    impl InteractionManager for InterManager {
+        type I = i64;
+        type FI = i64;
+        type E = ();
+
+        fn interact(&mut self, focal: usize, participants: &[&Self::I]) -> Result<Self::FI, Self::E> {
+            Ok(if self.competitive {
+                // a win is scored when the focal individual is greater
+                if participants[focal] > participants[1 - focal] { 1 } else { 0 }
+            } else {
+                // solution is better the closer participants sum is to 10
+                let sum: i64 = participants.iter().map(|p| **p).sum();
+                (sum - 10).abs()
+            })
+        }
+
+        fn combine(&mut self, total: Self::FI, outcome: Self::FI) -> Result<Self::FI, Self::E> {
+            Ok(total + outcome)
+        }
+    }
+
+    struct LocalContext {
+        fits_manager: set::vec::Manager<(i64, usize)>,
+        inter_manager: InterManager,
+    }
+
+    impl RetrieveFitsManager for LocalContext {
+        type FitsM = set::vec::Manager<(i64, usize)>;
+
+        fn retrieve(&mut self) -> &mut Self::FitsM {
+            &mut self.fits_manager
+        }
+    }
+
+    impl RetrieveInteractionManager for LocalContext {
+        type IM = InterManager;
+
+        fn retrieve(&mut self) -> &mut Self::IM {
+            &mut self.inter_manager
+        }
+    }
+
+    struct TestPolicy;
+    impl Policy for TestPolicy {
+        type LocalContext = LocalContext;
+        type Exec = ParallelExecutor<LocalContext>;
+
+        type Indiv = i64;
+        type Fit = i64;
+        type InterME = ();
+        type InterM = InterManager;
+
+        type PopE = set::vec::Error;
+        type Pop = Vec<i64>;
+
+        type FitsE = set::vec::Error;
+        type Fits = Vec<(i64, usize)>;
+        type FitsME = ();
+        type FitsM = set::vec::Manager<(i64, usize)>;
+    }
+
+    fn make_executor(competitive: bool) -> ParallelExecutor<LocalContext> {
+        let exec: ParallelExecutor<_> = Default::default();
+        exec.start(move || LocalContext {
+            fits_manager: set::vec::Manager::new(),
+            inter_manager: InterManager { competitive: competitive, },
+        }).unwrap()
+    }
+
+    #[test]
+    fn cooperative() {
+        let mut exec = make_executor(false);
+        let mut coevolution: Coevolution<TestPolicy> =
+            Coevolution::new(vec![(0 .. 10).collect(), (0 .. 8).map(|v| v * 3).collect()], Interaction::Cooperative, 1);
+
+        let mut fits = coevolution.evaluate::<Alternately, _>(&mut exec, |a, b| a < b).unwrap();
+        assert_eq!(fits.len(), 2);
+        fits[0].sort_by_key(|v| v.1);
+        fits[1].sort_by_key(|v| v.1);
+        assert_eq!(fits[0], (0 .. 10).map(|i| ((i as i64 - 10).abs(), i)).collect::<Vec<_>>());
+        assert_eq!(fits[1], (0 .. 8).map(|i| ((i as i64 * 3 - 10).abs(), i)).collect::<Vec<_>>());
+        assert_eq!(coevolution.representatives(), &[vec![9], vec![3]]);
+
+        let mut fits = coevolution.evaluate::<Alternately, _>(&mut exec, |a, b| a < b).unwrap();
+        fits[0].sort_by_key(|v| v.1);
+        assert_eq!(fits[0], (0 .. 10).map(|i| ((i as i64 - 1).abs(), i)).collect::<Vec<_>>());
+        assert_eq!(coevolution.representatives(), &[vec![1], vec![0]]);
+    }
+
+    #[test]
+    fn competitive_round_robin() {
+        let mut exec = make_executor(true);
+        let coevolution: Coevolution<TestPolicy> =
+            Coevolution::new(vec![(0 .. 10).collect(), (0 .. 10).map(|v| v * 2).collect()], Interaction::Competitive(Opponents::RoundRobin), 1);
+
+        let mut fits = coevolution.fit::<Alternately>(&mut exec).unwrap();
+        fits[0].sort_by_key(|v| v.1);
+        fits[1].sort_by_key(|v| v.1);
+        // host i beats parasites 0, 2, .. below i
+        assert_eq!(fits[0], (0 .. 10).map(|i| (((i + 1) / 2) as i64, i)).collect::<Vec<_>>());
+        // parasite 2 * i beats all hosts below 2 * i
+        assert_eq!(fits[1], (0 .. 10).map(|i| (if 2 * i > 10 { 10 } else { 2 * i as i64 }, i)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn competitive_sampled() {
+        let mut exec = make_executor(true);
+        let coevolution: Coevolution<TestPolicy> =
+            Coevolution::new(vec![(0 .. 10).collect(), (100 .. 110).collect()], Interaction::Competitive(Opponents::Sampled(5)), 1);
+
+        let fits = coevolution.fit::<Alternately>(&mut exec).unwrap();
+        assert!(fits[0].iter().all(|&(wins, _)| wins == 0));
+        assert!(fits[1].iter().all(|&(wins, _)| wins == 5));
+    }
+}
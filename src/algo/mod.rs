@@ -1,6 +1,11 @@
 use par_exec::Executor;
 
 pub mod mu_comma_lambda;
+pub mod annealing;
+pub mod parallel_tempering;
+pub mod coevolution;
+pub mod alps;
+pub mod steady_state;
 
 pub trait Algorithm {
     type Exec: Executor;
@@ -4,10 +4,12 @@ use par_exec::{Executor, LocalContextBuilder, WorkAmount, JobIterBuild, Executor
 
 use super::Algorithm;
 use super::super::pop::individual::IndividualManager;
+use super::super::pop::repair::{Repair, RepairManager, RetrieveRepairManager};
 use super::super::pop::init::PopulationInit;
 use super::super::pop::init::limited;
 use super::super::pop::fit::PopulationFit;
 use super::super::pop::fit::standard;
+use super::super::pop::fit::cache::{CacheManager, RetrieveCacheManager};
 use super::super::set::{Set, SetManager};
 
 // common policy
@@ -16,6 +18,8 @@ pub trait Policy {
     type Indiv;
     type IndivME: Send + 'static;
     type IndivM: IndividualManager<I = Self::Indiv, FI = Self::Fit, E = Self::IndivME>;
+    type RepairME: Send + 'static;
+    type RepairM: RepairManager<I = Self::Indiv, E = Self::RepairME>;
 
     // population config
     type PopSE: Send + 'static;
@@ -24,17 +28,20 @@ pub trait Policy {
     type PopSM: SetManager<S = Self::PopS, E = Self::PopSME>;
 
     // fitness config
-    type Fit;
+    type Fit: Clone + Send + Sync + 'static;
     type FitsE: Send + 'static;
     type Fits: Set<T = (Self::Fit, usize), E = Self::FitsE> + Send + 'static;
     type FitsME: Send + 'static;
     type FitsM: SetManager<S = Self::Fits, E = Self::FitsME>;
+    type CacheM: CacheManager<I = Self::Indiv, FI = Self::Fit>;
 }
 
 pub struct LocalContext<P> where P: Policy {
     indiv_manager: P::IndivM,
+    repair_manager: P::RepairM,
     pop_set_manager: P::PopSM,
     fits_set_manager: P::FitsM,
+    cache_manager: P::CacheM,
 }
 
 impl<P> limited::RetrievePopulationManager for LocalContext<P> where P: Policy {
@@ -53,6 +60,14 @@ impl<P> limited::RetrieveIndividualManager for LocalContext<P> where P: Policy {
     }
 }
 
+impl<P> RetrieveRepairManager for LocalContext<P> where P: Policy {
+    type RM = P::RepairM;
+
+    fn retrieve(&mut self) -> &mut Self::RM {
+        &mut self.repair_manager
+    }
+}
+
 impl<P> standard::RetrieveFitsManager for LocalContext<P> where P: Policy {
     type FitsM = P::FitsM;
 
@@ -69,6 +84,14 @@ impl<P> standard::RetrieveIndividualManager for LocalContext<P> where P: Policy
     }
 }
 
+impl<P> RetrieveCacheManager for LocalContext<P> where P: Policy {
+    type CM = P::CacheM;
+
+    fn retrieve(&mut self) -> &mut Self::CM {
+        &mut self.cache_manager
+    }
+}
+
 // algorithm policy
 pub trait APolicy {
     type P: Policy;
@@ -86,6 +109,8 @@ impl<AP> limited::Policy for PopInitPolicy<AP> where AP: APolicy {
     type Indiv = <AP::P as Policy>::Indiv;
     type IndivME = <AP::P as Policy>::IndivME;
     type IndivM = <AP::P as Policy>::IndivM;
+    type RepairME = <AP::P as Policy>::RepairME;
+    type RepairM = <AP::P as Policy>::RepairM;
     type PopE = <AP::P as Policy>::PopSE;
     type Pop = <AP::P as Policy>::PopS;
     type PopSME = <AP::P as Policy>::PopSME;
@@ -100,12 +125,15 @@ impl<AP> standard::Policy for PopFitPolicy<AP> where AP: APolicy {
     type Fit = <AP::P as Policy>::Fit;
     type IndivME = <AP::P as Policy>::IndivME;
     type IndivM = <AP::P as Policy>::IndivM;
+    type RepairME = <AP::P as Policy>::RepairME;
+    type RepairM = <AP::P as Policy>::RepairM;
     type PopE = <AP::P as Policy>::PopSE;
     type Pop = <AP::P as Policy>::PopS;
     type FitsE = <AP::P as Policy>::FitsE;
     type Fits = <AP::P as Policy>::Fits;
     type FitsME = <AP::P as Policy>::FitsME;
     type FitsM = <AP::P as Policy>::FitsM;
+    type CacheM = <AP::P as Policy>::CacheM;
 }
 
 pub struct MuCommaLambda<AP> where AP: APolicy {
@@ -116,10 +144,14 @@ pub struct MuCommaLambda<AP> where AP: APolicy {
 
 impl<AP> MuCommaLambda<AP> where AP: APolicy {
     pub fn new(lc_builder: AP::LCBuilder, lambda: usize) -> MuCommaLambda<AP> {
+        MuCommaLambda::with_repair(lc_builder, lambda, Repair::WriteBack)
+    }
+
+    pub fn with_repair(lc_builder: AP::LCBuilder, lambda: usize, repair: Repair) -> MuCommaLambda<AP> {
         MuCommaLambda {
             lc_builder: lc_builder,
-            pop_init: limited::LimitedPopulationInit::new(lambda),
-            pop_fit: standard::StandardPopulationFit::new(),
+            pop_init: limited::LimitedPopulationInit::with_repair(lambda, repair),
+            pop_fit: standard::StandardPopulationFit::with_repair(repair),
         }
     }
 }
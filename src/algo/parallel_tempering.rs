@@ -0,0 +1,234 @@
+use std::sync::Arc;
+use rand::{self, Rng};
+use par_exec::{Executor, LocalContextBuilder, WorkAmount, JobIterBuild, ExecutorNewError, ExecutorJobError, JobExecuteError};
+
+use super::Algorithm;
+use super::annealing::{Policy, LocalContext, Chain, AnnealError, start_chain, metropolis, pick_best};
+
+// algorithm policy
+pub trait APolicy {
+    type P: Policy;
+    type LCBuilder: LocalContextBuilder<LC = LocalContext<Self::P>>;
+    type Exec: Executor<LC = LocalContext<Self::P>>;
+    type ReplicasWA: WorkAmount;
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Params {
+    pub sweeps: usize,
+    pub sweep_steps: usize,
+}
+
+// runs one replica per temperature, replica `i` starts from `IndividualManager::generate(i)`
+pub struct ParallelTempering<AP> where AP: APolicy {
+    lc_builder: AP::LCBuilder,
+    temperatures: Vec<f64>,
+    params: Params,
+}
+
+impl<AP> ParallelTempering<AP> where AP: APolicy {
+    pub fn new(lc_builder: AP::LCBuilder, temperatures: Vec<f64>, params: Params) -> ParallelTempering<AP> {
+        ParallelTempering {
+            lc_builder: lc_builder,
+            temperatures: temperatures,
+            params: params,
+        }
+    }
+}
+
+// temperatures spaced geometrically between `min` and `max` inclusive
+pub fn geometric_ladder(min: f64, max: f64, replicas: usize) -> Vec<f64> {
+    match replicas {
+        0 => vec![],
+        1 => vec![min],
+        _ => {
+            let ratio = (max / min).powf(1.0 / (replicas - 1) as f64);
+            (0 .. replicas).map(|i| min * ratio.powi(i as i32)).collect()
+        },
+    }
+}
+
+#[derive(Debug)]
+pub enum Error<ExecE, LCBE, NME, IME> {
+    ExecutorStart(ExecutorNewError<ExecE, LCBE>),
+    Init(ExecutorJobError<ExecE, JobExecuteError<AnnealError<NME, IME>, ()>>),
+    Sweep(ExecutorJobError<ExecE, JobExecuteError<AnnealError<NME, IME>, ()>>),
+    NoReplicas,
+    ReplicasLost { expected: usize, received: usize, },
+}
+
+pub type ErrorAP<AP> where AP: APolicy = Error<
+    <AP::Exec as Executor>::E,
+    <AP::LCBuilder as LocalContextBuilder>::E,
+    <AP::P as Policy>::NeighME,
+    <AP::P as Policy>::IndivME>;
+
+type Replicas<P> = Vec<(usize, Chain<<P as Policy>::Indiv, <P as Policy>::Fit>)>;
+
+fn collect_replicas<P>(maybe_replicas: Option<Replicas<P>>, expected: usize) -> Result<Vec<Chain<P::Indiv, P::Fit>>, (usize, usize)> where P: Policy {
+    let mut replicas = maybe_replicas.unwrap_or_else(Vec::new);
+    if replicas.len() != expected {
+        return Err((expected, replicas.len()));
+    }
+    replicas.sort_by_key(|r| r.0);
+    Ok(replicas.into_iter().map(|(_, chain)| chain).collect())
+}
+
+impl<AP> Algorithm for ParallelTempering<AP> where
+    AP: APolicy,
+    <AP::Exec as Executor>::JIB: JobIterBuild<AP::ReplicasWA>
+{
+    type Exec = AP::Exec;
+    type Res = (<AP::P as Policy>::Indiv, <AP::P as Policy>::Fit);
+    type Err = ErrorAP<AP>;
+
+    fn run(self, not_started_executor: Self::Exec) -> Result<Self::Res, Self::Err> {
+        let total = self.temperatures.len();
+        if total == 0 {
+            return Err(Error::NoReplicas);
+        }
+        let mut executor =
+            try!(not_started_executor.try_start(self.lc_builder).map_err(Error::ExecutorStart));
+
+        let init_replicas = try!(executor.try_execute_job(
+            AP::ReplicasWA::new(total),
+            move |local_context, input_indices| {
+                let mut replicas: Replicas<AP::P> = Vec::new();
+                for index in input_indices {
+                    replicas.push((index, try!(start_chain::<AP::P, _>(local_context, index))));
+                }
+                Ok(replicas)
+            },
+            |_local_context, mut replicas_a, replicas_b| { replicas_a.extend(replicas_b); Ok(replicas_a) })
+            .map_err(Error::Init));
+        let mut replicas = try!(collect_replicas::<AP::P>(init_replicas, total)
+                                .map_err(|(expected, received)| Error::ReplicasLost { expected: expected, received: received, }));
+
+        let temperatures = Arc::new(self.temperatures);
+        let params = self.params;
+        let mut rng = rand::thread_rng();
+        for sweep in 0 .. params.sweeps {
+            let current = Arc::new(replicas);
+            let current_replicas = current.clone();
+            let sweep_temperatures = temperatures.clone();
+            let sweep_replicas = try!(executor.try_execute_job(
+                AP::ReplicasWA::new(total),
+                move |local_context, input_indices| {
+                    let mut rng = rand::thread_rng();
+                    let mut replicas: Replicas<AP::P> = Vec::new();
+                    for index in input_indices {
+                        let mut chain = current_replicas[index].clone();
+                        for step in 0 .. params.sweep_steps {
+                            try!(metropolis::<AP::P, _, _>(local_context, &mut chain, sweep_temperatures[index], sweep * params.sweep_steps + step, &mut rng));
+                        }
+                        replicas.push((index, chain));
+                    }
+                    Ok(replicas)
+                },
+                |_local_context, mut replicas_a, replicas_b| { replicas_a.extend(replicas_b); Ok(replicas_a) })
+                .map_err(Error::Sweep));
+            drop(current);
+            replicas = try!(collect_replicas::<AP::P>(sweep_replicas, total)
+                            .map_err(|(expected, received)| Error::ReplicasLost { expected: expected, received: received, }));
+
+            // exchange states between neighbouring temperatures, alternating even and odd pairs
+            let mut i = sweep % 2;
+            while i + 1 < total {
+                let beta_delta = 1.0 / temperatures[i] - 1.0 / temperatures[i + 1];
+                let energy_delta = replicas[i].energy.into() - replicas[i + 1].energy.into();
+                let exponent = beta_delta * energy_delta;
+                if exponent >= 0.0 || rng.gen::<f64>() < exponent.exp() {
+                    let (lower, upper) = replicas.split_at_mut(i + 1);
+                    let (cold, hot) = (&mut lower[i], &mut upper[0]);
+                    ::std::mem::swap(&mut cold.state, &mut hot.state);
+                    ::std::mem::swap(&mut cold.energy, &mut hot.energy);
+                }
+                i += 2;
+            }
+        }
+
+        replicas.into_iter()
+            .fold(None, |best, chain| pick_best(best, Some((chain.best_state, chain.best_energy))))
+            .ok_or(Error::NoReplicas)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{self, Rng};
+    use par_exec::par::{ParallelExecutor, Alternately};
+    use super::super::Algorithm;
+    use super::super::annealing::{Policy, LocalContext};
+    use super::super::super::pop::individual::IndividualManager;
+    use super::super::super::pop::neighbour::NeighbourManager;
+    use super::{APolicy, ParallelTempering, Params, geometric_ladder};
+
+    struct IndivManager;
+    impl IndividualManager for IndivManager {
+        type I = i64;
+        type FI = f64;
+        type E = ();
+
+        fn generate(&mut self, index: usize) -> Result<Self::I, Self::E> {
+            Ok(index as i64 * -100)
+        }
+
+        fn fitness(&mut self, indiv: &Self::I) -> Result<Self::FI, Self::E> {
+            // two basins with the global minimum at 42
+            Ok(((*indiv - 42) * (*indiv + 42)).abs() as f64 / 100.0 + if *indiv > 0 { 0.0 } else { 0.5 })
+        }
+    }
+
+    struct RandomStep;
+    impl NeighbourManager for RandomStep {
+        type I = i64;
+        type E = ();
+
+        fn neighbour(&mut self, indiv: &Self::I, _attempt: usize) -> Result<Option<Self::I>, Self::E> {
+            Ok(Some(*indiv + rand::thread_rng().gen_range(-3, 4)))
+        }
+    }
+
+    struct TestPolicy;
+    impl Policy for TestPolicy {
+        type Indiv = i64;
+        type Fit = f64;
+        type IndivME = ();
+        type IndivM = IndivManager;
+        type NeighME = ();
+        type NeighM = RandomStep;
+    }
+
+    struct TestAPolicy;
+    impl APolicy for TestAPolicy {
+        type P = TestPolicy;
+        type LCBuilder = fn() -> LocalContext<TestPolicy>;
+        type Exec = ParallelExecutor<LocalContext<TestPolicy>>;
+        type ReplicasWA = Alternately;
+    }
+
+    fn make_local_context() -> LocalContext<TestPolicy> {
+        LocalContext::new(IndivManager, RandomStep)
+    }
+
+    #[test]
+    fn ladder() {
+        let ladder = geometric_ladder(1.0, 8.0, 4);
+        for (t, expected) in ladder.into_iter().zip(vec![1.0, 2.0, 4.0, 8.0]) {
+            assert!((t - expected).abs() < 1e-9);
+        }
+        assert_eq!(geometric_ladder(1.0, 8.0, 1), vec![1.0]);
+    }
+
+    #[test]
+    fn tempering() {
+        let exec: ParallelExecutor<_> = Default::default();
+        let tempering: ParallelTempering<TestAPolicy> = ParallelTempering::new(
+            make_local_context as fn() -> LocalContext<TestPolicy>,
+            geometric_ladder(0.1, 1000.0, 8),
+            Params { sweeps: 100, sweep_steps: 100, });
+        let (best, energy) = tempering.run(exec).unwrap();
+        assert_eq!(best, 42);
+        assert_eq!(energy, 0.0);
+    }
+}
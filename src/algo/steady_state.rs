@@ -0,0 +1,294 @@
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use rand::{self, Rng};
+use par_exec::{Executor, LocalContextBuilder, WorkAmount, JobIterBuild, ExecutorNewError, ExecutorJobError, JobExecuteError};
+
+use super::Algorithm;
+use super::super::pop::individual::IndividualManager;
+use super::super::pop::breed::BreedManager;
+use super::super::pop::repair::{Repair, RepairManager};
+
+// common policy
+pub trait Policy {
+    type Indiv: Clone + Send + Sync + 'static;
+    type Fit: Clone + Send + Sync + 'static;
+    type IndivME: Send + 'static;
+    type IndivM: IndividualManager<I = Self::Indiv, FI = Self::Fit, E = Self::IndivME>;
+    type BreedME: Send + 'static;
+    type BreedM: BreedManager<I = Self::Indiv, E = Self::BreedME>;
+    type RepairME: Send + 'static;
+    type RepairM: RepairManager<I = Self::Indiv, E = Self::RepairME>;
+}
+
+pub struct LocalContext<P> where P: Policy {
+    indiv_manager: P::IndivM,
+    breed_manager: P::BreedM,
+    repair_manager: P::RepairM,
+}
+
+impl<P> LocalContext<P> where P: Policy {
+    pub fn new(indiv_manager: P::IndivM, breed_manager: P::BreedM, repair_manager: P::RepairM) -> LocalContext<P> {
+        LocalContext {
+            indiv_manager: indiv_manager,
+            breed_manager: breed_manager,
+            repair_manager: repair_manager,
+        }
+    }
+}
+
+// algorithm policy
+pub trait APolicy {
+    type P: Policy;
+    type LCBuilder: LocalContextBuilder<LC = LocalContext<Self::P>>;
+    type Exec: Executor<LC = LocalContext<Self::P>>;
+    type SlotsWA: WorkAmount;
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Params {
+    pub population_size: usize,
+    // total amount of fitness evaluations including the initial population
+    pub evaluations: usize,
+    // work amount of the evaluation job: every executor worker given at least one slot runs a single
+    // evaluation loop, so this should be at least the amount of executor workers
+    pub slots: usize,
+    pub repair: Repair,
+}
+
+#[derive(Clone, Debug)]
+pub struct Member<I, FI> {
+    pub indiv: I,
+    pub fitness: FI,
+}
+
+// Asynchronous steady-state evolution: a single job runs one loop per executor worker, each of them repeatedly takes
+// an evaluation from the shared budget, generates (while the population is filling up) or breeds an
+// individual, evaluates it and immediately inserts it into the shared population replacing the worst
+// member. There is no generation barrier, so uneven evaluation times do not leave workers idle.
+pub struct SteadyState<AP> where AP: APolicy {
+    lc_builder: AP::LCBuilder,
+    params: Params,
+    better: fn(&<AP::P as Policy>::Fit, &<AP::P as Policy>::Fit) -> bool,
+}
+
+impl<AP> SteadyState<AP> where AP: APolicy {
+    pub fn new(lc_builder: AP::LCBuilder,
+               params: Params,
+               better: fn(&<AP::P as Policy>::Fit, &<AP::P as Policy>::Fit) -> bool)
+               -> SteadyState<AP>
+    {
+        SteadyState {
+            lc_builder: lc_builder,
+            params: params,
+            better: better,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum EvalError<IME, BME, RME> {
+    IndividualManager(IME),
+    BreedManager(BME),
+    RepairManager(RME),
+}
+
+#[derive(Debug)]
+pub enum Error<ExecE, LCBE, IndivME, BreedME, RepairME> {
+    ExecutorStart(ExecutorNewError<ExecE, LCBE>),
+    Eval(ExecutorJobError<ExecE, JobExecuteError<EvalError<IndivME, BreedME, RepairME>, ()>>),
+    EmptyPopulation,
+}
+
+pub type ErrorAP<AP> where AP: APolicy = Error<
+    <AP::Exec as Executor>::E,
+    <AP::LCBuilder as LocalContextBuilder>::E,
+    <AP::P as Policy>::IndivME,
+    <AP::P as Policy>::BreedME,
+    <AP::P as Policy>::RepairME>;
+
+// puts a member into the population replacing its worst individual when the population is full
+fn admit<I, FI>(members: &mut Vec<Member<I, FI>>, member: Member<I, FI>, population_size: usize, better: fn(&FI, &FI) -> bool) {
+    if members.len() < population_size {
+        members.push(member);
+        return;
+    }
+    let mut worst: Option<usize> = None;
+    for i in 0 .. members.len() {
+        worst = match worst {
+            Some(w) if !better(&members[w].fitness, &members[i].fitness) => Some(w),
+            _ => Some(i),
+        };
+    }
+    if let Some(w) = worst {
+        if better(&member.fitness, &members[w].fitness) {
+            members[w] = member;
+        }
+    }
+}
+
+impl<AP> Algorithm for SteadyState<AP> where
+    AP: APolicy,
+    <AP::Exec as Executor>::JIB: JobIterBuild<AP::SlotsWA>
+{
+    type Exec = AP::Exec;
+    type Res = (<AP::P as Policy>::Indiv, <AP::P as Policy>::Fit);
+    type Err = ErrorAP<AP>;
+
+    fn run(self, not_started_executor: Self::Exec) -> Result<Self::Res, Self::Err> {
+        let params = self.params;
+        let better = self.better;
+        if params.population_size == 0 {
+            return Err(Error::EmptyPopulation);
+        }
+        let mut executor =
+            try!(not_started_executor.try_start(self.lc_builder).map_err(Error::ExecutorStart));
+
+        let population: Arc<Mutex<Vec<Member<<AP::P as Policy>::Indiv, <AP::P as Policy>::Fit>>>> =
+            Arc::new(Mutex::new(Vec::with_capacity(params.population_size)));
+        let tickets = Arc::new(AtomicUsize::new(0));
+        let population_job = population.clone();
+        try!(executor.try_execute_job(
+            AP::SlotsWA::new(params.slots),
+            move |local_context, input_indices| {
+                if input_indices.count() == 0 {
+                    return Ok(());
+                }
+                let mut rng = rand::thread_rng();
+                loop {
+                    let ticket = tickets.fetch_add(1, Ordering::SeqCst);
+                    if ticket >= params.evaluations {
+                        break;
+                    }
+                    // tournament parents are copied out so that the lock is not held while breeding
+                    let parents = {
+                        let members = population_job.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                        if ticket < params.population_size || members.len() < 2 {
+                            None
+                        } else {
+                            let mut tournament = || {
+                                let (a, b) = (&members[rng.gen_range(0, members.len())], &members[rng.gen_range(0, members.len())]);
+                                if better(&b.fitness, &a.fitness) { b.indiv.clone() } else { a.indiv.clone() }
+                            };
+                            Some((tournament(), tournament()))
+                        }
+                    };
+                    let indiv = match parents {
+                        None =>
+                            try!(local_context.indiv_manager.generate(ticket).map_err(EvalError::IndividualManager)),
+                        Some((parent_a, parent_b)) =>
+                            try!(local_context.breed_manager.breed(&parent_a, &parent_b).map_err(EvalError::BreedManager)),
+                    };
+                    let indiv = try!(params.repair.produce(&mut local_context.repair_manager, indiv).map_err(EvalError::RepairManager));
+                    let repaired = try!(params.repair.evaluate(&mut local_context.repair_manager, &indiv).map_err(EvalError::RepairManager));
+                    let fitness = try!(local_context.indiv_manager.fitness(repaired.as_ref().unwrap_or(&indiv)).map_err(EvalError::IndividualManager));
+                    let mut members = population_job.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                    admit(&mut members, Member { indiv: indiv, fitness: fitness, }, params.population_size, better);
+                }
+                Ok(())
+            },
+            |_local_context, (), ()| Ok(()))
+            .map_err(Error::Eval));
+
+        let members = population.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut best: Option<&Member<<AP::P as Policy>::Indiv, <AP::P as Policy>::Fit>> = None;
+        for member in members.iter() {
+            best = match best {
+                Some(best_member) if !better(&member.fitness, &best_member.fitness) => Some(best_member),
+                _ => Some(member),
+            };
+        }
+        match best {
+            Some(member) => Ok((member.indiv.clone(), member.fitness.clone())),
+            None => Err(Error::EmptyPopulation),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::Duration;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use rand::{self, Rng};
+    use par_exec::par::{ParallelExecutor, Alternately};
+    use super::super::Algorithm;
+    use super::super::super::pop::individual::IndividualManager;
+    use super::super::super::pop::breed::BreedManager;
+    use super::super::super::pop::repair::{Repair, NoRepair};
+    use super::{Policy, APolicy, LocalContext, SteadyState, Params};
+
+    static EVALUATIONS: AtomicUsize = AtomicUsize::new(0);
+
+    struct IndivManager;
+    impl IndividualManager for IndivManager {
+        type I = i64;
+        type FI = i64;
+        type E = ();
+
+        fn generate(&mut self, _index: usize) -> Result<Self::I, Self::E> {
+            Ok(rand::thread_rng().gen_range(-100, 100))
+        }
+
+        // some evaluations are much more expensive than others
+        fn fitness(&mut self, indiv: &Self::I) -> Result<Self::FI, Self::E> {
+            EVALUATIONS.fetch_add(1, Ordering::SeqCst);
+            if *indiv % 16 == 0 {
+                thread::sleep(Duration::from_millis(2));
+            }
+            Ok((*indiv - 300).abs())
+        }
+    }
+
+    struct BreedAndShift;
+    impl BreedManager for BreedAndShift {
+        type I = i64;
+        type E = ();
+
+        fn breed(&mut self, parent_a: &Self::I, parent_b: &Self::I) -> Result<Self::I, Self::E> {
+            let mut rng = rand::thread_rng();
+            Ok(if rng.gen() { *parent_a } else { *parent_b } + rng.gen_range(-10, 11))
+        }
+    }
+
+    struct TestPolicy;
+    impl Policy for TestPolicy {
+        type Indiv = i64;
+        type Fit = i64;
+        type IndivME = ();
+        type IndivM = IndivManager;
+        type BreedME = ();
+        type BreedM = BreedAndShift;
+        type RepairME = ();
+        type RepairM = NoRepair<i64>;
+    }
+
+    struct TestAPolicy;
+    impl APolicy for TestAPolicy {
+        type P = TestPolicy;
+        type LCBuilder = fn() -> LocalContext<TestPolicy>;
+        type Exec = ParallelExecutor<LocalContext<TestPolicy>>;
+        type SlotsWA = Alternately;
+    }
+
+    fn make_local_context() -> LocalContext<TestPolicy> {
+        LocalContext::new(IndivManager, BreedAndShift, NoRepair::new())
+    }
+
+    fn better(a: &i64, b: &i64) -> bool {
+        a < b
+    }
+
+    #[test]
+    fn steady_state() {
+        let exec: ParallelExecutor<_> = Default::default();
+        let steady_state: SteadyState<TestAPolicy> = SteadyState::new(
+            make_local_context as fn() -> LocalContext<TestPolicy>,
+            Params { population_size: 32, evaluations: 4000, slots: 16, repair: Repair::WriteBack, },
+            better);
+        // individuals start within 100 of zero, so the optimum is at least 200 away
+        let (best, fitness) = steady_state.run(exec).unwrap();
+        assert_eq!(fitness, (best - 300).abs());
+        assert!(fitness < 50);
+        assert_eq!(EVALUATIONS.load(Ordering::SeqCst), 4000);
+    }
+}
@@ -0,0 +1,330 @@
+use std::io::{self, BufRead, BufReader};
+use std::fs::File;
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    MissingDimension,
+    UnsupportedEdgeWeightType(String),
+    UnsupportedEdgeWeightFormat(String),
+    InvalidNumber { line: usize, token: String, },
+    InvalidNode { line: usize, },
+    MissingData { expected: usize, received: usize, },
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum WeightType {
+    Explicit,
+    Euc2d,
+    Euc3d,
+    Max2d,
+    Man2d,
+    Ceil2d,
+    Geo,
+    Att,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum WeightFormat {
+    FullMatrix,
+    UpperRow,
+    LowerRow,
+    UpperDiagRow,
+    LowerDiagRow,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Section {
+    Header,
+    NodeCoords,
+    EdgeWeights,
+    Skip,
+}
+
+// symmetric TSP instance with precomputed distance matrix
+#[derive(Clone, Debug)]
+pub struct Tsp {
+    pub name: String,
+    pub dimension: usize,
+    distances: Vec<f64>,
+}
+
+impl Tsp {
+    pub fn distance(&self, city_a: usize, city_b: usize) -> f64 {
+        self.distances[city_a * self.dimension + city_b]
+    }
+
+    pub fn tour_length(&self, tour: &[usize]) -> f64 {
+        let len = tour.len();
+        (0 .. len).map(|i| self.distance(tour[i], tour[(i + 1) % len])).sum()
+    }
+}
+
+pub fn load<P>(path: P) -> Result<Tsp, Error> where P: AsRef<Path> {
+    let file = try!(File::open(path).map_err(Error::Io));
+    read(BufReader::new(file))
+}
+
+fn parse_number(line: usize, token: &str) -> Result<f64, Error> {
+    token.parse().map_err(|_| Error::InvalidNumber { line: line, token: token.to_string(), })
+}
+
+fn nint(value: f64) -> f64 {
+    (value + 0.5).floor()
+}
+
+// TSPLIB uses this exact value
+#[allow(clippy::approx_constant)]
+const PI: f64 = 3.141592;
+
+fn geo_radians(value: f64) -> f64 {
+    let degrees = value.trunc();
+    let minutes = value - degrees;
+    PI * (degrees + 5.0 * minutes / 3.0) / 180.0
+}
+
+fn coords_distance(weight_type: WeightType, a: &[f64], b: &[f64]) -> f64 {
+    let dx = a[0] - b[0];
+    let dy = a[1] - b[1];
+    match weight_type {
+        WeightType::Euc2d =>
+            nint((dx * dx + dy * dy).sqrt()),
+        WeightType::Euc3d => {
+            let dz = a.get(2).cloned().unwrap_or(0.0) - b.get(2).cloned().unwrap_or(0.0);
+            nint((dx * dx + dy * dy + dz * dz).sqrt())
+        },
+        WeightType::Max2d =>
+            nint(dx.abs().max(dy.abs())),
+        WeightType::Man2d =>
+            nint(dx.abs() + dy.abs()),
+        WeightType::Ceil2d =>
+            (dx * dx + dy * dy).sqrt().ceil(),
+        WeightType::Att => {
+            let r = ((dx * dx + dy * dy) / 10.0).sqrt();
+            let t = nint(r);
+            if t < r { t + 1.0 } else { t }
+        },
+        WeightType::Geo => {
+            const RRR: f64 = 6378.388;
+            let (lat_a, long_a) = (geo_radians(a[0]), geo_radians(a[1]));
+            let (lat_b, long_b) = (geo_radians(b[0]), geo_radians(b[1]));
+            let q1 = (long_a - long_b).cos();
+            let q2 = (lat_a - lat_b).cos();
+            let q3 = (lat_a + lat_b).cos();
+            (RRR * (0.5 * ((1.0 + q1) * q2 - (1.0 - q1) * q3)).acos() + 1.0).trunc()
+        },
+        WeightType::Explicit =>
+            0.0,
+    }
+}
+
+pub fn read<R>(reader: R) -> Result<Tsp, Error> where R: BufRead {
+    let mut name = String::new();
+    let mut dimension = None;
+    let mut weight_type = WeightType::Euc2d;
+    let mut weight_format = WeightFormat::FullMatrix;
+    let mut section = Section::Header;
+    let mut coords: Vec<Option<Vec<f64>>> = Vec::new();
+    let mut weights: Vec<f64> = Vec::new();
+
+    for (line_index, maybe_line) in reader.lines().enumerate() {
+        let line_no = line_index + 1;
+        let line = try!(maybe_line.map_err(Error::Io));
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if line == "EOF" {
+            break;
+        }
+
+        if line.ends_with("_SECTION") {
+            section = match line {
+                "NODE_COORD_SECTION" => {
+                    coords = vec![None; try!(dimension.ok_or(Error::MissingDimension))];
+                    Section::NodeCoords
+                },
+                "EDGE_WEIGHT_SECTION" =>
+                    Section::EdgeWeights,
+                _ =>
+                    Section::Skip,
+            };
+            continue;
+        }
+
+        if let Some(colon) = line.find(':') {
+            if section == Section::Header || !line[.. colon].trim().chars().all(|c| c.is_ascii_digit()) {
+                let key = line[.. colon].trim();
+                let value = line[colon + 1 ..].trim();
+                match key {
+                    "NAME" =>
+                        name = value.to_string(),
+                    "DIMENSION" =>
+                        dimension = Some(try!(parse_number(line_no, value)) as usize),
+                    "EDGE_WEIGHT_TYPE" => weight_type = match value {
+                        "EXPLICIT" => WeightType::Explicit,
+                        "EUC_2D" => WeightType::Euc2d,
+                        "EUC_3D" => WeightType::Euc3d,
+                        "MAX_2D" => WeightType::Max2d,
+                        "MAN_2D" => WeightType::Man2d,
+                        "CEIL_2D" => WeightType::Ceil2d,
+                        "GEO" => WeightType::Geo,
+                        "ATT" => WeightType::Att,
+                        other => return Err(Error::UnsupportedEdgeWeightType(other.to_string())),
+                    },
+                    // column-wise formats of a symmetric matrix coincide with the transposed row-wise ones
+                    "EDGE_WEIGHT_FORMAT" => weight_format = match value {
+                        "FULL_MATRIX" => WeightFormat::FullMatrix,
+                        "UPPER_ROW" | "LOWER_COL" => WeightFormat::UpperRow,
+                        "LOWER_ROW" | "UPPER_COL" => WeightFormat::LowerRow,
+                        "UPPER_DIAG_ROW" | "LOWER_DIAG_COL" => WeightFormat::UpperDiagRow,
+                        "LOWER_DIAG_ROW" | "UPPER_DIAG_COL" => WeightFormat::LowerDiagRow,
+                        other => return Err(Error::UnsupportedEdgeWeightFormat(other.to_string())),
+                    },
+                    _ =>
+                        (),
+                }
+                section = Section::Header;
+                continue;
+            }
+        }
+
+        match section {
+            Section::Header | Section::Skip =>
+                (),
+            Section::NodeCoords => {
+                let mut tokens = line.split_whitespace();
+                let node = try!(tokens.next().ok_or(Error::InvalidNode { line: line_no, }).and_then(|t| parse_number(line_no, t))) as usize;
+                if node == 0 || node > coords.len() {
+                    return Err(Error::InvalidNode { line: line_no, });
+                }
+                let mut values = Vec::with_capacity(3);
+                for token in tokens {
+                    values.push(try!(parse_number(line_no, token)));
+                }
+                if values.len() < 2 {
+                    return Err(Error::InvalidNode { line: line_no, });
+                }
+                coords[node - 1] = Some(values);
+            },
+            Section::EdgeWeights =>
+                for token in line.split_whitespace() {
+                    weights.push(try!(parse_number(line_no, token)));
+                },
+        }
+    }
+
+    let dimension = try!(dimension.ok_or(Error::MissingDimension));
+    let mut distances = vec![0.0; dimension * dimension];
+    if weight_type == WeightType::Explicit {
+        let mut cells = Vec::with_capacity(dimension * dimension);
+        for row in 0 .. dimension {
+            let columns = match weight_format {
+                WeightFormat::FullMatrix => (0, dimension),
+                WeightFormat::UpperRow => (row + 1, dimension),
+                WeightFormat::LowerRow => (0, row),
+                WeightFormat::UpperDiagRow => (row, dimension),
+                WeightFormat::LowerDiagRow => (0, row + 1),
+            };
+            for column in columns.0 .. columns.1 {
+                cells.push((row, column));
+            }
+        }
+        if weights.len() < cells.len() {
+            return Err(Error::MissingData { expected: cells.len(), received: weights.len(), });
+        }
+        for (&(row, column), &weight) in cells.iter().zip(weights.iter()) {
+            distances[row * dimension + column] = weight;
+            if weight_format != WeightFormat::FullMatrix {
+                distances[column * dimension + row] = weight;
+            }
+        }
+    } else {
+        let received = coords.iter().filter(|c| c.is_some()).count();
+        if coords.len() != dimension || received != dimension {
+            return Err(Error::MissingData { expected: dimension, received: received, });
+        }
+        let coords: Vec<Vec<f64>> = coords.into_iter().map(|c| c.unwrap_or_else(Vec::new)).collect();
+        for a in 0 .. dimension {
+            for b in 0 .. dimension {
+                if a != b {
+                    distances[a * dimension + b] = coords_distance(weight_type, &coords[a], &coords[b]);
+                }
+            }
+        }
+    }
+
+    Ok(Tsp {
+        name: name,
+        dimension: dimension,
+        distances: distances,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Error, read};
+
+    #[test]
+    fn euc_2d() {
+        let instance = "NAME : square4\n\
+                        TYPE : TSP\n\
+                        DIMENSION : 4\n\
+                        EDGE_WEIGHT_TYPE : EUC_2D\n\
+                        NODE_COORD_SECTION\n\
+                        1 0 0\n\
+                        2 3 0\n\
+                        3 3 4\n\
+                        4 0 4\n\
+                        EOF\n";
+        let tsp = read(instance.as_bytes()).unwrap();
+        assert_eq!(tsp.name, "square4");
+        assert_eq!(tsp.dimension, 4);
+        assert_eq!(tsp.distance(0, 2), 5.0);
+        assert_eq!(tsp.tour_length(&[0, 1, 2, 3]), 14.0);
+    }
+
+    #[test]
+    fn explicit_lower_diag_row() {
+        let instance = "NAME: tri3\n\
+                        TYPE: TSP\n\
+                        DIMENSION: 3\n\
+                        EDGE_WEIGHT_TYPE: EXPLICIT\n\
+                        EDGE_WEIGHT_FORMAT: LOWER_DIAG_ROW\n\
+                        EDGE_WEIGHT_SECTION\n\
+                        0 7\n\
+                        0 9 11 0\n\
+                        EOF\n";
+        let tsp = read(instance.as_bytes()).unwrap();
+        assert_eq!(tsp.distance(0, 1), 7.0);
+        assert_eq!(tsp.distance(2, 0), 9.0);
+        assert_eq!(tsp.distance(1, 2), 11.0);
+        assert_eq!(tsp.tour_length(&[0, 1, 2]), 27.0);
+    }
+
+    #[test]
+    fn geo() {
+        // first two cities of burma14, whose TSPLIB distance is 153
+        let instance = "NAME: burma2\n\
+                        TYPE: TSP\n\
+                        DIMENSION: 2\n\
+                        EDGE_WEIGHT_TYPE: GEO\n\
+                        NODE_COORD_SECTION\n\
+                        1 16.47 96.10\n\
+                        2 16.47 94.44\n\
+                        EOF\n";
+        let tsp = read(instance.as_bytes()).unwrap();
+        assert_eq!(tsp.distance(0, 1), 153.0);
+        assert_eq!(tsp.distance(1, 0), 153.0);
+    }
+
+    #[test]
+    fn missing_data() {
+        let instance = "DIMENSION : 3\nNODE_COORD_SECTION\n1 0 0\n2 1 1\nEOF\n";
+        match read(instance.as_bytes()) {
+            Err(Error::MissingData { expected: 3, received: 2, }) => (),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
@@ -1,8 +1,11 @@
 extern crate par_exec;
+extern crate rand;
 
 pub mod pop;
 pub mod set;
 pub mod algo;
+pub mod repr;
+pub mod bench;
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,7 @@
+
+pub trait BreedManager {
+    type I;
+    type E;
+
+    fn breed(&mut self, parent_a: &Self::I, parent_b: &Self::I) -> Result<Self::I, Self::E>;
+}
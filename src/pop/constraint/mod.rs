@@ -0,0 +1,329 @@
+use std::sync::Arc;
+use std::collections::VecDeque;
+use rand::Rng;
+
+use super::individual::IndividualManager;
+
+// objective value together with total constraint violation, `violation == 0` means feasible;
+// all handlers in this module assume that a lower objective is better
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Constrained<F> {
+    pub objective: F,
+    pub violation: f64,
+}
+
+impl<F> Constrained<F> {
+    pub fn feasible(objective: F) -> Constrained<F> {
+        Constrained {
+            objective: objective,
+            violation: 0.0,
+        }
+    }
+
+    // `inequalities` are satisfied when g(x) <= 0, `equalities` when |h(x)| <= tolerance
+    pub fn from_constraints(objective: F, inequalities: &[f64], equalities: &[f64], tolerance: f64) -> Constrained<F> {
+        let violation =
+            inequalities.iter().map(|&g| g.max(0.0)).sum::<f64>() +
+            equalities.iter().map(|&h| (h.abs() - tolerance).max(0.0)).sum::<f64>();
+        Constrained {
+            objective: objective,
+            violation: violation,
+        }
+    }
+
+    pub fn is_feasible(&self) -> bool {
+        self.violation <= 0.0
+    }
+}
+
+pub trait ConstraintManager {
+    type I;
+    type E;
+
+    // total violation of all constraints for `indiv`, zero for feasible individuals
+    fn violation(&mut self, indiv: &Self::I) -> Result<f64, Self::E>;
+}
+
+#[derive(PartialEq, Debug)]
+pub enum Error<IME, CME> {
+    IndividualManager(IME),
+    ConstraintManager(CME),
+}
+
+// individual manager adapter reporting `Constrained` fitness
+pub struct ConstrainedManager<IM, CM> {
+    indiv_manager: IM,
+    constraint_manager: CM,
+}
+
+impl<IM, CM> ConstrainedManager<IM, CM> where IM: IndividualManager, CM: ConstraintManager<I = IM::I> {
+    pub fn new(indiv_manager: IM, constraint_manager: CM) -> ConstrainedManager<IM, CM> {
+        ConstrainedManager {
+            indiv_manager: indiv_manager,
+            constraint_manager: constraint_manager,
+        }
+    }
+}
+
+impl<IM, CM> IndividualManager for ConstrainedManager<IM, CM> where IM: IndividualManager, CM: ConstraintManager<I = IM::I> {
+    type I = IM::I;
+    type FI = Constrained<IM::FI>;
+    type E = Error<IM::E, CM::E>;
+
+    fn generate(&mut self, index: usize) -> Result<Self::I, Self::E> {
+        self.indiv_manager.generate(index).map_err(Error::IndividualManager)
+    }
+
+    fn fitness(&mut self, indiv: &Self::I) -> Result<Self::FI, Self::E> {
+        let objective = try!(self.indiv_manager.fitness(indiv).map_err(Error::IndividualManager));
+        let violation = try!(self.constraint_manager.violation(indiv).map_err(Error::ConstraintManager));
+        Ok(Constrained {
+            objective: objective,
+            violation: violation,
+        })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Penalty {
+    // objective + coefficient * violation
+    Static { coefficient: f64, },
+    // objective + (coefficient * generation) ^ alpha * violation ^ beta
+    Dynamic { coefficient: f64, alpha: f64, beta: f64, },
+}
+
+impl Penalty {
+    pub fn penalized<F>(&self, value: &Constrained<F>, generation: usize) -> f64 where F: Copy + Into<f64> {
+        let objective = value.objective.into();
+        if value.is_feasible() {
+            return objective;
+        }
+        match *self {
+            Penalty::Static { coefficient, } =>
+                objective + coefficient * value.violation,
+            Penalty::Dynamic { coefficient, alpha, beta, } =>
+                objective + (coefficient * (generation + 1) as f64).powf(alpha) * value.violation.powf(beta),
+        }
+    }
+}
+
+// penalty coefficient adapted from the feasibility of the best individual over the last `window`
+// generations: relaxed by `beta_1` when it was always feasible, tightened by `beta_2` when it never was
+#[derive(Clone, PartialEq, Debug)]
+pub struct AdaptivePenalty {
+    coefficient: f64,
+    beta_1: f64,
+    beta_2: f64,
+    window: usize,
+    history: VecDeque<bool>,
+}
+
+impl AdaptivePenalty {
+    pub fn new(coefficient: f64, beta_1: f64, beta_2: f64, window: usize) -> AdaptivePenalty {
+        AdaptivePenalty {
+            coefficient: coefficient,
+            beta_1: beta_1,
+            beta_2: beta_2,
+            window: window,
+            history: VecDeque::with_capacity(window),
+        }
+    }
+
+    pub fn coefficient(&self) -> f64 {
+        self.coefficient
+    }
+
+    pub fn update(&mut self, best_feasible: bool) {
+        if self.history.len() == self.window {
+            self.history.pop_front();
+        }
+        self.history.push_back(best_feasible);
+        if self.history.len() == self.window {
+            if self.history.iter().all(|&feasible| feasible) {
+                self.coefficient /= self.beta_1;
+            } else if self.history.iter().all(|&feasible| !feasible) {
+                self.coefficient *= self.beta_2;
+            }
+        }
+    }
+
+    pub fn penalized<F>(&self, value: &Constrained<F>) -> f64 where F: Copy + Into<f64> {
+        Penalty::Static { coefficient: self.coefficient, }.penalized(value, 0)
+    }
+}
+
+// Deb's rules: feasible beats infeasible, two feasible compare objectives, two infeasible compare violations
+pub fn feasibility_rules<F>(value_a: &Constrained<F>, value_b: &Constrained<F>) -> bool where F: PartialOrd {
+    match (value_a.is_feasible(), value_b.is_feasible()) {
+        (true, true) => value_a.objective < value_b.objective,
+        (true, false) => true,
+        (false, true) => false,
+        (false, false) => value_a.violation < value_b.violation,
+    }
+}
+
+// ε-constrained comparison: violations up to `epsilon` are treated as feasible
+pub fn epsilon_constrained<F>(value_a: &Constrained<F>, value_b: &Constrained<F>, epsilon: f64) -> bool where F: PartialOrd {
+    if (value_a.violation <= epsilon && value_b.violation <= epsilon) || value_a.violation == value_b.violation {
+        value_a.objective < value_b.objective
+    } else {
+        value_a.violation < value_b.violation
+    }
+}
+
+// ε level schedule ε(t) = ε(0) * (1 - t / cutoff) ^ power, zero after `cutoff` generations
+pub fn epsilon_level(initial: f64, generation: usize, cutoff: usize, power: f64) -> f64 {
+    if generation >= cutoff {
+        0.0
+    } else {
+        initial * (1.0 - generation as f64 / cutoff as f64).powf(power)
+    }
+}
+
+// pairwise comparison suitable as `set::sort::sort` predicate
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Comparison {
+    Objective,
+    Penalty { penalty: Penalty, generation: usize, },
+    FeasibilityRules,
+    Epsilon { epsilon: f64, },
+}
+
+impl Comparison {
+    pub fn better<F>(&self, value_a: &Constrained<F>, value_b: &Constrained<F>) -> bool where F: Copy + PartialOrd + Into<f64> {
+        match *self {
+            Comparison::Objective =>
+                value_a.objective < value_b.objective,
+            Comparison::Penalty { penalty, generation, } =>
+                penalty.penalized(value_a, generation) < penalty.penalized(value_b, generation),
+            Comparison::FeasibilityRules =>
+                feasibility_rules(value_a, value_b),
+            Comparison::Epsilon { epsilon, } =>
+                epsilon_constrained(value_a, value_b, epsilon),
+        }
+    }
+
+    // index predicate over shared fitness values for `set::sort::sort`
+    pub fn sort_pred<F>(self, values: Arc<Vec<Constrained<F>>>) -> Box<dyn Fn(usize, usize) -> bool + Send + Sync>
+        where F: Copy + PartialOrd + Into<f64> + Send + Sync + 'static
+    {
+        Box::new(move |index_a, index_b| self.better(&values[index_a], &values[index_b]))
+    }
+}
+
+// Runarsson & Yao stochastic ranking: bubble sort sweeps where adjacent pairs are compared by objective
+// when both are feasible or with probability `pf`, otherwise by violation; returns indices best first
+pub fn stochastic_ranking<F, R>(values: &[Constrained<F>], pf: f64, sweeps: usize, rng: &mut R) -> Vec<usize>
+    where F: PartialOrd, R: Rng
+{
+    let mut ranking: Vec<usize> = (0 .. values.len()).collect();
+    for _ in 0 .. sweeps {
+        let mut swapped = false;
+        for j in 1 .. ranking.len() {
+            let (a, b) = (&values[ranking[j - 1]], &values[ranking[j]]);
+            let swap = if (a.is_feasible() && b.is_feasible()) || rng.gen::<f64>() < pf {
+                b.objective < a.objective
+            } else {
+                b.violation < a.violation
+            };
+            if swap {
+                ranking.swap(j - 1, j);
+                swapped = true;
+            }
+        }
+        if !swapped {
+            break;
+        }
+    }
+    ranking
+}
+
+// position of every index in `ranking`, so that `ranks[a] < ranks[b]` can serve as a sort predicate
+pub fn ranks(ranking: &[usize]) -> Vec<usize> {
+    let mut ranks = vec![0; ranking.len()];
+    for (rank, &index) in ranking.iter().enumerate() {
+        ranks[index] = rank;
+    }
+    ranks
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use rand::{self, Rng};
+    use par_exec::{Executor, WorkAmount};
+    use par_exec::par::{ParallelExecutor, ByEqualChunks};
+    use super::{Constrained, Comparison, Penalty, AdaptivePenalty};
+    use super::{feasibility_rules, epsilon_constrained, epsilon_level, stochastic_ranking};
+    use super::super::super::set::vec;
+    use super::super::super::set::sort::{RetrieveSortManager, RetrieveSetManager, sort};
+
+    #[test]
+    fn handlers() {
+        let feasible = Constrained::feasible(10.0);
+        let slightly = Constrained::from_constraints(5.0, &[0.5, -1.0], &[0.05], 0.1);
+        let badly = Constrained::from_constraints(1.0, &[3.0], &[], 0.0);
+        assert_eq!(slightly.violation, 0.5);
+        assert!(feasibility_rules(&feasible, &slightly) && feasibility_rules(&slightly, &badly));
+        assert!(epsilon_constrained(&slightly, &feasible, 1.0) && !epsilon_constrained(&slightly, &feasible, 0.1));
+        assert_eq!(Penalty::Static { coefficient: 4.0, }.penalized(&slightly, 0), 7.0);
+        assert_eq!(Penalty::Dynamic { coefficient: 0.5, alpha: 2.0, beta: 1.0, }.penalized(&badly, 3), 13.0);
+        assert_eq!(epsilon_level(2.0, 5, 10, 1.0), 1.0);
+        assert_eq!(epsilon_level(2.0, 10, 10, 1.0), 0.0);
+
+        let mut adaptive = AdaptivePenalty::new(1.0, 2.0, 4.0, 2);
+        adaptive.update(false);
+        assert_eq!(adaptive.coefficient(), 1.0);
+        adaptive.update(false);
+        assert_eq!(adaptive.coefficient(), 4.0);
+        adaptive.update(true);
+        assert_eq!(adaptive.coefficient(), 4.0);
+        adaptive.update(true);
+        assert_eq!(adaptive.coefficient(), 2.0);
+        assert_eq!(adaptive.penalized(&badly), 7.0);
+
+        let values = [slightly, badly, feasible, Constrained::feasible(3.0)];
+        assert_eq!(stochastic_ranking(&values, 0.0, values.len(), &mut rand::thread_rng()), vec![3, 2, 0, 1]);
+        assert_eq!(stochastic_ranking(&values, 1.0, values.len(), &mut rand::thread_rng()), vec![1, 3, 0, 2]);
+    }
+
+    struct LocalContext(vec::Manager<usize>);
+
+    impl RetrieveSortManager for LocalContext {
+        type SortM = vec::Manager<usize>;
+
+        fn retrieve(&mut self) -> &mut Self::SortM {
+            &mut self.0
+        }
+    }
+
+    impl RetrieveSetManager for LocalContext {
+        type SetM = vec::Manager<usize>;
+
+        fn retrieve(&mut self) -> &mut Self::SetM {
+            &mut self.0
+        }
+    }
+
+    #[test]
+    fn feasibility_aware_sort() {
+        let total = 4096;
+        let mut rng = rand::thread_rng();
+        let values: Arc<Vec<Constrained<f64>>> = Arc::new((0 .. total).map(|_| {
+            let violation = if rng.gen() { 0.0 } else { rng.gen::<f64>() };
+            Constrained { objective: rng.gen::<f64>(), violation: violation, }
+        }).collect());
+
+        let exec: ParallelExecutor<_> = Default::default();
+        let mut exec = exec.start(|| LocalContext(vec::Manager::new())).unwrap();
+        let sorted_indices = sort(
+            ByEqualChunks::new(total),
+            Comparison::FeasibilityRules.sort_pred(values.clone()),
+            &mut exec).unwrap();
+
+        assert_eq!(sorted_indices.len(), total);
+        for i in 1 .. total {
+            assert!(!feasibility_rules(&values[sorted_indices[i]], &values[sorted_indices[i - 1]]));
+        }
+    }
+}
@@ -0,0 +1,322 @@
+use std::sync::Arc;
+use std::hash::Hash;
+use std::collections::{HashMap, HashSet};
+use par_exec::{Executor, WorkAmount, JobIterBuild, ExecutorJobError, JobExecuteError};
+
+use super::init::PopulationInit;
+use super::niching::{DistanceManager, RetrieveDistanceManager};
+use super::super::set::{Set, SetManager};
+
+pub trait GeneManager {
+    type I;
+    type G: Hash + Eq + Clone + Send + 'static;
+    type E;
+
+    // discrete gene values of `indiv` used for entropy and uniqueness statistics
+    fn genes(&mut self, indiv: &Self::I) -> Result<Vec<Self::G>, Self::E>;
+}
+
+pub trait RetrieveGeneManager {
+    type GM;
+
+    fn retrieve(&mut self) -> &mut Self::GM;
+}
+
+pub trait Policy {
+    type LocalContext: RetrieveDistanceManager<DM = Self::DistM> + RetrieveGeneManager<GM = Self::GeneM>;
+    type Exec: Executor<LC = Self::LocalContext>;
+
+    type Indiv;
+    type PopE: Send + 'static;
+    type Pop: Set<T = Self::Indiv, E = Self::PopE> + Sync + Send + 'static;
+
+    type DistME: Send + 'static;
+    type DistM: DistanceManager<I = Self::Indiv, E = Self::DistME>;
+    type GeneME: Send + 'static;
+    type GeneM: GeneManager<I = Self::Indiv, E = Self::GeneME>;
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Diversity {
+    // genotypic: mean distance over all distinct pairs
+    pub mean_distance: f64,
+    // genotypic: Shannon entropy (natural log) of every gene position
+    pub gene_entropy: Vec<f64>,
+    pub mean_entropy: f64,
+    // phenotypic
+    pub fitness_variance: f64,
+    pub unique: usize,
+    pub size: usize,
+}
+
+#[derive(Debug)]
+pub enum MeasureError<PE, DME, GME> {
+    Population(PE),
+    DistanceManager(DME),
+    GeneManager(GME),
+}
+
+#[derive(Debug)]
+pub enum Error<ExecE, PopE, DistME, GeneME> {
+    NoOutputStatistics,
+    Executor(ExecutorJobError<ExecE, JobExecuteError<MeasureError<PopE, DistME, GeneME>, ()>>),
+}
+
+pub type ErrorP<P> where P: Policy = Error<<P::Exec as Executor>::E, P::PopE, P::DistME, P::GeneME>;
+
+struct Partial<G> where G: Hash + Eq {
+    distance_sum: f64,
+    gene_counts: Vec<HashMap<G, usize>>,
+    unique: HashSet<Vec<G>>,
+}
+
+fn merge_partials<G>(mut partial_a: Partial<G>, partial_b: Partial<G>) -> Partial<G> where G: Hash + Eq {
+    partial_a.distance_sum += partial_b.distance_sum;
+    if partial_a.gene_counts.len() < partial_b.gene_counts.len() {
+        let missing = partial_b.gene_counts.len() - partial_a.gene_counts.len();
+        partial_a.gene_counts.extend((0 .. missing).map(|_| HashMap::new()));
+    }
+    for (counts_a, counts_b) in partial_a.gene_counts.iter_mut().zip(partial_b.gene_counts) {
+        for (gene, count) in counts_b {
+            *counts_a.entry(gene).or_insert(0) += count;
+        }
+    }
+    partial_a.unique.extend(partial_b.unique);
+    partial_a
+}
+
+pub fn fitness_variance(fitness: &[f64]) -> f64 {
+    if fitness.is_empty() {
+        return 0.0;
+    }
+    let mean = fitness.iter().sum::<f64>() / fitness.len() as f64;
+    fitness.iter().map(|f| (f - mean) * (f - mean)).sum::<f64>() / fitness.len() as f64
+}
+
+// collects diversity statistics, every worker handles distances of its rows to all subsequent individuals
+pub fn measure<P, WA>(population: Arc<P::Pop>, fitness: &[f64], exec: &mut P::Exec) -> Result<Diversity, ErrorP<P>> where
+    P: Policy,
+    WA: WorkAmount,
+    <P::Exec as Executor>::JIB: JobIterBuild<WA>
+{
+    let size = population.size();
+    let fitness_variance = fitness_variance(fitness);
+    if size == 0 {
+        return Ok(Diversity {
+            mean_distance: 0.0,
+            gene_entropy: Vec::new(),
+            mean_entropy: 0.0,
+            fitness_variance: fitness_variance,
+            unique: 0,
+            size: 0,
+        });
+    }
+    let maybe_partial = exec.try_execute_job(
+        WA::new(size),
+        move |local_context, input_indices| {
+            let mut partial = Partial { distance_sum: 0.0, gene_counts: Vec::new(), unique: HashSet::new(), };
+            for row in input_indices {
+                let indiv = try!(population.get(row).map_err(MeasureError::Population));
+                {
+                    let distance_manager = <P::LocalContext as RetrieveDistanceManager>::retrieve(local_context);
+                    for column in row + 1 .. size {
+                        let other = try!(population.get(column).map_err(MeasureError::Population));
+                        partial.distance_sum += try!(distance_manager.distance(indiv, other).map_err(MeasureError::DistanceManager));
+                    }
+                }
+                let gene_manager = <P::LocalContext as RetrieveGeneManager>::retrieve(local_context);
+                let genes = try!(gene_manager.genes(indiv).map_err(MeasureError::GeneManager));
+                while partial.gene_counts.len() < genes.len() {
+                    partial.gene_counts.push(HashMap::new());
+                }
+                for (position, gene) in genes.iter().enumerate() {
+                    *partial.gene_counts[position].entry(gene.clone()).or_insert(0) += 1;
+                }
+                partial.unique.insert(genes);
+            }
+            Ok(partial)
+        },
+        |_local_context, partial_a, partial_b| Ok(merge_partials(partial_a, partial_b)));
+    let partial = match maybe_partial {
+        Ok(None) => return Err(Error::NoOutputStatistics),
+        Ok(Some(partial)) => partial,
+        Err(e) => return Err(Error::Executor(e)),
+    };
+
+    let pairs = size * (size - 1) / 2;
+    let gene_entropy: Vec<f64> = partial.gene_counts.iter().map(|counts| {
+        counts.values().map(|&count| {
+            let p = count as f64 / size as f64;
+            -p * p.ln()
+        }).sum::<f64>()
+    }).collect();
+    let mean_entropy = if gene_entropy.is_empty() { 0.0 } else { gene_entropy.iter().sum::<f64>() / gene_entropy.len() as f64 };
+    Ok(Diversity {
+        mean_distance: if pairs == 0 { 0.0 } else { partial.distance_sum / pairs as f64 },
+        gene_entropy: gene_entropy,
+        mean_entropy: mean_entropy,
+        fitness_variance: fitness_variance,
+        unique: partial.unique.len(),
+        size: size,
+    })
+}
+
+// diversity threshold below which a restart is due
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Criterion {
+    MeanDistance(f64),
+    MeanEntropy(f64),
+    FitnessVariance(f64),
+    // unique individuals to population size
+    UniqueRatio(f64),
+}
+
+impl Criterion {
+    pub fn restart_needed(&self, diversity: &Diversity) -> bool {
+        match *self {
+            Criterion::MeanDistance(threshold) => diversity.mean_distance < threshold,
+            Criterion::MeanEntropy(threshold) => diversity.mean_entropy < threshold,
+            Criterion::FitnessVariance(threshold) => diversity.fitness_variance < threshold,
+            Criterion::UniqueRatio(threshold) =>
+                diversity.size != 0 && (diversity.unique as f64 / diversity.size as f64) < threshold,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum RestartError<InitE, PopE, PopSME> {
+    PopulationInit(InitE),
+    Population(PopE),
+    PopulationSetManager(PopSME),
+}
+
+// partial re-initialisation: individuals at `survivors` indices are kept, the rest of the population
+// is replaced with the fresh individuals generated by `pop_init`
+pub fn partial_restart<PI, SM, WA>(population: PI::Pop, survivors: &[usize], pop_init: &PI, set_manager: &mut SM, exec: &mut PI::Exec) ->
+    Result<PI::Pop, RestartError<PI::Err, <PI::Pop as Set>::E, SM::E>> where
+    PI: PopulationInit,
+    SM: SetManager<S = PI::Pop>,
+    WA: WorkAmount,
+    <PI::Exec as Executor>::JIB: JobIterBuild<WA>
+{
+    let fresh = try!(pop_init.init::<WA>(exec).map_err(RestartError::PopulationInit));
+    let mut keep = vec![false; population.size()];
+    for &index in survivors {
+        if index < keep.len() {
+            keep[index] = true;
+        }
+    }
+    let mut restarted = try!(set_manager.make_set(Some(survivors.len() + fresh.size())).map_err(RestartError::PopulationSetManager));
+    for (index, maybe_indiv) in population.into_iter().enumerate() {
+        let indiv = try!(maybe_indiv.map_err(RestartError::Population));
+        if keep[index] {
+            try!(restarted.add(indiv).map_err(RestartError::Population));
+        }
+    }
+    for maybe_indiv in fresh.into_iter() {
+        try!(restarted.add(try!(maybe_indiv.map_err(RestartError::Population))).map_err(RestartError::Population));
+    }
+    Ok(restarted)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use par_exec::{Executor, WorkAmount, JobIterBuild};
+    use par_exec::par::{ParallelExecutor, Alternately};
+    use super::super::super::set;
+    use super::super::init::PopulationInit;
+    use super::super::niching::{DistanceManager, RetrieveDistanceManager};
+    use super::{Policy, GeneManager, RetrieveGeneManager, Criterion, measure, partial_restart};
+
+    struct Hamming;
+    impl DistanceManager for Hamming {
+        type I = Vec<u8>;
+        type E = ();
+
+        fn distance(&mut self, indiv_a: &Self::I, indiv_b: &Self::I) -> Result<f64, Self::E> {
+            Ok(indiv_a.iter().zip(indiv_b.iter()).filter(|&(a, b)| a != b).count() as f64)
+        }
+    }
+
+    struct Genes;
+    impl GeneManager for Genes {
+        type I = Vec<u8>;
+        type G = u8;
+        type E = ();
+
+        fn genes(&mut self, indiv: &Self::I) -> Result<Vec<Self::G>, Self::E> {
+            Ok(indiv.clone())
+        }
+    }
+
+    struct LocalContext(Hamming, Genes);
+
+    impl RetrieveDistanceManager for LocalContext {
+        type DM = Hamming;
+
+        fn retrieve(&mut self) -> &mut Self::DM {
+            &mut self.0
+        }
+    }
+
+    impl RetrieveGeneManager for LocalContext {
+        type GM = Genes;
+
+        fn retrieve(&mut self) -> &mut Self::GM {
+            &mut self.1
+        }
+    }
+
+    struct TestPolicy;
+    impl Policy for TestPolicy {
+        type LocalContext = LocalContext;
+        type Exec = ParallelExecutor<LocalContext>;
+        type Indiv = Vec<u8>;
+        type PopE = set::vec::Error;
+        type Pop = Vec<Vec<u8>>;
+        type DistME = ();
+        type DistM = Hamming;
+        type GeneME = ();
+        type GeneM = Genes;
+    }
+
+    struct FreshInit;
+    impl PopulationInit for FreshInit {
+        type Exec = ParallelExecutor<LocalContext>;
+        type Indiv = Vec<u8>;
+        type Pop = Vec<Vec<u8>>;
+        type Err = ();
+
+        fn init<WA>(&self, _exec: &mut Self::Exec) -> Result<Self::Pop, Self::Err>
+            where WA: WorkAmount, <Self::Exec as Executor>::JIB: JobIterBuild<WA>
+        {
+            Ok(vec![vec![7, 7], vec![8, 8]])
+        }
+    }
+
+    #[test]
+    fn statistics_and_restart() {
+        let exec: ParallelExecutor<_> = Default::default();
+        let mut exec = exec.start(|| LocalContext(Hamming, Genes)).unwrap();
+
+        let converged = Arc::new(vec![vec![1, 2], vec![1, 2], vec![1, 2], vec![1, 2]]);
+        let diversity = measure::<TestPolicy, Alternately>(converged.clone(), &[3.0, 3.0, 3.0, 3.0], &mut exec).unwrap();
+        assert_eq!((diversity.mean_distance, diversity.mean_entropy, diversity.fitness_variance, diversity.unique), (0.0, 0.0, 0.0, 1));
+        assert!(Criterion::UniqueRatio(0.5).restart_needed(&diversity));
+        assert!(Criterion::MeanDistance(0.1).restart_needed(&diversity));
+
+        let diverse = Arc::new(vec![vec![0, 5], vec![1, 5], vec![0, 5], vec![1, 6]]);
+        let diversity = measure::<TestPolicy, Alternately>(diverse, &[1.0, 3.0, 1.0, 3.0], &mut exec).unwrap();
+        assert_eq!(diversity.mean_distance, 7.0 / 6.0);
+        assert!((diversity.gene_entropy[0] - 2.0f64.ln()).abs() < 1e-12);
+        assert!((diversity.gene_entropy[1] - (0.75 * (4.0f64 / 3.0).ln() + 0.25 * 4.0f64.ln())).abs() < 1e-12);
+        assert_eq!(diversity.fitness_variance, 1.0);
+        assert_eq!(diversity.unique, 3);
+        assert!(!Criterion::MeanEntropy(0.5).restart_needed(&diversity));
+
+        let converged = Arc::try_unwrap(converged).unwrap();
+        let restarted = partial_restart::<_, _, Alternately>(converged, &[0, 2], &FreshInit, &mut set::vec::Manager::new(), &mut exec).unwrap();
+        assert_eq!(restarted, vec![vec![1, 2], vec![1, 2], vec![7, 7], vec![8, 8]]);
+    }
+}
@@ -0,0 +1,237 @@
+use std::cmp;
+use std::iter::Map;
+use super::super::set::{Set, SetManager, merge};
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Entry<I, FI> {
+    pub indiv: I,
+    pub fitness: FI,
+    // generation in which the individual was discovered
+    pub generation: usize,
+}
+
+// top-k distinct individuals ever seen, best first
+pub struct HallOfFame<SM, I, FI> where SM: SetManager, SM::S: Set<T = Entry<I, FI>> {
+    capacity: usize,
+    better: fn(&FI, &FI) -> bool,
+    set_manager: SM,
+    entries: SM::S,
+}
+
+pub type Error<SM> = merge::Error<<<SM as SetManager>::S as Set>::E, <SM as SetManager>::E>;
+
+#[derive(Debug)]
+pub enum UpdateError<PE, FE, HE> {
+    Population(PE),
+    Fits(FE),
+    HallOfFame(HE),
+}
+
+impl<SM, I, FI> HallOfFame<SM, I, FI> where SM: SetManager, SM::S: Set<T = Entry<I, FI>>, I: PartialEq {
+    pub fn new(capacity: usize, better: fn(&FI, &FI) -> bool, mut set_manager: SM) -> Result<HallOfFame<SM, I, FI>, Error<SM>> {
+        let entries = try!(set_manager.make_set(Some(capacity)).map_err(merge::Error::SetManager));
+        Ok(HallOfFame {
+            capacity: capacity,
+            better: better,
+            set_manager: set_manager,
+            entries: entries,
+        })
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn best(&self) -> Option<&Entry<I, FI>> {
+        self.entries.get(0).ok()
+    }
+
+    // merges best first `candidates` into the hall, keeping older entries on ties and dropping duplicates;
+    // the hall is left untouched when any step fails
+    fn absorb(&mut self, candidates: SM::S) -> Result<(), Error<SM>> where I: Clone, FI: Clone {
+        let better = self.better;
+        let mut current = try!(self.set_manager.make_set(Some(self.entries.size())).map_err(merge::Error::SetManager));
+        for index in 0 .. self.entries.size() {
+            let entry = try!(self.entries.get(index).map_err(merge::Error::Set));
+            try!(current.add(entry.clone()).map_err(merge::Error::Set));
+        }
+        let merged = try!(merge::merge(&mut self.set_manager, current, candidates, |a, b| !better(&b.fitness, &a.fitness)));
+        let mut kept: Vec<Entry<I, FI>> = Vec::with_capacity(self.capacity);
+        for maybe_entry in merged.into_iter() {
+            let entry = try!(maybe_entry.map_err(merge::Error::Set));
+            // a rediscovered individual keeps the generation it was first found in
+            if let Some(k) = kept.iter_mut().find(|k| k.indiv == entry.indiv) {
+                k.generation = cmp::min(k.generation, entry.generation);
+                continue;
+            }
+            if kept.len() < self.capacity {
+                kept.push(entry);
+            }
+        }
+        let mut entries = try!(self.set_manager.make_set(Some(self.capacity)).map_err(merge::Error::SetManager));
+        for entry in kept {
+            try!(entries.add(entry).map_err(merge::Error::Set));
+        }
+        self.entries = entries;
+        Ok(())
+    }
+
+    // updates the hall from the fits of a population (as produced by `StandardPopulationFit`)
+    pub fn update<P, F>(&mut self, population: &P, fits: &F, generation: usize) -> Result<(), UpdateError<P::E, F::E, Error<SM>>> where
+        P: Set<T = I>,
+        F: Set<T = (FI, usize)>,
+        I: Clone,
+        FI: Clone
+    {
+        let better = self.better;
+        let mut best: Vec<&(FI, usize)> = Vec::with_capacity(fits.size());
+        for index in 0 .. fits.size() {
+            best.push(try!(fits.get(index).map_err(UpdateError::Fits)));
+        }
+        sort_best_first(&mut best, |fit| &fit.0, better);
+
+        let mut candidates = try!(self.set_manager.make_set(Some(self.capacity)).map_err(|e| UpdateError::HallOfFame(merge::Error::SetManager(e))));
+        let mut added: Vec<&I> = Vec::with_capacity(self.capacity);
+        for &&(ref fitness, index) in best.iter() {
+            if added.len() >= self.capacity {
+                break;
+            }
+            let indiv = try!(population.get(index).map_err(UpdateError::Population));
+            if added.iter().any(|&a| a == indiv) {
+                continue;
+            }
+            added.push(indiv);
+            let entry = Entry { indiv: indiv.clone(), fitness: fitness.clone(), generation: generation, };
+            try!(candidates.add(entry).map_err(|e| UpdateError::HallOfFame(merge::Error::Set(e))));
+        }
+        self.absorb(candidates).map_err(UpdateError::HallOfFame)
+    }
+}
+
+fn sort_best_first<T, FI, K>(items: &mut Vec<T>, key: K, better: fn(&FI, &FI) -> bool) where K: Fn(&T) -> &FI {
+    use std::cmp::Ordering;
+    items.sort_by(|a, b| if better(key(a), key(b)) {
+        Ordering::Less
+    } else if better(key(b), key(a)) {
+        Ordering::Greater
+    } else {
+        Ordering::Equal
+    });
+}
+
+impl<SM, I, FI> Set for HallOfFame<SM, I, FI> where SM: SetManager, SM::S: Set<T = Entry<I, FI>>, I: PartialEq + Clone, FI: Clone {
+    type T = Entry<I, FI>;
+    type E = Error<SM>;
+    type I = Map<<SM::S as Set>::I, fn(Result<Entry<I, FI>, <SM::S as Set>::E>) -> Result<Entry<I, FI>, Error<SM>>>;
+
+    fn size(&self) -> usize {
+        self.entries.size()
+    }
+
+    fn get(&self, index: usize) -> Result<&Self::T, Self::E> {
+        self.entries.get(index).map_err(merge::Error::Set)
+    }
+
+    fn add(&mut self, item: Self::T) -> Result<(), Self::E> {
+        let mut candidates = try!(self.set_manager.make_set(Some(1)).map_err(merge::Error::SetManager));
+        try!(candidates.add(item).map_err(merge::Error::Set));
+        self.absorb(candidates)
+    }
+
+    fn into_iter(self) -> Self::I {
+        fn set_error<T, SE, SME>(item: Result<T, SE>) -> Result<T, merge::Error<SE, SME>> {
+            item.map_err(merge::Error::Set)
+        }
+        self.entries.into_iter().map(set_error)
+    }
+}
+
+// copies the best `count` individuals of the population unchanged into the next generation
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Elitism {
+    pub count: usize,
+}
+
+#[derive(Debug)]
+pub enum ElitismError<PE, FE, NE> {
+    Population(PE),
+    Fits(FE),
+    NextPopulation(NE),
+}
+
+impl Elitism {
+    pub fn new(count: usize) -> Elitism {
+        Elitism {
+            count: count,
+        }
+    }
+
+    // population indices of the elite, best first
+    pub fn select<F, FI>(&self, fits: &F, better: fn(&FI, &FI) -> bool) -> Result<Vec<usize>, F::E> where F: Set<T = (FI, usize)> {
+        let mut best: Vec<&(FI, usize)> = Vec::with_capacity(fits.size());
+        for index in 0 .. fits.size() {
+            best.push(try!(fits.get(index)));
+        }
+        sort_best_first(&mut best, |fit| &fit.0, better);
+        Ok(best.iter().take(self.count).map(|fit| fit.1).collect())
+    }
+
+    // adds elite individuals to `next`, returns the amount copied
+    pub fn carry_over<P, F, N, FI>(&self, population: &P, fits: &F, next: &mut N, better: fn(&FI, &FI) -> bool) ->
+        Result<usize, ElitismError<P::E, F::E, N::E>> where
+        P: Set,
+        P::T: Clone,
+        F: Set<T = (FI, usize)>,
+        N: Set<T = P::T>
+    {
+        let elite = try!(self.select(fits, better).map_err(ElitismError::Fits));
+        for &index in elite.iter() {
+            let indiv = try!(population.get(index).map_err(ElitismError::Population));
+            try!(next.add(indiv.clone()).map_err(ElitismError::NextPopulation));
+        }
+        Ok(elite.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::set::{self, Set};
+    use super::{Entry, HallOfFame, Elitism};
+
+    fn lower(a: &f64, b: &f64) -> bool {
+        a < b
+    }
+
+    #[test]
+    fn hall_of_fame() {
+        let mut hall = HallOfFame::new(3, lower, set::vec::Manager::new()).unwrap();
+        assert_eq!(hall.best(), None);
+
+        let population = vec!['a', 'b', 'a', 'c', 'd'];
+        let fits = vec![(4.0, 0), (5.0, 1), (4.0, 2), (1.0, 3), (9.0, 4)];
+        hall.update(&population, &fits, 0).unwrap();
+        assert_eq!(hall.size(), 3);
+        assert_eq!(hall.best(), Some(&Entry { indiv: 'c', fitness: 1.0, generation: 0, }));
+
+        let population = vec!['e', 'c', 'f'];
+        let fits = vec![(4.0, 0), (0.5, 1), (2.0, 2)];
+        hall.update(&population, &fits, 1).unwrap();
+        assert_eq!(hall.add(Entry { indiv: 'g', fitness: 2.0, generation: 2, }), Ok(()));
+        assert_eq!(hall.into_iter().map(|e| e.unwrap()).collect::<Vec<_>>(), vec![
+            Entry { indiv: 'c', fitness: 0.5, generation: 0, },
+            Entry { indiv: 'f', fitness: 2.0, generation: 1, },
+            Entry { indiv: 'g', fitness: 2.0, generation: 2, },
+        ]);
+    }
+
+    #[test]
+    fn elitism() {
+        let population = vec![10, 20, 30, 40];
+        let fits = vec![(3.0, 0), (1.0, 1), (4.0, 2), (2.0, 3)];
+        let elitism = Elitism::new(2);
+        assert_eq!(elitism.select(&fits, lower), Ok(vec![1, 3]));
+        let mut next = vec![99];
+        assert_eq!(elitism.carry_over(&population, &fits, &mut next, lower).unwrap(), 2);
+        assert_eq!(next, vec![99, 20, 40]);
+    }
+}
@@ -0,0 +1,350 @@
+use std::io::{self, Read, Write, BufRead, BufReader};
+use std::process::{Command, Stdio, Child, ChildStdin, ChildStdout};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use super::individual::{IndividualManager, CancelToken};
+
+// converts individuals into requests for the child process and its replies into fitness values
+pub trait Codec {
+    type I;
+    type FI;
+    type E;
+
+    fn encode(&mut self, indiv: &Self::I, request: &mut Vec<u8>) -> Result<(), Self::E>;
+    fn decode(&mut self, reply: &[u8]) -> Result<Self::FI, Self::E>;
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Framing {
+    // one message per line, trailing newline is not part of the message
+    Lines,
+    // every message is preceded by its length as a big endian u32
+    LengthPrefixed,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Executable {
+    pub program: String,
+    pub args: Vec<String>,
+}
+
+impl Executable {
+    pub fn new(program: &str, args: &[&str]) -> Executable {
+        Executable {
+            program: program.to_owned(),
+            args: args.iter().map(|&arg| arg.to_owned()).collect(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Error<IME, CE> {
+    IndividualManager(IME),
+    Codec(CE),
+    Spawn(io::Error),
+    NewlineInRequest,
+    MessageTooLong { len: usize, },
+    // the child kept failing after all the restarts, holds the last failure
+    Io(io::Error),
+    UnexpectedEof,
+}
+
+struct Running {
+    // shared with the watchdog killing the child on cancellation
+    child: Arc<Mutex<Child>>,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        let mut child = self.child.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+}
+
+enum ChannelError {
+    Io(io::Error),
+    UnexpectedEof,
+}
+
+impl From<io::Error> for ChannelError {
+    fn from(e: io::Error) -> ChannelError {
+        ChannelError::Io(e)
+    }
+}
+
+// individual manager evaluating fitness in a long-lived child process: keep one per worker local context;
+// generation is delegated to the wrapped manager
+pub struct ExternalManager<IM, C> {
+    indiv_manager: IM,
+    codec: C,
+    executable: Executable,
+    framing: Framing,
+    max_restarts: usize,
+    restarts: usize,
+    running: Option<Running>,
+    request: Vec<u8>,
+    reply: Vec<u8>,
+}
+
+impl<IM, C> ExternalManager<IM, C> where IM: IndividualManager, C: Codec<I = IM::I> {
+    pub fn new(indiv_manager: IM, codec: C, executable: Executable, framing: Framing) -> ExternalManager<IM, C> {
+        ExternalManager::with_restarts(indiv_manager, codec, executable, framing, 3)
+    }
+
+    pub fn with_restarts(indiv_manager: IM, codec: C, executable: Executable, framing: Framing, max_restarts: usize) -> ExternalManager<IM, C> {
+        ExternalManager {
+            indiv_manager: indiv_manager,
+            codec: codec,
+            executable: executable,
+            framing: framing,
+            max_restarts: max_restarts,
+            restarts: 0,
+            running: None,
+            request: Vec::new(),
+            reply: Vec::new(),
+        }
+    }
+
+    // total amount of child restarts caused by crashes
+    pub fn restarts(&self) -> usize {
+        self.restarts
+    }
+
+    fn spawn(&self) -> io::Result<Running> {
+        let mut child = try!(Command::new(&self.executable.program)
+                             .args(&self.executable.args)
+                             .stdin(Stdio::piped())
+                             .stdout(Stdio::piped())
+                             .spawn());
+        let stdin = child.stdin.take();
+        let stdout = child.stdout.take();
+        match (stdin, stdout) {
+            (Some(stdin), Some(stdout)) =>
+                Ok(Running { child: Arc::new(Mutex::new(child)), stdin: stdin, stdout: BufReader::new(stdout), }),
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                Err(io::Error::new(io::ErrorKind::BrokenPipe, "child process standard streams are not available"))
+            },
+        }
+    }
+
+    fn exchange(framing: Framing, running: &mut Running, request: &[u8], reply: &mut Vec<u8>) -> Result<(), ChannelError> {
+        reply.clear();
+        match framing {
+            Framing::Lines => {
+                try!(running.stdin.write_all(request));
+                try!(running.stdin.write_all(b"\n"));
+                try!(running.stdin.flush());
+                if try!(running.stdout.read_until(b'\n', reply)) == 0 || reply.last() != Some(&b'\n') {
+                    return Err(ChannelError::UnexpectedEof);
+                }
+                reply.pop();
+                if reply.last() == Some(&b'\r') {
+                    reply.pop();
+                }
+            },
+            Framing::LengthPrefixed => {
+                let len = request.len() as u32;
+                try!(running.stdin.write_all(&[(len >> 24) as u8, (len >> 16) as u8, (len >> 8) as u8, len as u8]));
+                try!(running.stdin.write_all(request));
+                try!(running.stdin.flush());
+                let mut header = [0; 4];
+                try!(read_exact(&mut running.stdout, &mut header));
+                let len = ((header[0] as usize) << 24) | ((header[1] as usize) << 16) | ((header[2] as usize) << 8) | header[3] as usize;
+                reply.resize(len, 0);
+                try!(read_exact(&mut running.stdout, reply));
+            },
+        }
+        Ok(())
+    }
+}
+
+fn read_exact<R>(reader: &mut R, buffer: &mut [u8]) -> Result<(), ChannelError> where R: Read {
+    let mut offset = 0;
+    while offset < buffer.len() {
+        match reader.read(&mut buffer[offset ..]) {
+            Ok(0) => return Err(ChannelError::UnexpectedEof),
+            Ok(amount) => offset += amount,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => (),
+            Err(e) => return Err(ChannelError::Io(e)),
+        }
+    }
+    Ok(())
+}
+
+impl<IM, C> IndividualManager for ExternalManager<IM, C> where IM: IndividualManager, C: Codec<I = IM::I> {
+    type I = IM::I;
+    type FI = C::FI;
+    type E = Error<IM::E, C::E>;
+
+    fn generate(&mut self, index: usize) -> Result<Self::I, Self::E> {
+        self.indiv_manager.generate(index).map_err(Error::IndividualManager)
+    }
+
+    fn fitness(&mut self, indiv: &Self::I) -> Result<Self::FI, Self::E> {
+        match try!(self.evaluate(indiv, None)) {
+            Some(fitness) => Ok(fitness),
+            None => unreachable!("evaluation without a cancel token can not be cancelled"),
+        }
+    }
+
+    // the child is killed as soon as the token is cancelled and a fresh one is started for the next individual;
+    // note that grandchildren keeping the output pipe open delay the cancellation until they exit
+    fn fitness_cancellable(&mut self, indiv: &Self::I, token: &CancelToken) -> Result<Option<Self::FI>, Self::E> {
+        self.evaluate(indiv, Some(token))
+    }
+}
+
+impl<IM, C> ExternalManager<IM, C> where IM: IndividualManager, C: Codec<I = IM::I> {
+    fn evaluate(&mut self, indiv: &IM::I, token: Option<&CancelToken>) -> Result<Option<C::FI>, Error<IM::E, C::E>> {
+        self.request.clear();
+        try!(self.codec.encode(indiv, &mut self.request).map_err(Error::Codec));
+        match self.framing {
+            Framing::Lines if self.request.contains(&b'\n') =>
+                return Err(Error::NewlineInRequest),
+            Framing::LengthPrefixed if self.request.len() > ::std::u32::MAX as usize =>
+                return Err(Error::MessageTooLong { len: self.request.len(), }),
+            _ => (),
+        }
+
+        let mut attempt = 0;
+        loop {
+            if self.running.is_none() {
+                self.running = Some(try!(self.spawn().map_err(Error::Spawn)));
+            }
+            let outcome = {
+                let running = self.running.as_mut().unwrap();
+                match token {
+                    None =>
+                        ExternalManager::<IM, C>::exchange(self.framing, running, &self.request, &mut self.reply),
+                    Some(token) => {
+                        let finished = Arc::new(AtomicBool::new(false));
+                        let watchdog = {
+                            let (finished, token, child) = (finished.clone(), token.clone(), running.child.clone());
+                            thread::spawn(move || while !finished.load(Ordering::SeqCst) {
+                                if token.is_cancelled() {
+                                    let _ = child.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).kill();
+                                    break;
+                                }
+                                thread::sleep(Duration::from_millis(5));
+                            })
+                        };
+                        let outcome = ExternalManager::<IM, C>::exchange(self.framing, running, &self.request, &mut self.reply);
+                        finished.store(true, Ordering::SeqCst);
+                        let _ = watchdog.join();
+                        outcome
+                    },
+                }
+            };
+            let failure = match outcome {
+                Ok(()) =>
+                    return self.codec.decode(&self.reply).map(Some).map_err(Error::Codec),
+                Err(_) if token.map(|token| token.is_cancelled()).unwrap_or(false) => {
+                    // killed by the watchdog: not a crash
+                    self.running = None;
+                    return Ok(None);
+                },
+                Err(ChannelError::Io(e)) => Error::Io(e),
+                Err(ChannelError::UnexpectedEof) => Error::UnexpectedEof,
+            };
+            // the child is considered crashed: replace it with a fresh one
+            self.running = None;
+            if attempt >= self.max_restarts {
+                return Err(failure);
+            }
+            attempt += 1;
+            self.restarts += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str;
+    use super::super::individual::IndividualManager;
+    use std::time::{Duration, Instant};
+    use super::super::individual::CancelToken;
+    use super::{Codec, Framing, Executable, ExternalManager, Error};
+
+    struct Generator;
+    impl IndividualManager for Generator {
+        type I = i64;
+        type FI = ();
+        type E = ();
+
+        fn generate(&mut self, index: usize) -> Result<Self::I, Self::E> {
+            Ok(index as i64)
+        }
+
+        fn fitness(&mut self, _indiv: &Self::I) -> Result<Self::FI, Self::E> {
+            Ok(())
+        }
+    }
+
+    struct Decimal;
+    impl Codec for Decimal {
+        type I = i64;
+        type FI = i64;
+        type E = ();
+
+        fn encode(&mut self, indiv: &Self::I, request: &mut Vec<u8>) -> Result<(), Self::E> {
+            request.extend_from_slice(indiv.to_string().as_bytes());
+            Ok(())
+        }
+
+        fn decode(&mut self, reply: &[u8]) -> Result<Self::FI, Self::E> {
+            str::from_utf8(reply).ok().and_then(|s| s.trim().parse().ok()).ok_or(())
+        }
+    }
+
+    #[test]
+    fn line_protocol_with_restarts() {
+        // answers a single request and exits, so every other call has to restart the child
+        let script = Executable::new("sh", &["-c", "read x; echo $((x * x))"]);
+        let mut manager = ExternalManager::new(Generator, Decimal, script, Framing::Lines);
+        for index in 0 .. 4 {
+            let indiv = manager.generate(index).unwrap();
+            assert_eq!(manager.fitness(&indiv).unwrap(), indiv * indiv);
+        }
+        assert_eq!(manager.restarts(), 3);
+
+        let broken = Executable::new("sh", &["-c", "exit 0"]);
+        let mut manager = ExternalManager::with_restarts(Generator, Decimal, broken, Framing::Lines, 2);
+        match manager.fitness(&1) {
+            Err(Error::UnexpectedEof) | Err(Error::Io(..)) => (),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert_eq!(manager.restarts(), 2);
+    }
+
+    #[test]
+    fn length_prefixed_protocol() {
+        // echoes the frames back, so the fitness is the individual itself
+        let mut manager = ExternalManager::new(Generator, Decimal, Executable::new("cat", &[]), Framing::LengthPrefixed);
+        for indiv in vec![7, -12, 123456789] {
+            assert_eq!(manager.fitness(&indiv).unwrap(), indiv);
+        }
+        assert_eq!(manager.restarts(), 0);
+    }
+
+    #[test]
+    fn killed_on_timeout() {
+        // answers immediately except for zero which hangs the child waiting for input
+        let script = Executable::new("sh", &["-c", "while read x; do if [ $x -eq 0 ]; then read never; fi; echo $x; done"]);
+        let mut manager = ExternalManager::new(Generator, Decimal, script, Framing::Lines);
+        let started = Instant::now();
+        let token = CancelToken::with_deadline(started + Duration::from_millis(100));
+        assert_eq!(manager.fitness_cancellable(&0, &token).unwrap(), None);
+        assert!(started.elapsed() < Duration::from_secs(5));
+        let token = CancelToken::with_deadline(Instant::now() + Duration::from_secs(5));
+        assert_eq!(manager.fitness_cancellable(&5, &token).unwrap(), Some(5));
+        assert_eq!(manager.restarts(), 0);
+    }
+}
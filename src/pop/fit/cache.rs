@@ -0,0 +1,180 @@
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+use std::marker::PhantomData;
+use std::collections::{HashMap, BTreeMap};
+
+pub trait CacheManager {
+    type I;
+    type FI;
+
+    // previously stored fitness of `indiv` if any
+    fn lookup(&mut self, indiv: &Self::I) -> Option<Self::FI>;
+    fn store(&mut self, indiv: &Self::I, fitness: &Self::FI);
+}
+
+pub trait RetrieveCacheManager {
+    type CM;
+
+    fn retrieve(&mut self) -> &mut Self::CM;
+}
+
+// cache manager that never hits
+pub struct NoCache<I, FI> {
+    _marker: PhantomData<(I, FI)>,
+}
+
+impl<I, FI> NoCache<I, FI> {
+    pub fn new() -> NoCache<I, FI> {
+        NoCache {
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<I, FI> CacheManager for NoCache<I, FI> {
+    type I = I;
+    type FI = FI;
+
+    fn lookup(&mut self, _indiv: &Self::I) -> Option<Self::FI> {
+        None
+    }
+
+    fn store(&mut self, _indiv: &Self::I, _fitness: &Self::FI) {}
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Stats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    pub entries: usize,
+}
+
+struct Lru<K, FI> where K: Hash + Eq {
+    capacity: usize,
+    clock: u64,
+    entries: HashMap<K, (FI, u64)>,
+    order: BTreeMap<u64, K>,
+    stats: Stats,
+}
+
+impl<K, FI> Lru<K, FI> where K: Hash + Eq + Clone, FI: Clone {
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    fn lookup(&mut self, key: &K) -> Option<FI> {
+        let stamp = self.tick();
+        match self.entries.get_mut(key) {
+            Some(&mut (ref fitness, ref mut used)) => {
+                self.order.remove(used);
+                *used = stamp;
+                self.order.insert(stamp, key.clone());
+                self.stats.hits += 1;
+                Some(fitness.clone())
+            },
+            None => {
+                self.stats.misses += 1;
+                None
+            },
+        }
+    }
+
+    fn store(&mut self, key: K, fitness: FI) {
+        if self.capacity == 0 {
+            return;
+        }
+        let stamp = self.tick();
+        if let Some((_, used)) = self.entries.insert(key.clone(), (fitness, stamp)) {
+            self.order.remove(&used);
+        }
+        self.order.insert(stamp, key);
+        while self.entries.len() > self.capacity {
+            let oldest = match self.order.keys().next() {
+                Some(&oldest) => oldest,
+                None => break,
+            };
+            if let Some(key) = self.order.remove(&oldest) {
+                self.entries.remove(&key);
+                self.stats.evictions += 1;
+            }
+        }
+        self.stats.entries = self.entries.len();
+    }
+}
+
+// bounded LRU fitness cache shared between all workers: every local context keeps a clone of the same cache;
+// individuals are identified by the user provided `key` function, so equal keys must mean equal fitness
+pub struct SharedCache<I, K, FI> where K: Hash + Eq {
+    key: fn(&I) -> K,
+    lru: Arc<Mutex<Lru<K, FI>>>,
+}
+
+impl<I, K, FI> Clone for SharedCache<I, K, FI> where K: Hash + Eq {
+    fn clone(&self) -> SharedCache<I, K, FI> {
+        SharedCache {
+            key: self.key,
+            lru: self.lru.clone(),
+        }
+    }
+}
+
+impl<I, K, FI> SharedCache<I, K, FI> where K: Hash + Eq + Clone, FI: Clone {
+    pub fn new(capacity: usize, key: fn(&I) -> K) -> SharedCache<I, K, FI> {
+        SharedCache {
+            key: key,
+            lru: Arc::new(Mutex::new(Lru {
+                capacity: capacity,
+                clock: 0,
+                entries: HashMap::new(),
+                order: BTreeMap::new(),
+                stats: Stats { hits: 0, misses: 0, evictions: 0, entries: 0, },
+            })),
+        }
+    }
+
+    pub fn stats(&self) -> Stats {
+        self.lru.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).stats
+    }
+}
+
+impl<I, K, FI> CacheManager for SharedCache<I, K, FI> where K: Hash + Eq + Clone, FI: Clone {
+    type I = I;
+    type FI = FI;
+
+    fn lookup(&mut self, indiv: &Self::I) -> Option<Self::FI> {
+        let key = (self.key)(indiv);
+        self.lru.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).lookup(&key)
+    }
+
+    fn store(&mut self, indiv: &Self::I, fitness: &Self::FI) {
+        let key = (self.key)(indiv);
+        self.lru.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).store(key, fitness.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CacheManager, SharedCache, Stats};
+
+    fn identity(indiv: &u32) -> u32 {
+        *indiv
+    }
+
+    #[test]
+    fn lru_eviction() {
+        let mut cache = SharedCache::new(2, identity);
+        let mut other_worker = cache.clone();
+        assert_eq!(cache.lookup(&1), None);
+        cache.store(&1, &10.0);
+        cache.store(&2, &20.0);
+        assert_eq!(other_worker.lookup(&1), Some(10.0));
+        // 2 is now least recently used
+        other_worker.store(&3, &30.0);
+        assert_eq!(cache.lookup(&2), None);
+        assert_eq!(cache.lookup(&1), Some(10.0));
+        assert_eq!(cache.lookup(&3), Some(30.0));
+        assert_eq!(cache.stats(), Stats { hits: 3, misses: 2, evictions: 1, entries: 2, });
+    }
+}
@@ -0,0 +1,676 @@
+use std::io::{self, Read, Write};
+use std::fmt::Debug;
+use std::thread;
+use std::time::Duration;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Sender, Receiver};
+use std::net::{TcpStream, ToSocketAddrs, Shutdown};
+use std::collections::{HashMap, VecDeque};
+use std::marker::PhantomData;
+use par_exec::{Executor, WorkAmount, JobIterBuild};
+
+use super::PopulationFit;
+use super::super::individual::IndividualManager;
+use super::super::super::set::{Set, SetManager};
+
+// serialization of individuals (master to worker) and fitness values (worker to master)
+pub trait WireCodec {
+    type I;
+    type FI;
+    type E;
+
+    fn encode_indiv(&mut self, indiv: &Self::I, buffer: &mut Vec<u8>) -> Result<(), Self::E>;
+    fn decode_indiv(&mut self, bytes: &[u8]) -> Result<Self::I, Self::E>;
+    fn encode_fitness(&mut self, fitness: &Self::FI, buffer: &mut Vec<u8>) -> Result<(), Self::E>;
+    fn decode_fitness(&mut self, bytes: &[u8]) -> Result<Self::FI, Self::E>;
+}
+
+// every frame is a big endian u32 length followed by a tag byte and the payload
+const TAG_BATCH: u8 = 1;
+const TAG_RESULTS: u8 = 2;
+const TAG_HEARTBEAT: u8 = 3;
+const TAG_SHUTDOWN: u8 = 4;
+const TAG_FAILED: u8 = 5;
+
+fn put_u32(buffer: &mut Vec<u8>, value: u32) {
+    buffer.extend_from_slice(&[(value >> 24) as u8, (value >> 16) as u8, (value >> 8) as u8, value as u8]);
+}
+
+fn put_u64(buffer: &mut Vec<u8>, value: u64) {
+    put_u32(buffer, (value >> 32) as u32);
+    put_u32(buffer, value as u32);
+}
+
+fn put_bytes(buffer: &mut Vec<u8>, bytes: &[u8]) {
+    put_u32(buffer, bytes.len() as u32);
+    buffer.extend_from_slice(bytes);
+}
+
+struct Cursor<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, amount: usize) -> Option<&'a [u8]> {
+        if self.bytes.len() < amount {
+            return None;
+        }
+        let (head, tail) = self.bytes.split_at(amount);
+        self.bytes = tail;
+        Some(head)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.take(4).map(|b| ((b[0] as u32) << 24) | ((b[1] as u32) << 16) | ((b[2] as u32) << 8) | b[3] as u32)
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        match (self.u32(), self.u32()) {
+            (Some(high), Some(low)) => Some(((high as u64) << 32) | low as u64),
+            _ => None,
+        }
+    }
+
+    fn bytes(&mut self) -> Option<&'a [u8]> {
+        self.u32().and_then(|len| self.take(len as usize))
+    }
+}
+
+// (index, payload) items of a batch or of its results
+fn put_items<'a, I>(buffer: &mut Vec<u8>, batch_id: u64, items: I) where I: ExactSizeIterator<Item = (usize, &'a [u8])> {
+    put_u64(buffer, batch_id);
+    put_u32(buffer, items.len() as u32);
+    for (index, payload) in items {
+        put_u64(buffer, index as u64);
+        put_bytes(buffer, payload);
+    }
+}
+
+fn parse_items(payload: &[u8]) -> Option<(u64, Vec<(usize, &[u8])>)> {
+    let mut cursor = Cursor { bytes: payload, };
+    let (batch_id, count) = match (cursor.u64(), cursor.u32()) {
+        (Some(batch_id), Some(count)) => (batch_id, count),
+        _ => return None,
+    };
+    let mut items = Vec::new();
+    for _ in 0 .. count {
+        match (cursor.u64(), cursor.bytes()) {
+            (Some(index), Some(bytes)) => items.push((index as usize, bytes)),
+            _ => return None,
+        }
+    }
+    Some((batch_id, items))
+}
+
+fn write_frame<W>(writer: &mut W, tag: u8, payload: &[u8]) -> io::Result<()> where W: Write {
+    let mut frame = Vec::with_capacity(payload.len() + 5);
+    put_u32(&mut frame, payload.len() as u32 + 1);
+    frame.push(tag);
+    frame.extend_from_slice(payload);
+    try!(writer.write_all(&frame));
+    writer.flush()
+}
+
+// `None` when the peer closed the connection between frames
+fn read_frame<R>(reader: &mut R) -> io::Result<Option<(u8, Vec<u8>)>> where R: Read {
+    let mut header = [0; 5];
+    let mut offset = 0;
+    while offset < header.len() {
+        match reader.read(&mut header[offset ..]) {
+            Ok(0) if offset == 0 => return Ok(None),
+            Ok(0) => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated frame header")),
+            Ok(amount) => offset += amount,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => (),
+            Err(e) => return Err(e),
+        }
+    }
+    let len = ((header[0] as usize) << 24) | ((header[1] as usize) << 16) | ((header[2] as usize) << 8) | header[3] as usize;
+    if len == 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "empty frame"));
+    }
+    let mut payload = vec![0; len - 1];
+    try!(reader.read_exact(&mut payload));
+    Ok(Some((header[4], payload)))
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Config {
+    // population indices per batch
+    pub batch_size: usize,
+    // back-pressure: batches a single worker may have outstanding
+    pub max_in_flight: usize,
+    // a worker silent for longer than this is considered lost
+    pub heartbeat_timeout: Duration,
+}
+
+#[derive(Debug)]
+pub enum Error<PopE, FitsE, FitsME, CE> {
+    Population(PopE),
+    FitsSet(FitsE),
+    FitsSetManager(FitsME),
+    Codec(CE),
+    NoWorkersLeft { pending: usize, },
+    WorkerFailure { index: usize, message: String, },
+}
+
+enum Event {
+    Results { worker: usize, batch_id: u64, items: Vec<(usize, Vec<u8>)>, },
+    Failed { worker: usize, batch_id: u64, index: usize, message: String, },
+    Lost { worker: usize, },
+}
+
+struct Connection {
+    stream: TcpStream,
+    alive: bool,
+    in_flight: Vec<u64>,
+}
+
+// master side: ships batches of encoded individuals to the connected workers and gathers their fitness values
+pub struct Coordinator {
+    config: Config,
+    connections: Vec<Connection>,
+    events_tx: Sender<Event>,
+    events_rx: Receiver<Event>,
+    next_batch_id: u64,
+    lost: usize,
+}
+
+impl Coordinator {
+    pub fn new(config: Config) -> Coordinator {
+        let (events_tx, events_rx) = channel();
+        Coordinator {
+            config: config,
+            connections: Vec::new(),
+            events_tx: events_tx,
+            events_rx: events_rx,
+            next_batch_id: 0,
+            lost: 0,
+        }
+    }
+
+    pub fn connect<A>(&mut self, address: A) -> io::Result<()> where A: ToSocketAddrs {
+        let stream = try!(TcpStream::connect(address));
+        self.add_worker(stream)
+    }
+
+    pub fn add_worker(&mut self, stream: TcpStream) -> io::Result<()> {
+        try!(stream.set_nodelay(true));
+        try!(stream.set_read_timeout(Some(self.config.heartbeat_timeout)));
+        let mut reader = try!(stream.try_clone());
+        let worker = self.connections.len();
+        let events_tx = self.events_tx.clone();
+        thread::spawn(move || {
+            loop {
+                let event = match read_frame(&mut reader) {
+                    Ok(Some((TAG_HEARTBEAT, _))) =>
+                        continue,
+                    Ok(Some((TAG_RESULTS, payload))) => match parse_frame_items(&payload) {
+                        Some((batch_id, items)) => Event::Results { worker: worker, batch_id: batch_id, items: items, },
+                        None => Event::Lost { worker: worker, },
+                    },
+                    Ok(Some((TAG_FAILED, payload))) => {
+                        let mut cursor = Cursor { bytes: &payload, };
+                        match (cursor.u64(), cursor.u64(), cursor.bytes()) {
+                            (Some(batch_id), Some(index), Some(message)) => Event::Failed {
+                                worker: worker,
+                                batch_id: batch_id,
+                                index: index as usize,
+                                message: String::from_utf8_lossy(message).into_owned(),
+                            },
+                            _ =>
+                                Event::Lost { worker: worker, },
+                        }
+                    },
+                    // timeout, disconnect or garbage
+                    _ => Event::Lost { worker: worker, },
+                };
+                let stop = match event { Event::Lost { .. } => true, _ => false, };
+                if events_tx.send(event).is_err() || stop {
+                    break;
+                }
+            }
+        });
+        self.connections.push(Connection { stream: stream, alive: true, in_flight: Vec::new(), });
+        Ok(())
+    }
+
+    pub fn live_workers(&self) -> usize {
+        self.connections.iter().filter(|c| c.alive).count()
+    }
+
+    // workers dropped so far because of disconnects, timeouts or protocol errors
+    pub fn lost_workers(&self) -> usize {
+        self.lost
+    }
+
+    fn lose(&mut self, worker: usize, queue: &mut VecDeque<u64>) {
+        let connection = &mut self.connections[worker];
+        if connection.alive {
+            connection.alive = false;
+            let _ = connection.stream.shutdown(Shutdown::Both);
+            self.lost += 1;
+        }
+        // re-dispatch everything the worker still owed
+        for batch_id in connection.in_flight.drain(..) {
+            queue.push_front(batch_id);
+        }
+    }
+
+    pub fn evaluate<P, C, FM>(&mut self, population: &P, codec: &mut C, fits_manager: &mut FM) ->
+        Result<FM::S, Error<P::E, <FM::S as Set>::E, FM::E, C::E>> where
+        P: Set<T = C::I>,
+        C: WireCodec,
+        FM: SetManager,
+        FM::S: Set<T = (C::FI, usize)>
+    {
+        let population_size = population.size();
+        let mut fits = try!(fits_manager.make_set(Some(population_size)).map_err(Error::FitsSetManager));
+        // batches left over by a previous failed evaluation are not waited for anymore
+        for connection in self.connections.iter_mut() {
+            connection.in_flight.clear();
+        }
+
+        let mut batches: HashMap<u64, Vec<u8>> = HashMap::new();
+        let mut queue = VecDeque::new();
+        let mut encoded = Vec::new();
+        let batch_size = ::std::cmp::max(self.config.batch_size, 1);
+        let mut start = 0;
+        while start < population_size {
+            let end = ::std::cmp::min(start + batch_size, population_size);
+            let mut items = Vec::with_capacity(end - start);
+            for index in start .. end {
+                encoded.clear();
+                let indiv = try!(population.get(index).map_err(Error::Population));
+                try!(codec.encode_indiv(indiv, &mut encoded).map_err(Error::Codec));
+                items.push((index, encoded.clone()));
+            }
+            let batch_id = self.next_batch_id;
+            self.next_batch_id += 1;
+            let mut payload = Vec::new();
+            put_items(&mut payload, batch_id, items.iter().map(|&(index, ref bytes)| (index, &bytes[..])));
+            batches.insert(batch_id, payload);
+            queue.push_back(batch_id);
+            start = end;
+        }
+
+        let max_in_flight = ::std::cmp::max(self.config.max_in_flight, 1);
+        while !batches.is_empty() {
+            for worker in 0 .. self.connections.len() {
+                while self.connections[worker].alive && self.connections[worker].in_flight.len() < max_in_flight {
+                    let batch_id = match queue.pop_front() {
+                        // completed meanwhile by the results of a lost worker
+                        Some(batch_id) if !batches.contains_key(&batch_id) => continue,
+                        Some(batch_id) => batch_id,
+                        None => break,
+                    };
+                    self.connections[worker].in_flight.push(batch_id);
+                    if write_frame(&mut self.connections[worker].stream, TAG_BATCH, &batches[&batch_id]).is_err() {
+                        self.lose(worker, &mut queue);
+                    }
+                }
+            }
+            if self.live_workers() == 0 {
+                return Err(Error::NoWorkersLeft { pending: batches.len(), });
+            }
+
+            // the coordinator keeps a sender itself, so the channel is never disconnected
+            match self.events_rx.recv().unwrap() {
+                Event::Results { worker, batch_id, items, } => {
+                    self.connections[worker].in_flight.retain(|&id| id != batch_id);
+                    // results of a batch which has already been completed elsewhere are ignored
+                    if batches.remove(&batch_id).is_some() {
+                        for (index, bytes) in items {
+                            let fitness = try!(codec.decode_fitness(&bytes).map_err(Error::Codec));
+                            try!(fits.add((fitness, index)).map_err(Error::FitsSet));
+                        }
+                    }
+                },
+                Event::Failed { worker, batch_id, index, message, } => {
+                    self.connections[worker].in_flight.retain(|&id| id != batch_id);
+                    // failures of batches from a previous evaluation are stale
+                    if batches.contains_key(&batch_id) {
+                        return Err(Error::WorkerFailure { index: index, message: message, });
+                    }
+                },
+                Event::Lost { worker, } =>
+                    self.lose(worker, &mut queue),
+            }
+        }
+        Ok(fits)
+    }
+}
+
+fn parse_frame_items(payload: &[u8]) -> Option<(u64, Vec<(usize, Vec<u8>)>)> {
+    parse_items(payload).map(|(batch_id, items)| (batch_id, IntoIterator::into_iter(items).map(|(index, bytes)| (index, bytes.to_vec())).collect()))
+}
+
+impl Drop for Coordinator {
+    fn drop(&mut self) {
+        for connection in self.connections.iter_mut().filter(|c| c.alive) {
+            let _ = write_frame(&mut connection.stream, TAG_SHUTDOWN, &[]);
+            let _ = connection.stream.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum WorkerError<CE> {
+    Io(io::Error),
+    MalformedFrame,
+    Codec(CE),
+}
+
+// worker side: evaluates the batches received from a coordinator with a local individual manager
+pub struct Worker<IM, C> {
+    indiv_manager: IM,
+    codec: C,
+    heartbeat: Duration,
+}
+
+impl<IM, C> Worker<IM, C> where IM: IndividualManager, IM::E: Debug, C: WireCodec<I = IM::I, FI = IM::FI> {
+    pub fn new(indiv_manager: IM, codec: C, heartbeat: Duration) -> Worker<IM, C> {
+        Worker {
+            indiv_manager: indiv_manager,
+            codec: codec,
+            heartbeat: heartbeat,
+        }
+    }
+
+    // serves a single coordinator connection until it shuts down or disconnects
+    pub fn serve(&mut self, mut stream: TcpStream) -> Result<(), WorkerError<C::E>> {
+        try!(stream.set_nodelay(true).map_err(WorkerError::Io));
+        let writer = Arc::new(Mutex::new(try!(stream.try_clone().map_err(WorkerError::Io))));
+        let stop = Arc::new(AtomicBool::new(false));
+        {
+            let (writer, stop, heartbeat) = (writer.clone(), stop.clone(), self.heartbeat);
+            thread::spawn(move || {
+                while !stop.load(Ordering::SeqCst) {
+                    thread::sleep(heartbeat);
+                    let mut stream = writer.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                    if stop.load(Ordering::SeqCst) || write_frame(&mut *stream, TAG_HEARTBEAT, &[]).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        let result = self.serve_frames(&mut stream, &writer);
+        stop.store(true, Ordering::SeqCst);
+        result
+    }
+
+    fn serve_frames(&mut self, stream: &mut TcpStream, writer: &Arc<Mutex<TcpStream>>) -> Result<(), WorkerError<C::E>> {
+        let mut encoded = Vec::new();
+        loop {
+            let payload = match try!(read_frame(stream).map_err(WorkerError::Io)) {
+                None | Some((TAG_SHUTDOWN, _)) => return Ok(()),
+                Some((TAG_BATCH, payload)) => payload,
+                Some(_) => return Err(WorkerError::MalformedFrame),
+            };
+            let (batch_id, items) = try!(parse_items(&payload).ok_or(WorkerError::MalformedFrame));
+            let mut results = Vec::with_capacity(items.len());
+            let mut failure = None;
+            for (index, bytes) in items {
+                let indiv = try!(self.codec.decode_indiv(bytes).map_err(WorkerError::Codec));
+                match self.indiv_manager.fitness(&indiv) {
+                    Ok(fitness) => {
+                        encoded.clear();
+                        try!(self.codec.encode_fitness(&fitness, &mut encoded).map_err(WorkerError::Codec));
+                        results.push((index, encoded.clone()));
+                    },
+                    Err(e) => {
+                        failure = Some((index, format!("{:?}", e)));
+                        break;
+                    },
+                }
+            }
+            let mut reply = Vec::new();
+            let tag = match failure {
+                Some((index, message)) => {
+                    put_u64(&mut reply, batch_id);
+                    put_u64(&mut reply, index as u64);
+                    put_bytes(&mut reply, message.as_bytes());
+                    TAG_FAILED
+                },
+                None => {
+                    put_items(&mut reply, batch_id, results.iter().map(|&(index, ref bytes)| (index, &bytes[..])));
+                    TAG_RESULTS
+                },
+            };
+            let mut stream = writer.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            try!(write_frame(&mut *stream, tag, &reply).map_err(WorkerError::Io));
+        }
+    }
+}
+
+pub trait Policy {
+    type Exec: Executor;
+
+    type Indiv;
+    type Fit;
+    type Codec: WireCodec<I = Self::Indiv, FI = Self::Fit>;
+
+    type PopE;
+    type Pop: Set<T = Self::Indiv, E = Self::PopE>;
+
+    type FitsE;
+    type Fits: Set<T = (Self::Fit, usize), E = Self::FitsE>;
+    type FitsME;
+    type FitsM: SetManager<S = Self::Fits, E = Self::FitsME>;
+}
+
+struct State<P> where P: Policy {
+    coordinator: Coordinator,
+    codec: P::Codec,
+    fits_manager: P::FitsM,
+}
+
+// population fit stage backed by remote workers: the local executor stays idle during evaluation
+pub struct DistributedPopulationFit<P> where P: Policy {
+    state: Mutex<State<P>>,
+    _marker: PhantomData<P>,
+}
+
+pub type ErrorP<P> where P: Policy = Error<P::PopE, P::FitsE, P::FitsME, <P::Codec as WireCodec>::E>;
+
+impl<P> DistributedPopulationFit<P> where P: Policy {
+    pub fn new(coordinator: Coordinator, codec: P::Codec, fits_manager: P::FitsM) -> DistributedPopulationFit<P> {
+        DistributedPopulationFit {
+            state: Mutex::new(State {
+                coordinator: coordinator,
+                codec: codec,
+                fits_manager: fits_manager,
+            }),
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn live_workers(&self) -> usize {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).coordinator.live_workers()
+    }
+
+    pub fn lost_workers(&self) -> usize {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).coordinator.lost_workers()
+    }
+}
+
+impl<P> PopulationFit for DistributedPopulationFit<P> where P: Policy {
+    type Exec = P::Exec;
+    type Indiv = P::Indiv;
+    type Pop = P::Pop;
+    type Fit = P::Fit;
+    type Fits = P::Fits;
+    type Err = ErrorP<P>;
+
+    fn fit<WA>(&self, population: Arc<Self::Pop>, _exec: &mut Self::Exec) -> Result<Self::Fits, Self::Err>
+        where WA: WorkAmount, <Self::Exec as Executor>::JIB: JobIterBuild<WA>
+    {
+        let mut state = self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let State { ref mut coordinator, ref mut codec, ref mut fits_manager, } = *state;
+        coordinator.evaluate(&*population, codec, fits_manager)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use std::net::{TcpListener, SocketAddr};
+    use par_exec::Executor;
+    use par_exec::par::{ParallelExecutor, Alternately};
+    use super::super::super::super::set;
+    use super::super::PopulationFit;
+    use super::super::super::individual::IndividualManager;
+    use super::{WireCodec, Config, Coordinator, Worker, Policy, DistributedPopulationFit, Error, read_frame};
+
+    struct Square;
+    impl IndividualManager for Square {
+        type I = i64;
+        type FI = i64;
+        type E = String;
+
+        fn generate(&mut self, index: usize) -> Result<Self::I, Self::E> {
+            Ok(index as i64)
+        }
+
+        fn fitness(&mut self, indiv: &Self::I) -> Result<Self::FI, Self::E> {
+            if *indiv < 0 {
+                Err("negative individual".to_owned())
+            } else {
+                Ok(indiv * indiv)
+            }
+        }
+    }
+
+    struct Bytes;
+    impl Bytes {
+        fn encode(value: i64, buffer: &mut Vec<u8>) -> Result<(), ()> {
+            super::put_u64(buffer, value as u64);
+            Ok(())
+        }
+
+        fn decode(bytes: &[u8]) -> Result<i64, ()> {
+            super::Cursor { bytes: bytes, }.u64().map(|value| value as i64).ok_or(())
+        }
+    }
+
+    impl WireCodec for Bytes {
+        type I = i64;
+        type FI = i64;
+        type E = ();
+
+        fn encode_indiv(&mut self, indiv: &Self::I, buffer: &mut Vec<u8>) -> Result<(), Self::E> {
+            Bytes::encode(*indiv, buffer)
+        }
+
+        fn decode_indiv(&mut self, bytes: &[u8]) -> Result<Self::I, Self::E> {
+            Bytes::decode(bytes)
+        }
+
+        fn encode_fitness(&mut self, fitness: &Self::FI, buffer: &mut Vec<u8>) -> Result<(), Self::E> {
+            Bytes::encode(*fitness, buffer)
+        }
+
+        fn decode_fitness(&mut self, bytes: &[u8]) -> Result<Self::FI, Self::E> {
+            Bytes::decode(bytes)
+        }
+    }
+
+    enum Behaviour {
+        Serve,
+        // drops the connection after receiving the first batch
+        Disconnect,
+        // reads batches but never answers nor sends heartbeats
+        Hang,
+    }
+
+    fn spawn_worker(behaviour: Behaviour) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            match behaviour {
+                Behaviour::Serve => {
+                    let _ = Worker::new(Square, Bytes, Duration::from_millis(20)).serve(stream);
+                },
+                Behaviour::Disconnect => {
+                    let _ = read_frame(&mut stream);
+                },
+                Behaviour::Hang => {
+                    while let Ok(Some(_)) = read_frame(&mut stream) {}
+                },
+            }
+        });
+        address
+    }
+
+    fn config() -> Config {
+        Config { batch_size: 7, max_in_flight: 2, heartbeat_timeout: Duration::from_millis(250), }
+    }
+
+    struct TestPolicy;
+    impl Policy for TestPolicy {
+        type Exec = ParallelExecutor<()>;
+
+        type Indiv = i64;
+        type Fit = i64;
+        type Codec = Bytes;
+
+        type PopE = set::vec::Error;
+        type Pop = Vec<i64>;
+
+        type FitsE = set::vec::Error;
+        type Fits = Vec<(i64, usize)>;
+        type FitsME = ();
+        type FitsM = set::vec::Manager<(i64, usize)>;
+    }
+
+    #[test]
+    fn redispatch_on_worker_loss() {
+        let mut coordinator = Coordinator::new(config());
+        for behaviour in vec![Behaviour::Disconnect, Behaviour::Serve, Behaviour::Hang, Behaviour::Serve] {
+            coordinator.connect(spawn_worker(behaviour)).unwrap();
+        }
+        let fitness_calculator: DistributedPopulationFit<TestPolicy> =
+            DistributedPopulationFit::new(coordinator, Bytes, set::vec::Manager::new());
+
+        let exec: ParallelExecutor<_> = Default::default();
+        let mut exec = exec.start(|| ()).unwrap();
+        for _ in 0 .. 2 {
+            let population = Arc::new((0 .. 100).collect::<Vec<i64>>());
+            let mut fit_results = fitness_calculator.fit::<Alternately>(population, &mut exec).unwrap();
+            fit_results.sort_by_key(|v| v.1);
+            assert_eq!(fit_results, (0 .. 100).map(|i| (i as i64 * i as i64, i)).collect::<Vec<_>>());
+        }
+        assert_eq!(fitness_calculator.live_workers(), 2);
+        assert_eq!(fitness_calculator.lost_workers(), 2);
+    }
+
+    #[test]
+    fn worker_failures() {
+        let mut coordinator = Coordinator::new(config());
+        coordinator.connect(spawn_worker(Behaviour::Serve)).unwrap();
+        let population = vec![1, 2, -3, 4];
+        match coordinator.evaluate(&population, &mut Bytes, &mut set::vec::Manager::new()) {
+            Err(Error::WorkerFailure { index: 2, ref message, }) if message.contains("negative") => (),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        // the failed batch does not keep occupying an in flight slot
+        let mut coordinator = Coordinator::new(Config { max_in_flight: 0, ..config() });
+        coordinator.connect(spawn_worker(Behaviour::Serve)).unwrap();
+        assert!(coordinator.evaluate(&population, &mut Bytes, &mut set::vec::Manager::new()).is_err());
+        for _ in 0 .. 2 {
+            let mut fit_results = coordinator.evaluate(&vec![1, 2, 3, 4], &mut Bytes, &mut set::vec::Manager::new()).unwrap();
+            fit_results.sort_by_key(|v| v.1);
+            assert_eq!(fit_results, vec![(1, 0), (4, 1), (9, 2), (16, 3)]);
+        }
+
+        let mut coordinator = Coordinator::new(config());
+        coordinator.connect(spawn_worker(Behaviour::Hang)).unwrap();
+        match coordinator.evaluate(&population, &mut Bytes, &mut set::vec::Manager::new()) {
+            Err(Error::NoWorkersLeft { pending: 1, }) => (),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,286 @@
+use std::cmp;
+use std::sync::Arc;
+use std::marker::PhantomData;
+use par_exec::{Executor, WorkAmount, JobIterBuild, ExecutorJobError, JobExecuteError};
+
+use super::PopulationFit;
+use super::standard::{RetrieveFitsManager, RetrieveIndividualManager};
+use super::super::individual::IndividualManager;
+use super::super::niching::FitsError;
+use super::super::super::set::{Set, SetManager};
+use super::super::super::set::union;
+
+// fitness value together with the fidelity level it was computed at
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Graded<F> {
+    pub value: F,
+    pub fidelity: usize,
+}
+
+pub trait Policy {
+    type LocalContext: RetrieveFitsManager<FitsM = Self::FitsM> + RetrieveIndividualManager<IM = Self::IndivM>;
+    type Exec: Executor<LC = Self::LocalContext>;
+
+    type Indiv;
+    type Fit: Clone + Send + Sync + 'static;
+    type IndivME: Send + 'static;
+    type IndivM: IndividualManager<I = Self::Indiv, FI = Self::Fit, E = Self::IndivME>;
+
+    type PopE: Send + 'static;
+    type Pop: Set<T = Self::Indiv, E = Self::PopE> + Sync + Send + 'static;
+
+    type FitsE: Send + 'static;
+    type Fits: Set<T = (Graded<Self::Fit>, usize), E = Self::FitsE> + Send + 'static;
+    type FitsME: Send + 'static;
+    type FitsM: SetManager<S = Self::Fits, E = Self::FitsME>;
+}
+
+#[derive(Debug)]
+pub enum FitnessError<PE, FE, FME, IME> {
+    Population(PE),
+    FitsSet(FE),
+    FitsSetManager(FME),
+    IndividualManager(IME),
+    UnsupportedFidelity { fidelity: usize, fidelities: usize, },
+}
+
+#[derive(Debug)]
+pub enum Error<ExecE, PopE, FitsE, FitsME, IndivME> {
+    NoOutputFitnessValues,
+    Fits(FitsError<FitsE>),
+    Executor(ExecutorJobError<ExecE, JobExecuteError<FitnessError<PopE, FitsE, FitsME, IndivME>, union::Error<FitsE, FitsME>>>),
+}
+
+pub type ErrorP<P> where P: Policy = Error<<P::Exec as Executor>::E, P::PopE, P::FitsE, P::FitsME, P::IndivME>;
+
+// Successive halving: the whole population is screened at fidelity 0, then only the best `promote` fraction
+// of every level is re-evaluated at the next one up to `fidelities - 1`. Each individual keeps the fitness
+// of the highest fidelity it reached.
+pub struct MultiFidelityPopulationFit<P> where P: Policy {
+    fidelities: usize,
+    promote: f64,
+    better: fn(&P::Fit, &P::Fit) -> bool,
+    _marker: PhantomData<P>,
+}
+
+impl<P> MultiFidelityPopulationFit<P> where P: Policy {
+    pub fn new(fidelities: usize, promote: f64, better: fn(&P::Fit, &P::Fit) -> bool) -> MultiFidelityPopulationFit<P> {
+        MultiFidelityPopulationFit {
+            fidelities: cmp::max(fidelities, 1),
+            promote: promote,
+            better: better,
+            _marker: PhantomData,
+        }
+    }
+
+    // evaluates `levels[index]` individuals at that fidelity, the others keep `current[index]`
+    fn evaluate<WA>(&self,
+                    population: Arc<P::Pop>,
+                    current: Arc<Vec<Option<Graded<P::Fit>>>>,
+                    levels: Arc<Vec<Option<usize>>>,
+                    exec: &mut P::Exec)
+                    -> Result<P::Fits, ErrorP<P>> where WA: WorkAmount, <P::Exec as Executor>::JIB: JobIterBuild<WA>
+    {
+        let population_size = population.size();
+        match exec.try_execute_job(
+            WA::new(population_size),
+            move |local_context, input_indices| {
+                let mut fitness_results = {
+                    let set_manager = <P::LocalContext as RetrieveFitsManager>::retrieve(local_context);
+                    try!(set_manager.make_set(Some(population_size)).map_err(FitnessError::FitsSetManager))
+                };
+                for index in input_indices {
+                    let graded = match (levels[index], &current[index]) {
+                        (Some(fidelity), _) => {
+                            let indiv = try!(population.get(index).map_err(FitnessError::Population));
+                            let indiv_manager = <P::LocalContext as RetrieveIndividualManager>::retrieve(local_context);
+                            if fidelity >= indiv_manager.fidelities() {
+                                return Err(FitnessError::UnsupportedFidelity { fidelity: fidelity, fidelities: indiv_manager.fidelities(), });
+                            }
+                            let value = try!(indiv_manager.fitness_at(indiv, fidelity).map_err(FitnessError::IndividualManager));
+                            Graded { value: value, fidelity: fidelity, }
+                        },
+                        (None, &Some(ref graded)) =>
+                            graded.clone(),
+                        (None, &None) =>
+                            continue,
+                    };
+                    try!(fitness_results.add((graded, index)).map_err(FitnessError::FitsSet));
+                }
+                Ok(fitness_results)
+            },
+            move |local_context, fits_a, fits_b| union::union(<P::LocalContext as RetrieveFitsManager>::retrieve(local_context), fits_a, fits_b))
+        {
+            Ok(None) => Err(Error::NoOutputFitnessValues),
+            Ok(Some(fitness_results)) => Ok(fitness_results),
+            Err(e) => Err(Error::Executor(e)),
+        }
+    }
+}
+
+// graded values ordered by population index
+pub fn graded_by_index<S, F>(fits: &S, population_size: usize) -> Result<Vec<Graded<F>>, FitsError<S::E>> where
+    S: Set<T = (Graded<F>, usize)>,
+    F: Clone
+{
+    let mut values: Vec<Option<Graded<F>>> = vec![None; population_size];
+    for fit_index in 0 .. fits.size() {
+        let &(ref graded, index) = try!(fits.get(fit_index).map_err(FitsError::Set));
+        if index < population_size {
+            values[index] = Some(graded.clone());
+        }
+    }
+    let mut graded = Vec::with_capacity(population_size);
+    for (index, value) in IntoIterator::into_iter(values).enumerate() {
+        graded.push(try!(value.ok_or(FitsError::MissingFitness { index: index, })));
+    }
+    Ok(graded)
+}
+
+impl<P> PopulationFit for MultiFidelityPopulationFit<P> where P: Policy {
+    type Exec = P::Exec;
+    type Indiv = P::Indiv;
+    type Pop = P::Pop;
+    type Fit = Graded<P::Fit>;
+    type Fits = P::Fits;
+    type Err = ErrorP<P>;
+
+    fn fit<WA>(&self, population: Arc<Self::Pop>, exec: &mut Self::Exec) -> Result<Self::Fits, Self::Err>
+        where WA: WorkAmount, <Self::Exec as Executor>::JIB: JobIterBuild<WA>
+    {
+        let population_size = population.size();
+        let better = self.better;
+        let mut survivors: Vec<usize> = (0 .. population_size).collect();
+        let mut current = Arc::new(vec![None; population_size]);
+        let mut fidelity = 0;
+        loop {
+            let mut levels = vec![None; population_size];
+            for &index in survivors.iter() {
+                levels[index] = Some(fidelity);
+            }
+            let fits = try!(self.evaluate::<WA>(population.clone(), current, Arc::new(levels), exec));
+            fidelity += 1;
+            if fidelity >= self.fidelities || survivors.len() <= 1 {
+                return Ok(fits);
+            }
+
+            let graded = try!(graded_by_index(&fits, population_size).map_err(Error::Fits));
+            survivors.sort_by(|&a, &b| if better(&graded[a].value, &graded[b].value) {
+                cmp::Ordering::Less
+            } else if better(&graded[b].value, &graded[a].value) {
+                cmp::Ordering::Greater
+            } else {
+                cmp::Ordering::Equal
+            });
+            let promoted = (self.promote.max(0.0).min(1.0) * survivors.len() as f64).ceil() as usize;
+            survivors.truncate(cmp::max(promoted, 1));
+            current = Arc::new(IntoIterator::into_iter(graded).map(Some).collect());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use par_exec::Executor;
+    use par_exec::par::{ParallelExecutor, Alternately};
+    use super::super::super::super::set;
+    use super::super::PopulationFit;
+    use super::super::super::individual::IndividualManager;
+    use super::super::standard::{RetrieveFitsManager, RetrieveIndividualManager};
+    use super::{Policy, MultiFidelityPopulationFit, Graded};
+
+    static EVALUATIONS: [AtomicUsize; 3] = [AtomicUsize::new(0), AtomicUsize::new(0), AtomicUsize::new(0)];
+
+    struct IndivManager;
+    impl IndividualManager for IndivManager {
+        type I = usize;
+        type FI = f64;
+        type E = ();
+
+        fn generate(&mut self, index: usize) -> Result<Self::I, Self::E> {
+            Ok(index)
+        }
+
+        fn fitness(&mut self, indiv: &Self::I) -> Result<Self::FI, Self::E> {
+            self.fitness_at(indiv, 2)
+        }
+
+        fn fidelities(&self) -> usize {
+            3
+        }
+
+        // lower fidelities are biased by an error which shrinks with the level
+        fn fitness_at(&mut self, indiv: &Self::I, fidelity: usize) -> Result<Self::FI, Self::E> {
+            EVALUATIONS[fidelity].fetch_add(1, Ordering::SeqCst);
+            Ok(*indiv as f64 + ((*indiv * 7) % 5) as f64 * (2 - fidelity) as f64)
+        }
+    }
+
+    struct LocalContext {
+        fits_manager: set::vec::Manager<(Graded<f64>, usize)>,
+        indiv_manager: IndivManager,
+    }
+
+    impl RetrieveFitsManager for LocalContext {
+        type FitsM = set::vec::Manager<(Graded<f64>, usize)>;
+
+        fn retrieve(&mut self) -> &mut Self::FitsM {
+            &mut self.fits_manager
+        }
+    }
+
+    impl RetrieveIndividualManager for LocalContext {
+        type IM = IndivManager;
+
+        fn retrieve(&mut self) -> &mut Self::IM {
+            &mut self.indiv_manager
+        }
+    }
+
+    struct TestPolicy;
+    impl Policy for TestPolicy {
+        type LocalContext = LocalContext;
+        type Exec = ParallelExecutor<LocalContext>;
+
+        type Indiv = usize;
+        type Fit = f64;
+        type IndivME = ();
+        type IndivM = IndivManager;
+
+        type PopE = set::vec::Error;
+        type Pop = Vec<usize>;
+
+        type FitsE = set::vec::Error;
+        type Fits = Vec<(Graded<f64>, usize)>;
+        type FitsME = ();
+        type FitsM = set::vec::Manager<(Graded<f64>, usize)>;
+    }
+
+    fn smaller(a: &f64, b: &f64) -> bool {
+        a < b
+    }
+
+    #[test]
+    fn successive_halving() {
+        let exec: ParallelExecutor<_> = Default::default();
+        let mut exec = exec.start(|| LocalContext {
+            fits_manager: set::vec::Manager::new(),
+            indiv_manager: IndivManager,
+        }).unwrap();
+
+        let population = Arc::new((0 .. 64).collect::<Vec<_>>());
+        let fitness_calculator: MultiFidelityPopulationFit<TestPolicy> = MultiFidelityPopulationFit::new(3, 0.5, smaller);
+        let mut fits = fitness_calculator.fit::<Alternately>(population.clone(), &mut exec).unwrap();
+        fits.sort_by_key(|v| v.1);
+        assert_eq!(fits.len(), 64);
+        assert_eq!(EVALUATIONS.iter().map(|counter| counter.load(Ordering::SeqCst)).collect::<Vec<_>>(), vec![64, 32, 16]);
+
+        let levels = fits.iter().fold(vec![0; 3], |mut levels, &(graded, _)| { levels[graded.fidelity] += 1; levels });
+        assert_eq!(levels, vec![32, 16, 16]);
+        let top: Vec<_> = fits.iter().filter(|&&(graded, _)| graded.fidelity == 2).map(|&(graded, index)| (graded.value, index)).collect();
+        assert!(top.iter().all(|&(value, index)| value == population[index] as f64));
+        assert!(top.iter().any(|&(_, index)| index == 0));
+    }
+}
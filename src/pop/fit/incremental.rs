@@ -0,0 +1,223 @@
+use std::sync::Arc;
+use std::marker::PhantomData;
+use par_exec::{Executor, WorkAmount, JobIterBuild, ExecutorJobError, JobExecuteError};
+
+use super::standard::{self, Policy, RetrieveFitsManager, FitnessError};
+use super::super::repair::Repair;
+use super::super::super::set::{Set, SetManager};
+use super::super::super::set::union;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Marker {
+    // new or modified individual, has to be evaluated
+    Changed,
+    // individual is identical to the one at the given index of the previous population
+    Unchanged(usize),
+}
+
+// evaluates only changed individuals, the fitness of unchanged ones is carried over from the previous fits
+pub struct IncrementalPopulationFit<P> where P: Policy {
+    repair: Repair,
+    _marker: PhantomData<P>,
+}
+
+impl<P> IncrementalPopulationFit<P> where P: Policy {
+    pub fn new() -> IncrementalPopulationFit<P> {
+        IncrementalPopulationFit::with_repair(Repair::WriteBack)
+    }
+
+    pub fn with_repair(repair: Repair) -> IncrementalPopulationFit<P> {
+        IncrementalPopulationFit {
+            repair: repair,
+            _marker: PhantomData,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Error<ExecE, PopE, FitsE, FitsME, IndivME, RepairME> {
+    MarkersMismatch { population: usize, markers: usize, },
+    PreviousFits(FitsE),
+    MissingPreviousFitness { index: usize, previous_index: usize, },
+    NoOutputFitnessValues,
+    Executor(ExecutorJobError<ExecE, JobExecuteError<FitnessError<PopE, FitsE, FitsME, IndivME, RepairME>, union::Error<FitsE, FitsME>>>),
+}
+
+pub type ErrorP<P> where P: Policy = Error<<P::Exec as Executor>::E, P::PopE, P::FitsE, P::FitsME, P::IndivME, P::RepairME>;
+
+impl<P> IncrementalPopulationFit<P> where P: Policy {
+    pub fn fit<WA>(&self, population: Arc<P::Pop>, previous_fits: &P::Fits, markers: Arc<Vec<Marker>>, exec: &mut P::Exec) ->
+        Result<P::Fits, ErrorP<P>> where WA: WorkAmount, <P::Exec as Executor>::JIB: JobIterBuild<WA>
+    {
+        let population_size = population.size();
+        if markers.len() != population_size {
+            return Err(Error::MarkersMismatch { population: population_size, markers: markers.len(), });
+        }
+
+        // previous fitness values by previous population index
+        let mut previous: Vec<Option<P::Fit>> = Vec::new();
+        for fit_index in 0 .. previous_fits.size() {
+            let &(ref fitness, previous_index) = try!(previous_fits.get(fit_index).map_err(Error::PreviousFits));
+            if previous_index >= previous.len() {
+                previous.resize(previous_index + 1, None);
+            }
+            previous[previous_index] = Some(fitness.clone());
+        }
+        for (index, marker) in markers.iter().enumerate() {
+            if let Marker::Unchanged(previous_index) = *marker {
+                if previous.get(previous_index).map(|fitness| fitness.is_none()).unwrap_or(true) {
+                    return Err(Error::MissingPreviousFitness { index: index, previous_index: previous_index, });
+                }
+            }
+        }
+        let previous = Arc::new(previous);
+
+        let repair = self.repair;
+        match exec.try_execute_job(
+            WA::new(population_size),
+            move |local_context, input_indices| {
+                let mut fitness_results = {
+                    let set_manager = <P::LocalContext as RetrieveFitsManager>::retrieve(local_context);
+                    try!(set_manager.make_set(Some(population_size)).map_err(FitnessError::FitsSetManager))
+                };
+                for index in input_indices {
+                    let fitness = match markers[index] {
+                        // presence checked before the job
+                        Marker::Unchanged(previous_index) => previous[previous_index].clone().unwrap(),
+                        Marker::Changed => {
+                            let indiv = try!(population.get(index).map_err(FitnessError::Population));
+                            try!(standard::evaluate::<P>(local_context, repair, None, index, indiv))
+                        },
+                    };
+                    try!(fitness_results.add((fitness, index)).map_err(FitnessError::FitsSet));
+                }
+                Ok(fitness_results)
+            },
+            move |local_context, fits_a, fits_b| union::union(<P::LocalContext as RetrieveFitsManager>::retrieve(local_context), fits_a, fits_b))
+        {
+            Ok(None) => Err(Error::NoOutputFitnessValues),
+            Ok(Some(fitness_results)) => Ok(fitness_results),
+            Err(e) => Err(Error::Executor(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use par_exec::Executor;
+    use par_exec::par::{ParallelExecutor, Alternately};
+    use super::super::super::super::set;
+    use super::super::super::individual::IndividualManager;
+    use super::super::super::repair::{NoRepair, RetrieveRepairManager};
+    use super::super::cache::{NoCache, RetrieveCacheManager};
+    use super::super::standard::{Policy, RetrieveFitsManager, RetrieveIndividualManager};
+    use super::{IncrementalPopulationFit, Marker, Error};
+
+    struct IndivManager;
+    impl IndividualManager for IndivManager {
+        type I = usize;
+        type FI = f64;
+        type E = ();
+
+        fn generate(&mut self, index: usize) -> Result<Self::I, Self::E> {
+            Ok(index)
+        }
+
+        fn fitness(&mut self, indiv: &Self::I) -> Result<Self::FI, Self::E> {
+            Ok(*indiv as f64)
+        }
+    }
+
+    struct LocalContext {
+        set_manager: set::vec::Manager<(f64, usize)>,
+        indiv_manager: IndivManager,
+        repair_manager: NoRepair<usize>,
+        cache_manager: NoCache<usize, f64>,
+    }
+
+    impl RetrieveFitsManager for LocalContext {
+        type FitsM = set::vec::Manager<(f64, usize)>;
+
+        fn retrieve(&mut self) -> &mut Self::FitsM {
+            &mut self.set_manager
+        }
+    }
+
+    impl RetrieveIndividualManager for LocalContext {
+        type IM = IndivManager;
+
+        fn retrieve(&mut self) -> &mut Self::IM {
+            &mut self.indiv_manager
+        }
+    }
+
+    impl RetrieveRepairManager for LocalContext {
+        type RM = NoRepair<usize>;
+
+        fn retrieve(&mut self) -> &mut Self::RM {
+            &mut self.repair_manager
+        }
+    }
+
+    impl RetrieveCacheManager for LocalContext {
+        type CM = NoCache<usize, f64>;
+
+        fn retrieve(&mut self) -> &mut Self::CM {
+            &mut self.cache_manager
+        }
+    }
+
+    struct TestPolicy;
+    impl Policy for TestPolicy {
+        type LocalContext = LocalContext;
+        type Exec = ParallelExecutor<LocalContext>;
+
+        type Indiv = usize;
+        type Fit = f64;
+        type IndivME = ();
+        type IndivM = IndivManager;
+        type RepairME = ();
+        type RepairM = NoRepair<usize>;
+        type CacheM = NoCache<usize, f64>;
+
+        type PopE = set::vec::Error;
+        type Pop = Vec<usize>;
+
+        type FitsE = set::vec::Error;
+        type Fits = Vec<(f64, usize)>;
+        type FitsME = ();
+        type FitsM = set::vec::Manager<(f64, usize)>;
+    }
+
+    #[test]
+    fn carry_over_unchanged() {
+        let exec: ParallelExecutor<_> = Default::default();
+        let mut exec = exec.start(|| LocalContext {
+            set_manager: set::vec::Manager::new(),
+            indiv_manager: IndivManager,
+            repair_manager: NoRepair::new(),
+            cache_manager: NoCache::new(),
+        }).unwrap();
+
+        // previous fits are deliberately different from what the individual manager would compute
+        let previous_fits: Vec<_> = (0 .. 128).rev().map(|i| (-1.0 - i as f64, i)).collect();
+        let population = Arc::new((0 .. 256).collect::<Vec<usize>>());
+        let markers = Arc::new((0 .. 256).map(|i| if i % 2 == 0 { Marker::Unchanged(i / 2) } else { Marker::Changed }).collect::<Vec<_>>());
+
+        let fitness_calculator: IncrementalPopulationFit<TestPolicy> = IncrementalPopulationFit::new();
+        let mut fit_results = fitness_calculator.fit::<Alternately>(population.clone(), &previous_fits, markers, &mut exec).unwrap();
+        fit_results.sort_by_key(|v| v.1);
+        assert_eq!(fit_results.len(), 256);
+        for &(fitness, index) in fit_results.iter() {
+            let expected = if index % 2 == 0 { -1.0 - (index / 2) as f64 } else { index as f64 };
+            assert_eq!(fitness, expected);
+        }
+
+        let markers = Arc::new((0 .. 256).map(|_| Marker::Unchanged(200)).collect::<Vec<_>>());
+        match fitness_calculator.fit::<Alternately>(population, &previous_fits, markers, &mut exec) {
+            Err(Error::MissingPreviousFitness { index: 0, previous_index: 200, }) => (),
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+    }
+}
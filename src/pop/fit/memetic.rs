@@ -0,0 +1,379 @@
+use std::sync::Arc;
+use std::marker::PhantomData;
+use par_exec::{Executor, WorkAmount, JobIterBuild, ExecutorJobError, JobExecuteError};
+
+use super::PopulationFit;
+use super::super::individual::IndividualManager;
+use super::super::local_search::{LocalSearchManager, SearchError};
+use super::super::super::set::{Set, SetManager};
+use super::super::super::set::union;
+
+pub trait RetrieveFitsManager {
+    type FitsM;
+
+    fn retrieve(&mut self) -> &mut Self::FitsM;
+}
+
+pub trait RetrieveImprovedManager {
+    type ImprovedM;
+
+    fn retrieve(&mut self) -> &mut Self::ImprovedM;
+}
+
+// local search manager is used together with the individual manager it evaluates neighbours with
+pub trait RetrieveLocalSearchManager {
+    type LSM;
+    type IM;
+
+    fn retrieve(&mut self) -> (&mut Self::LSM, &mut Self::IM);
+}
+
+pub trait Policy {
+    type LocalContext: RetrieveFitsManager<FitsM = Self::FitsM> +
+        RetrieveImprovedManager<ImprovedM = Self::ImprovedM> +
+        RetrieveLocalSearchManager<LSM = Self::LSM, IM = Self::IndivM>;
+    type Exec: Executor<LC = Self::LocalContext>;
+
+    type Indiv;
+    type Fit;
+    type IndivME: Send + 'static;
+    type IndivM: IndividualManager<I = Self::Indiv, FI = Self::Fit, E = Self::IndivME>;
+
+    type LSE: Send + 'static;
+    type LSM: LocalSearchManager<I = Self::Indiv, FI = Self::Fit, E = Self::LSE>;
+
+    type PopE: Send + 'static;
+    type Pop: Set<T = Self::Indiv, E = Self::PopE> + Sync + Send + 'static;
+
+    type FitsE: Send + 'static;
+    type Fits: Set<T = (Self::Fit, usize), E = Self::FitsE> + Send + 'static;
+    type FitsME: Send + 'static;
+    type FitsM: SetManager<S = Self::Fits, E = Self::FitsME>;
+
+    type ImprovedE: Send + 'static;
+    type Improved: Set<T = (Self::Indiv, usize), E = Self::ImprovedE> + Send + 'static;
+    type ImprovedME: Send + 'static;
+    type ImprovedM: SetManager<S = Self::Improved, E = Self::ImprovedME>;
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum WriteBack {
+    // improved individuals replace the originals
+    Lamarckian,
+    // only improved fitness is recorded, individuals stay untouched
+    Baldwinian,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Apply {
+    All,
+    EveryNth(usize),
+}
+
+impl Apply {
+    pub fn covers(&self, index: usize) -> bool {
+        match *self {
+            Apply::All => true,
+            Apply::EveryNth(0) => false,
+            Apply::EveryNth(n) => index % n == 0,
+        }
+    }
+}
+
+pub struct MemeticPopulationFit<P> where P: Policy {
+    write_back: WriteBack,
+    apply: Apply,
+    _marker: PhantomData<P>,
+}
+
+impl<P> MemeticPopulationFit<P> where P: Policy {
+    pub fn new(write_back: WriteBack, apply: Apply) -> MemeticPopulationFit<P> {
+        MemeticPopulationFit {
+            write_back: write_back,
+            apply: apply,
+            _marker: PhantomData,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum FitnessError<PE, FE, FME, IE, IME, IndivME, LSE> {
+    Population(PE),
+    FitsSet(FE),
+    FitsSetManager(FME),
+    ImprovedSet(IE),
+    ImprovedSetManager(IME),
+    IndividualManager(IndivME),
+    LocalSearch(SearchError<LSE, IndivME>),
+}
+
+#[derive(Debug)]
+pub enum UnionError<FE, FME, IE, IME> {
+    Fits(union::Error<FE, FME>),
+    Improved(union::Error<IE, IME>),
+}
+
+#[derive(Debug)]
+pub enum Error<ExecE, PopE, FitsE, FitsME, ImprE, ImprME, IndivME, LSE> {
+    NoOutputFitnessValues,
+    Executor(ExecutorJobError<ExecE, JobExecuteError<FitnessError<PopE, FitsE, FitsME, ImprE, ImprME, IndivME, LSE>,
+                                                     UnionError<FitsE, FitsME, ImprE, ImprME>>>),
+}
+
+pub type ErrorP<P> where P: Policy =
+    Error<<P::Exec as Executor>::E, P::PopE, P::FitsE, P::FitsME, P::ImprovedE, P::ImprovedME, P::IndivME, P::LSE>;
+
+impl<P> MemeticPopulationFit<P> where P: Policy {
+    // returns fits along with individuals improved by local search (the latter is always empty in `WriteBack::Baldwinian` mode)
+    pub fn fit_and_improve<WA>(&self, population: Arc<P::Pop>, exec: &mut P::Exec) -> Result<(P::Fits, P::Improved), ErrorP<P>>
+        where WA: WorkAmount, <P::Exec as Executor>::JIB: JobIterBuild<WA>
+    {
+        let population_size = population.size();
+        let write_back = self.write_back;
+        let apply = self.apply;
+        match exec.try_execute_job(
+            WA::new(population_size),
+            move |local_context, input_indices| {
+                let mut fitness_results = {
+                    let set_manager = <P::LocalContext as RetrieveFitsManager>::retrieve(local_context);
+                    try!(set_manager.make_set(Some(population_size)).map_err(FitnessError::FitsSetManager))
+                };
+                let mut improved = {
+                    let set_manager = <P::LocalContext as RetrieveImprovedManager>::retrieve(local_context);
+                    try!(set_manager.make_set(None).map_err(FitnessError::ImprovedSetManager))
+                };
+                let (search_manager, indiv_manager) = <P::LocalContext as RetrieveLocalSearchManager>::retrieve(local_context);
+                for index in input_indices {
+                    let indiv = try!(population.get(index).map_err(FitnessError::Population));
+                    let fitness = try!(indiv_manager.fitness(indiv).map_err(FitnessError::IndividualManager));
+                    let maybe_better = if apply.covers(index) {
+                        try!(search_manager.search(indiv, &fitness, indiv_manager).map_err(FitnessError::LocalSearch))
+                    } else {
+                        None
+                    };
+                    match maybe_better {
+                        None =>
+                            try!(fitness_results.add((fitness, index)).map_err(FitnessError::FitsSet)),
+                        Some((better_indiv, better_fitness)) => {
+                            try!(fitness_results.add((better_fitness, index)).map_err(FitnessError::FitsSet));
+                            if write_back == WriteBack::Lamarckian {
+                                try!(improved.add((better_indiv, index)).map_err(FitnessError::ImprovedSet));
+                            }
+                        },
+                    }
+                }
+                Ok((fitness_results, improved))
+            },
+            move |local_context, (fits_a, improved_a), (fits_b, improved_b)| {
+                let fits = try!(union::union(<P::LocalContext as RetrieveFitsManager>::retrieve(local_context), fits_a, fits_b)
+                                .map_err(UnionError::Fits));
+                let improved = try!(union::union(<P::LocalContext as RetrieveImprovedManager>::retrieve(local_context), improved_a, improved_b)
+                                    .map_err(UnionError::Improved));
+                Ok((fits, improved))
+            })
+        {
+            Ok(None) => Err(Error::NoOutputFitnessValues),
+            Ok(Some(results)) => Ok(results),
+            Err(e) => Err(Error::Executor(e)),
+        }
+    }
+}
+
+// note that improved individuals are dropped here, use `fit_and_improve` for `WriteBack::Lamarckian` mode
+impl<P> PopulationFit for MemeticPopulationFit<P> where P: Policy {
+    type Exec = P::Exec;
+    type Indiv = P::Indiv;
+    type Pop = P::Pop;
+    type Fit = P::Fit;
+    type Fits = P::Fits;
+    type Err = ErrorP<P>;
+
+    fn fit<WA>(&self, population: Arc<Self::Pop>, exec: &mut Self::Exec) -> Result<Self::Fits, Self::Err>
+        where WA: WorkAmount, <Self::Exec as Executor>::JIB: JobIterBuild<WA>
+    {
+        let (fits, _improved) = try!(self.fit_and_improve::<WA>(population, exec));
+        Ok(fits)
+    }
+}
+
+#[derive(Debug)]
+pub enum WriteBackError<PE, PSME, IE> {
+    Population(PE),
+    PopulationManager(PSME),
+    Improved(IE),
+}
+
+// builds a new population with individuals replaced by their improved versions
+pub fn write_back<I, Pop, PopM, Impr>(pop_manager: &mut PopM, population: &Pop, improved: Impr) ->
+    Result<Pop, WriteBackError<Pop::E, PopM::E, Impr::E>> where
+    I: Clone,
+    Pop: Set<T = I>,
+    PopM: SetManager<S = Pop>,
+    Impr: Set<T = (I, usize)>
+{
+    let population_size = population.size();
+    let mut replacements: Vec<Option<I>> = (0 .. population_size).map(|_| None).collect();
+    for maybe_value in improved.into_iter() {
+        let (indiv, index) = try!(maybe_value.map_err(WriteBackError::Improved));
+        if index < population_size {
+            replacements[index] = Some(indiv);
+        }
+    }
+
+    let mut target = try!(pop_manager.make_set(Some(population_size)).map_err(WriteBackError::PopulationManager));
+    for (index, replacement) in IntoIterator::into_iter(replacements).enumerate() {
+        let indiv = match replacement {
+            Some(indiv) => indiv,
+            None => try!(population.get(index).map_err(WriteBackError::Population)).clone(),
+        };
+        try!(target.add(indiv).map_err(WriteBackError::Population));
+    }
+    Ok(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use par_exec::Executor;
+    use par_exec::par::{ParallelExecutor, Alternately};
+    use super::super::super::super::set;
+    use super::super::super::individual::IndividualManager;
+    use super::super::super::neighbour::NeighbourManager;
+    use super::super::super::local_search::climbing::{Limits, HillClimbing};
+    use super::{Policy, MemeticPopulationFit, WriteBack, Apply, write_back};
+    use super::{RetrieveFitsManager, RetrieveImprovedManager, RetrieveLocalSearchManager};
+
+    struct IndivManager;
+    impl IndividualManager for IndivManager {
+        type I = usize;
+        type FI = usize;
+        type E = ();
+
+        fn generate(&mut self, index: usize) -> Result<Self::I, Self::E> {
+            Ok(index)
+        }
+
+        fn fitness(&mut self, indiv: &Self::I) -> Result<Self::FI, Self::E> {
+            Ok(if *indiv > 100 { *indiv - 100 } else { 100 - *indiv })
+        }
+    }
+
+    struct Steps;
+    impl NeighbourManager for Steps {
+        type I = usize;
+        type E = ();
+
+        fn neighbour(&mut self, indiv: &Self::I, attempt: usize) -> Result<Option<Self::I>, Self::E> {
+            Ok(match attempt {
+                0 => Some(*indiv + 1),
+                1 if *indiv > 0 => Some(*indiv - 1),
+                _ => None,
+            })
+        }
+    }
+
+    fn better(a: &usize, b: &usize) -> bool {
+        a < b
+    }
+
+    type Search = HillClimbing<Steps, usize, fn(&usize, &usize) -> bool>;
+
+    struct LocalContext {
+        fits_manager: set::vec::Manager<(usize, usize)>,
+        improved_manager: set::vec::Manager<(usize, usize)>,
+        search_manager: Search,
+        indiv_manager: IndivManager,
+    }
+
+    impl RetrieveFitsManager for LocalContext {
+        type FitsM = set::vec::Manager<(usize, usize)>;
+
+        fn retrieve(&mut self) -> &mut Self::FitsM {
+            &mut self.fits_manager
+        }
+    }
+
+    impl RetrieveImprovedManager for LocalContext {
+        type ImprovedM = set::vec::Manager<(usize, usize)>;
+
+        fn retrieve(&mut self) -> &mut Self::ImprovedM {
+            &mut self.improved_manager
+        }
+    }
+
+    impl RetrieveLocalSearchManager for LocalContext {
+        type LSM = Search;
+        type IM = IndivManager;
+
+        fn retrieve(&mut self) -> (&mut Self::LSM, &mut Self::IM) {
+            (&mut self.search_manager, &mut self.indiv_manager)
+        }
+    }
+
+    struct TestPolicy;
+    impl Policy for TestPolicy {
+        type LocalContext = LocalContext;
+        type Exec = ParallelExecutor<LocalContext>;
+
+        type Indiv = usize;
+        type Fit = usize;
+        type IndivME = ();
+        type IndivM = IndivManager;
+
+        type LSE = ();
+        type LSM = Search;
+
+        type PopE = set::vec::Error;
+        type Pop = Vec<usize>;
+
+        type FitsE = set::vec::Error;
+        type Fits = Vec<(usize, usize)>;
+        type FitsME = ();
+        type FitsM = set::vec::Manager<(usize, usize)>;
+
+        type ImprovedE = set::vec::Error;
+        type Improved = Vec<(usize, usize)>;
+        type ImprovedME = ();
+        type ImprovedM = set::vec::Manager<(usize, usize)>;
+    }
+
+    fn make_executor() -> ParallelExecutor<LocalContext> {
+        let exec: ParallelExecutor<_> = Default::default();
+        exec.start(|| LocalContext {
+            fits_manager: set::vec::Manager::new(),
+            improved_manager: set::vec::Manager::new(),
+            search_manager: HillClimbing::new(Steps, better as fn(&usize, &usize) -> bool, Limits { max_steps: 256, max_neighbours: 2, }),
+            indiv_manager: IndivManager,
+        }).unwrap()
+    }
+
+    #[test]
+    fn lamarckian() {
+        let mut exec = make_executor();
+        let population = Arc::new((0 .. 256).collect::<Vec<_>>());
+
+        let memetic: MemeticPopulationFit<TestPolicy> = MemeticPopulationFit::new(WriteBack::Lamarckian, Apply::All);
+        let (mut fits, improved) = memetic.fit_and_improve::<Alternately>(population.clone(), &mut exec).unwrap();
+        fits.sort_by_key(|v| v.1);
+        assert_eq!(fits, (0 .. 256).map(|i| (0, i)).collect::<Vec<_>>());
+        assert_eq!(improved.len(), 255);
+
+        let new_population = write_back(&mut set::vec::Manager::new(), &*population, improved).unwrap();
+        assert_eq!(new_population, (0 .. 256).map(|_| 100).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn baldwinian_every_nth() {
+        let mut exec = make_executor();
+        let population = Arc::new((0 .. 256).collect::<Vec<_>>());
+
+        let memetic: MemeticPopulationFit<TestPolicy> = MemeticPopulationFit::new(WriteBack::Baldwinian, Apply::EveryNth(2));
+        let (mut fits, improved) = memetic.fit_and_improve::<Alternately>(population.clone(), &mut exec).unwrap();
+        fits.sort_by_key(|v| v.1);
+        for (fitness, index) in fits {
+            let expected = if index % 2 == 0 { 0 } else if index > 100 { index - 100 } else { 100 - index };
+            assert_eq!(fitness, expected);
+        }
+        assert!(improved.is_empty());
+    }
+}
@@ -1,7 +1,14 @@
 use std::sync::Arc;
 use par_exec::{Executor, WorkAmount, JobIterBuild};
 
+pub mod cache;
 pub mod standard;
+pub mod incremental;
+pub mod distributed;
+pub mod memetic;
+pub mod noisy;
+pub mod fidelity;
+pub mod surrogate;
 
 use super::super::set::Set;
 
@@ -0,0 +1,441 @@
+use std::cmp::{self, Ordering};
+use std::sync::Arc;
+use std::marker::PhantomData;
+use par_exec::{Executor, WorkAmount, JobIterBuild, ExecutorJobError, JobExecuteError};
+
+use super::PopulationFit;
+use super::standard::{RetrieveFitsManager, RetrieveIndividualManager};
+use super::super::individual::IndividualManager;
+use super::super::niching::FitsError;
+use super::super::super::set::{Set, SetManager};
+use super::super::super::set::union;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Aggregate {
+    Mean,
+    Median,
+    // 0.0 is the minimum, 1.0 the maximum sample
+    Quantile(f64),
+}
+
+impl Aggregate {
+    pub fn apply(&self, samples: &[f64]) -> f64 {
+        if samples.is_empty() {
+            return ::std::f64::NAN;
+        }
+        match *self {
+            Aggregate::Mean => mean(samples),
+            Aggregate::Median => quantile(samples, 0.5),
+            Aggregate::Quantile(q) => quantile(samples, q),
+        }
+    }
+}
+
+fn mean(samples: &[f64]) -> f64 {
+    samples.iter().sum::<f64>() / samples.len() as f64
+}
+
+// linear interpolation between the closest order statistics
+fn quantile(samples: &[f64], q: f64) -> f64 {
+    let mut sorted = samples.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    let position = q.max(0.0).min(1.0) * (sorted.len() - 1) as f64;
+    let (lower, upper) = (position.floor() as usize, position.ceil() as usize);
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (position - lower as f64)
+}
+
+// repeated evaluations of a single individual
+#[derive(Clone, PartialEq, Debug)]
+pub struct Noisy {
+    aggregate: Aggregate,
+    samples: Vec<f64>,
+    value: f64,
+}
+
+impl Noisy {
+    pub fn new(aggregate: Aggregate) -> Noisy {
+        Noisy {
+            aggregate: aggregate,
+            samples: Vec::new(),
+            value: ::std::f64::NAN,
+        }
+    }
+
+    pub fn add(&mut self, sample: f64) {
+        self.samples.push(sample);
+        self.value = self.aggregate.apply(&self.samples);
+    }
+
+    pub fn samples(&self) -> &[f64] {
+        &self.samples
+    }
+
+    pub fn count(&self) -> usize {
+        self.samples.len()
+    }
+
+    // aggregated fitness value
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    pub fn mean(&self) -> f64 {
+        mean(&self.samples)
+    }
+
+    // unbiased sample variance
+    pub fn variance(&self) -> f64 {
+        if self.samples.len() < 2 {
+            return ::std::f64::INFINITY;
+        }
+        let m = self.mean();
+        self.samples.iter().map(|s| (s - m) * (s - m)).sum::<f64>() / (self.samples.len() - 1) as f64
+    }
+}
+
+// statistical test deciding whether one noisy individual has a smaller mean than another
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Race {
+    // samples are assumed to lie within an interval of width `range`, `delta` is the error probability
+    Hoeffding { range: f64, delta: f64, },
+    // Welch's t statistic compared against the `critical` value (e.g. 1.96)
+    Welch { critical: f64, },
+}
+
+impl Race {
+    // `None` while the samples are not sufficient to tell the individuals apart
+    pub fn compare(&self, a: &Noisy, b: &Noisy) -> Option<Ordering> {
+        if a.count() == 0 || b.count() == 0 {
+            return None;
+        }
+        let difference = a.mean() - b.mean();
+        let threshold = match *self {
+            Race::Hoeffding { range, delta } => {
+                let bound = |n: usize| range * ((2.0 / delta).ln() / (2.0 * n as f64)).sqrt();
+                bound(a.count()) + bound(b.count())
+            },
+            Race::Welch { critical } => {
+                if a.count() < 2 || b.count() < 2 {
+                    return None;
+                }
+                critical * (a.variance() / a.count() as f64 + b.variance() / b.count() as f64).sqrt()
+            },
+        };
+        if difference < -threshold {
+            Some(Ordering::Less)
+        } else if difference > threshold {
+            Some(Ordering::Greater)
+        } else {
+            None
+        }
+    }
+}
+
+// ascending means predicate for `set::sort::sort`; every race decision agrees with it, so the sorted order
+// is reliable once `NoisyPopulationFit::resample` got all the neighbouring ranks decided
+pub fn sort_pred(values: Arc<Vec<Noisy>>) -> Box<dyn Fn(usize, usize) -> bool + Send + Sync> {
+    Box::new(move |index_a, index_b| values[index_a].mean() < values[index_b].mean())
+}
+
+pub trait Policy {
+    type LocalContext: RetrieveFitsManager<FitsM = Self::FitsM> + RetrieveIndividualManager<IM = Self::IndivM>;
+    type Exec: Executor<LC = Self::LocalContext>;
+
+    // every fitness call returns a single noisy sample
+    type Indiv;
+    type IndivME: Send + 'static;
+    type IndivM: IndividualManager<I = Self::Indiv, FI = f64, E = Self::IndivME>;
+
+    type PopE: Send + 'static;
+    type Pop: Set<T = Self::Indiv, E = Self::PopE> + Sync + Send + 'static;
+
+    type FitsE: Send + 'static;
+    type Fits: Set<T = (Noisy, usize), E = Self::FitsE> + Send + 'static;
+    type FitsME: Send + 'static;
+    type FitsM: SetManager<S = Self::Fits, E = Self::FitsME>;
+}
+
+pub struct NoisyPopulationFit<P> where P: Policy {
+    samples: usize,
+    aggregate: Aggregate,
+    _marker: PhantomData<P>,
+}
+
+impl<P> NoisyPopulationFit<P> where P: Policy {
+    // every individual is evaluated at least once
+    pub fn new(samples: usize, aggregate: Aggregate) -> NoisyPopulationFit<P> {
+        NoisyPopulationFit {
+            samples: cmp::max(samples, 1),
+            aggregate: aggregate,
+            _marker: PhantomData,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum FitnessError<PE, FE, FME, IME> {
+    Population(PE),
+    FitsSet(FE),
+    FitsSetManager(FME),
+    IndividualManager(IME),
+}
+
+#[derive(Debug)]
+pub enum Error<ExecE, PopE, FitsE, FitsME, IndivME> {
+    NoOutputFitnessValues,
+    Fits(FitsError<FitsE>),
+    Executor(ExecutorJobError<ExecE, JobExecuteError<FitnessError<PopE, FitsE, FitsME, IndivME>, union::Error<FitsE, FitsME>>>),
+}
+
+pub type ErrorP<P> where P: Policy = Error<<P::Exec as Executor>::E, P::PopE, P::FitsE, P::FitsME, P::IndivME>;
+
+impl<P> NoisyPopulationFit<P> where P: Policy {
+    // takes `extra[index]` more samples of every individual starting from `current[index]`
+    fn sample<WA>(&self, population: Arc<P::Pop>, current: Arc<Vec<Noisy>>, extra: Arc<Vec<usize>>, exec: &mut P::Exec) ->
+        Result<P::Fits, ErrorP<P>> where WA: WorkAmount, <P::Exec as Executor>::JIB: JobIterBuild<WA>
+    {
+        let population_size = population.size();
+        match exec.try_execute_job(
+            WA::new(population_size),
+            move |local_context, input_indices| {
+                let mut fitness_results = {
+                    let set_manager = <P::LocalContext as RetrieveFitsManager>::retrieve(local_context);
+                    try!(set_manager.make_set(Some(population_size)).map_err(FitnessError::FitsSetManager))
+                };
+                for index in input_indices {
+                    let mut noisy = current[index].clone();
+                    if extra[index] > 0 {
+                        let indiv = try!(population.get(index).map_err(FitnessError::Population));
+                        let indiv_manager = <P::LocalContext as RetrieveIndividualManager>::retrieve(local_context);
+                        for _ in 0 .. extra[index] {
+                            noisy.add(try!(indiv_manager.fitness(indiv).map_err(FitnessError::IndividualManager)));
+                        }
+                    }
+                    try!(fitness_results.add((noisy, index)).map_err(FitnessError::FitsSet));
+                }
+                Ok(fitness_results)
+            },
+            move |local_context, fits_a, fits_b| union::union(<P::LocalContext as RetrieveFitsManager>::retrieve(local_context), fits_a, fits_b))
+        {
+            Ok(None) => Err(Error::NoOutputFitnessValues),
+            Ok(Some(fitness_results)) => Ok(fitness_results),
+            Err(e) => Err(Error::Executor(e)),
+        }
+    }
+
+    // adaptive resampling: individuals are ranked by their means and, while the `budget` of extra evaluations
+    // lasts, every one which `race` cannot distinguish from one of its rank neighbours receives one more sample
+    // per round; stops early once all the neighbouring ranks are decided
+    pub fn resample<WA>(&self, population: Arc<P::Pop>, mut fits: P::Fits, race: Race, mut budget: usize, exec: &mut P::Exec) ->
+        Result<P::Fits, ErrorP<P>> where WA: WorkAmount, <P::Exec as Executor>::JIB: JobIterBuild<WA>
+    {
+        let population_size = population.size();
+        while budget > 0 {
+            let current = try!(noisy_by_index(&fits, population_size).map_err(Error::Fits));
+            let mut ranking: Vec<usize> = (0 .. population_size).collect();
+            ranking.sort_by(|&a, &b| current[a].mean().partial_cmp(&current[b].mean()).unwrap_or(Ordering::Equal));
+
+            let mut extra = vec![0; population_size];
+            let mut spent = 0;
+            for pair in ranking.windows(2) {
+                if race.compare(&current[pair[0]], &current[pair[1]]).is_none() {
+                    for &index in pair {
+                        if extra[index] == 0 && spent < budget {
+                            extra[index] = 1;
+                            spent += 1;
+                        }
+                    }
+                }
+            }
+            if spent == 0 {
+                break;
+            }
+            budget -= spent;
+            fits = try!(self.sample::<WA>(population.clone(), Arc::new(current), Arc::new(extra), exec));
+        }
+        Ok(fits)
+    }
+}
+
+// noisy values ordered by population index, suitable for `sort_pred`
+pub fn noisy_by_index<S>(fits: &S, population_size: usize) -> Result<Vec<Noisy>, FitsError<S::E>> where S: Set<T = (Noisy, usize)> {
+    let mut values: Vec<Option<Noisy>> = vec![None; population_size];
+    for fit_index in 0 .. fits.size() {
+        let &(ref noisy, index) = try!(fits.get(fit_index).map_err(FitsError::Set));
+        if index < population_size {
+            values[index] = Some(noisy.clone());
+        }
+    }
+    let mut noisy = Vec::with_capacity(population_size);
+    for (index, value) in IntoIterator::into_iter(values).enumerate() {
+        noisy.push(try!(value.ok_or(FitsError::MissingFitness { index: index, })));
+    }
+    Ok(noisy)
+}
+
+impl<P> PopulationFit for NoisyPopulationFit<P> where P: Policy {
+    type Exec = P::Exec;
+    type Indiv = P::Indiv;
+    type Pop = P::Pop;
+    type Fit = Noisy;
+    type Fits = P::Fits;
+    type Err = ErrorP<P>;
+
+    fn fit<WA>(&self, population: Arc<Self::Pop>, exec: &mut Self::Exec) -> Result<Self::Fits, Self::Err>
+        where WA: WorkAmount, <Self::Exec as Executor>::JIB: JobIterBuild<WA>
+    {
+        let population_size = population.size();
+        let current = Arc::new(vec![Noisy::new(self.aggregate); population_size]);
+        let extra = Arc::new(vec![self.samples; population_size]);
+        self.sample::<WA>(population, current, extra, exec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::cmp::Ordering;
+    use rand::{self, Rng};
+    use par_exec::{Executor, WorkAmount};
+    use par_exec::par::{ParallelExecutor, Alternately, ByEqualChunks};
+    use super::super::super::super::set;
+    use super::super::super::super::set::sort::{RetrieveSortManager, RetrieveSetManager, sort};
+    use super::super::PopulationFit;
+    use super::super::super::individual::IndividualManager;
+    use super::super::standard::{RetrieveFitsManager, RetrieveIndividualManager};
+    use super::{Policy, NoisyPopulationFit, Noisy, Aggregate, Race, noisy_by_index, sort_pred};
+
+    fn noisy(aggregate: Aggregate, samples: &[f64]) -> Noisy {
+        let mut noisy = Noisy::new(aggregate);
+        for &sample in samples {
+            noisy.add(sample);
+        }
+        noisy
+    }
+
+    #[test]
+    fn aggregates_and_races() {
+        let samples = [4.0, 1.0, 3.0, 2.0];
+        assert_eq!(noisy(Aggregate::Mean, &samples).value(), 2.5);
+        assert_eq!(noisy(Aggregate::Median, &samples).value(), 2.5);
+        assert_eq!(noisy(Aggregate::Quantile(1.0 / 3.0), &samples).value(), 2.0);
+        assert_eq!(noisy(Aggregate::Quantile(1.0), &samples).value(), 4.0);
+
+        let low = noisy(Aggregate::Mean, &[0.9, 1.1, 1.0, 0.95, 1.05]);
+        let high = noisy(Aggregate::Mean, &[1.9, 2.1, 2.0, 1.95, 2.05]);
+        let close = noisy(Aggregate::Mean, &[0.2, 2.0]);
+        let welch = Race::Welch { critical: 1.96, };
+        assert_eq!(welch.compare(&low, &high), Some(Ordering::Less));
+        assert_eq!(welch.compare(&high, &low), Some(Ordering::Greater));
+        assert_eq!(welch.compare(&low, &close), None);
+        let hoeffding = Race::Hoeffding { range: 0.2, delta: 0.05, };
+        assert_eq!(hoeffding.compare(&low, &high), Some(Ordering::Less));
+        assert_eq!(Race::Hoeffding { range: 2.0, delta: 0.05, }.compare(&low, &high), None);
+    }
+
+    struct IndivManager;
+    impl IndividualManager for IndivManager {
+        type I = usize;
+        type FI = f64;
+        type E = ();
+
+        fn generate(&mut self, index: usize) -> Result<Self::I, Self::E> {
+            Ok(index)
+        }
+
+        fn fitness(&mut self, indiv: &Self::I) -> Result<Self::FI, Self::E> {
+            // noise wider than half the spacing, so a couple of samples cannot sort the population
+            Ok(*indiv as f64 + rand::thread_rng().gen_range(-0.8, 0.8))
+        }
+    }
+
+    struct LocalContext {
+        fits_manager: set::vec::Manager<(Noisy, usize)>,
+        indices_manager: set::vec::Manager<usize>,
+        indiv_manager: IndivManager,
+    }
+
+    impl RetrieveFitsManager for LocalContext {
+        type FitsM = set::vec::Manager<(Noisy, usize)>;
+
+        fn retrieve(&mut self) -> &mut Self::FitsM {
+            &mut self.fits_manager
+        }
+    }
+
+    impl RetrieveIndividualManager for LocalContext {
+        type IM = IndivManager;
+
+        fn retrieve(&mut self) -> &mut Self::IM {
+            &mut self.indiv_manager
+        }
+    }
+
+    impl RetrieveSortManager for LocalContext {
+        type SortM = set::vec::Manager<usize>;
+
+        fn retrieve(&mut self) -> &mut Self::SortM {
+            &mut self.indices_manager
+        }
+    }
+
+    impl RetrieveSetManager for LocalContext {
+        type SetM = set::vec::Manager<usize>;
+
+        fn retrieve(&mut self) -> &mut Self::SetM {
+            &mut self.indices_manager
+        }
+    }
+
+    struct TestPolicy;
+    impl Policy for TestPolicy {
+        type LocalContext = LocalContext;
+        type Exec = ParallelExecutor<LocalContext>;
+
+        type Indiv = usize;
+        type IndivME = ();
+        type IndivM = IndivManager;
+
+        type PopE = set::vec::Error;
+        type Pop = Vec<usize>;
+
+        type FitsE = set::vec::Error;
+        type Fits = Vec<(Noisy, usize)>;
+        type FitsME = ();
+        type FitsM = set::vec::Manager<(Noisy, usize)>;
+    }
+
+    #[test]
+    fn resampled_sort() {
+        let exec: ParallelExecutor<_> = Default::default();
+        let mut exec = exec.start(|| LocalContext {
+            fits_manager: set::vec::Manager::new(),
+            indices_manager: set::vec::Manager::new(),
+            indiv_manager: IndivManager,
+        }).unwrap();
+
+        let total = 64;
+        let population = Arc::new((0 .. total).rev().collect::<Vec<usize>>());
+        let fitness_calculator: NoisyPopulationFit<TestPolicy> = NoisyPopulationFit::new(2, Aggregate::Median);
+        let fits = fitness_calculator.fit::<Alternately>(population.clone(), &mut exec).unwrap();
+        assert!(fits.iter().all(|&(ref noisy, _)| noisy.count() == 2));
+
+        let spent = |fits: &Vec<(Noisy, usize)>| fits.iter().map(|&(ref noisy, _)| noisy.count() - 2).sum::<usize>();
+        let budget = total * 200;
+
+        // a race which can never tell the individuals apart spends the whole budget
+        let blind = Race::Hoeffding { range: 100.0, delta: 0.05, };
+        let blind_fits = fitness_calculator.resample::<Alternately>(population.clone(), fits.clone(), blind, budget, &mut exec).unwrap();
+        assert_eq!(spent(&blind_fits), budget);
+
+        // the proper one stops as soon as all the neighbouring ranks are decided
+        let race = Race::Hoeffding { range: 1.6, delta: 0.05, };
+        let fits = fitness_calculator.resample::<Alternately>(population.clone(), fits, race, budget, &mut exec).unwrap();
+        assert!(spent(&fits) > 0 && spent(&fits) < budget);
+
+        let values = Arc::new(noisy_by_index(&fits, total).unwrap());
+        let sorted_indices = sort(ByEqualChunks::new(total), sort_pred(values.clone()), &mut exec).unwrap();
+        assert_eq!(sorted_indices, (0 .. total).rev().collect::<Vec<_>>());
+        assert!(sorted_indices.windows(2).all(|pair| race.compare(&values[pair[0]], &values[pair[1]]) == Some(Ordering::Less)));
+    }
+}
@@ -1,9 +1,12 @@
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use std::marker::PhantomData;
 use par_exec::{Executor, WorkAmount, JobIterBuild, ExecutorJobError, JobExecuteError};
 
 use super::PopulationFit;
-use super::super::individual::IndividualManager;
+use super::cache::{CacheManager, RetrieveCacheManager};
+use super::super::individual::{IndividualManager, CancelToken};
+use super::super::repair::{Repair, RepairManager, RetrieveRepairManager};
 use super::super::super::set::{Set, SetManager};
 use super::super::super::set::union;
 
@@ -20,13 +23,17 @@ pub trait RetrieveIndividualManager {
 }
 
 pub trait Policy {
-    type LocalContext: RetrieveFitsManager<FitsM = Self::FitsM> + RetrieveIndividualManager<IM = Self::IndivM>;
+    type LocalContext: RetrieveFitsManager<FitsM = Self::FitsM> + RetrieveIndividualManager<IM = Self::IndivM> + RetrieveRepairManager<RM = Self::RepairM> +
+        RetrieveCacheManager<CM = Self::CacheM>;
     type Exec: Executor<LC = Self::LocalContext>;
 
     type Indiv;
-    type Fit;
+    type Fit: Clone + Send + Sync + 'static;
     type IndivME: Send + 'static;
     type IndivM: IndividualManager<I = Self::Indiv, FI = Self::Fit, E = Self::IndivME>;
+    type RepairME: Send + 'static;
+    type RepairM: RepairManager<I = Self::Indiv, E = Self::RepairME>;
+    type CacheM: CacheManager<I = Self::Indiv, FI = Self::Fit>;
 
     type PopE: Send + 'static;
     type Pop: Set<T = Self::Indiv, E = Self::PopE> + Sync + Send + 'static;
@@ -37,46 +44,172 @@ pub trait Policy {
     type FitsM: SetManager<S = Self::Fits, E = Self::FitsME>;
 }
 
+#[derive(Clone, PartialEq, Debug)]
+pub enum OnTimeout<F> {
+    // the individual gets this fitness value (it is not cached)
+    Worst(F),
+    // evaluation fails with `FitnessError::Timeout`
+    Fail,
+}
+
+// time bound of a single fitness evaluation: the manager receives a cancel token expiring after `limit`,
+// an evaluation finished later than that is treated as timed out as well
+#[derive(Clone, PartialEq, Debug)]
+pub struct Timeout<F> {
+    pub limit: Duration,
+    pub on_timeout: OnTimeout<F>,
+}
+
+// what happens to an individual whose evaluation failed or timed out with `OnTimeout::Fail`
+#[derive(Clone, PartialEq, Debug)]
+pub enum OnFailure<F> {
+    // the whole fit fails
+    Abort,
+    // the individual gets this fitness value
+    Penalty(F),
+    // the individual is missing from the fits set
+    Drop,
+}
+
+// failed evaluation tolerated by `OnFailure::Penalty` or `OnFailure::Drop`
+#[derive(Debug)]
+pub enum Failure<IME> {
+    IndividualManager { index: usize, error: IME, },
+    Timeout { index: usize, },
+}
+
 pub struct StandardPopulationFit<P> where P: Policy {
+    repair: Repair,
+    timeout: Option<Arc<Timeout<P::Fit>>>,
+    on_failure: Arc<OnFailure<P::Fit>>,
     _marker: PhantomData<P>,
 }
 
 impl<P> StandardPopulationFit<P> where P: Policy {
     pub fn new() -> StandardPopulationFit<P> {
+        StandardPopulationFit::with_repair(Repair::WriteBack)
+    }
+
+    pub fn with_repair(repair: Repair) -> StandardPopulationFit<P> {
+        StandardPopulationFit::with_config(repair, None, OnFailure::Abort)
+    }
+
+    pub fn with_timeout(repair: Repair, timeout: Timeout<P::Fit>) -> StandardPopulationFit<P> {
+        StandardPopulationFit::with_config(repair, Some(timeout), OnFailure::Abort)
+    }
+
+    pub fn with_config(repair: Repair, timeout: Option<Timeout<P::Fit>>, on_failure: OnFailure<P::Fit>) -> StandardPopulationFit<P> {
         StandardPopulationFit {
+            repair: repair,
+            timeout: timeout.map(Arc::new),
+            on_failure: Arc::new(on_failure),
             _marker: PhantomData,
         }
     }
 }
 
 #[derive(Debug)]
-pub enum FitnessError<PE, FE, FME, IME> {
+pub enum FitnessError<PE, FE, FME, IME, RME> {
     Population(PE),
     FitsSet(FE),
     FitsSetManager(FME),
     IndividualManager(IME),
+    RepairManager(RME),
+    Timeout { index: usize, },
+    BatchSizeMismatch { expected: usize, received: usize, },
 }
 
 #[derive(Debug)]
-pub enum Error<ExecE, PopE, FitsE, FitsME, IndivME> {
+pub enum Error<ExecE, PopE, FitsE, FitsME, IndivME, RepairME> {
     NoOutputFitnessValues,
-    Executor(ExecutorJobError<ExecE, JobExecuteError<FitnessError<PopE, FitsE, FitsME, IndivME>, union::Error<FitsE, FitsME>>>),
+    Executor(ExecutorJobError<ExecE, JobExecuteError<FitnessError<PopE, FitsE, FitsME, IndivME, RepairME>, union::Error<FitsE, FitsME>>>),
 }
 
-pub type ErrorP<P> where P: Policy = Error<<P::Exec as Executor>::E, P::PopE, P::FitsE, P::FitsME, P::IndivME>;
+pub type ErrorP<P> where P: Policy = Error<<P::Exec as Executor>::E, P::PopE, P::FitsE, P::FitsME, P::IndivME, P::RepairME>;
+
+pub type FitnessErrorP<P> where P: Policy = FitnessError<P::PopE, P::FitsE, P::FitsME, P::IndivME, P::RepairME>;
+
+// fitness of the individual at `index`: repaired copy and cache are consulted before the individual manager
+pub fn evaluate<P>(local_context: &mut P::LocalContext, repair: Repair, timeout: Option<&Timeout<P::Fit>>, index: usize, indiv: &P::Indiv) ->
+    Result<P::Fit, FitnessErrorP<P>> where P: Policy
+{
+    let repaired = {
+        let repair_manager = <P::LocalContext as RetrieveRepairManager>::retrieve(local_context);
+        try!(repair.evaluate(repair_manager, indiv).map_err(FitnessError::RepairManager))
+    };
+    let evaluated = repaired.as_ref().unwrap_or(indiv);
+    if let Some(fitness) = <P::LocalContext as RetrieveCacheManager>::retrieve(local_context).lookup(evaluated) {
+        return Ok(fitness);
+    }
+    compute::<P>(local_context, timeout, index, evaluated)
+}
 
-impl<P> PopulationFit for StandardPopulationFit<P> where P: Policy {
-    type Exec = P::Exec;
-    type Indiv = P::Indiv;
-    type Pop = P::Pop;
-    type Fit = P::Fit;
-    type Fits = P::Fits;
-    type Err = ErrorP<P>;
+// asks the individual manager and caches the result
+fn compute<P>(local_context: &mut P::LocalContext, timeout: Option<&Timeout<P::Fit>>, index: usize, evaluated: &P::Indiv) ->
+    Result<P::Fit, FitnessErrorP<P>> where P: Policy
+{
+    let fitness = {
+        let indiv_manager = <P::LocalContext as RetrieveIndividualManager>::retrieve(local_context);
+        match timeout {
+            None =>
+                try!(indiv_manager.fitness(evaluated).map_err(FitnessError::IndividualManager)),
+            Some(timeout) => {
+                let token = CancelToken::with_deadline(Instant::now() + timeout.limit);
+                match try!(indiv_manager.fitness_cancellable(evaluated, &token).map_err(FitnessError::IndividualManager)) {
+                    Some(fitness) if !token.is_cancelled() =>
+                        fitness,
+                    _ => return match timeout.on_timeout {
+                        OnTimeout::Worst(ref worst) => Ok(worst.clone()),
+                        OnTimeout::Fail => Err(FitnessError::Timeout { index: index, }),
+                    },
+                }
+            },
+        }
+    };
+    <P::LocalContext as RetrieveCacheManager>::retrieve(local_context).store(evaluated, &fitness);
+    Ok(fitness)
+}
 
-    fn fit<WA>(&self, population: Arc<Self::Pop>, exec: &mut Self::Exec) -> Result<Self::Fits, Self::Err>
-        where WA: WorkAmount, <Self::Exec as Executor>::JIB: JobIterBuild<WA>
+fn is_abort<F>(on_failure: &OnFailure<F>) -> bool {
+    match *on_failure { OnFailure::Abort => true, _ => false, }
+}
+
+// puts the outcome of an evaluation into the fits set or into the failures according to the `OnFailure` policy
+fn settle<P>(outcome: Result<P::Fit, FitnessErrorP<P>>,
+             index: usize,
+             on_failure: &OnFailure<P::Fit>,
+             fitness_results: &mut P::Fits,
+             failures: &mut Vec<Failure<P::IndivME>>)
+             -> Result<(), FitnessErrorP<P>> where P: Policy
+{
+    let failure = match (outcome, on_failure) {
+        (Ok(fitness), _) =>
+            return fitness_results.add((fitness, index)).map_err(FitnessError::FitsSet),
+        (Err(e), &OnFailure::Abort) =>
+            return Err(e),
+        (Err(FitnessError::IndividualManager(e)), _) =>
+            Failure::IndividualManager { index: index, error: e, },
+        (Err(FitnessError::Timeout { index, }), _) =>
+            Failure::Timeout { index: index, },
+        (Err(e), _) =>
+            return Err(e),
+    };
+    failures.push(failure);
+    if let OnFailure::Penalty(ref penalty) = *on_failure {
+        try!(fitness_results.add((penalty.clone(), index)).map_err(FitnessError::FitsSet));
+    }
+    Ok(())
+}
+
+impl<P> StandardPopulationFit<P> where P: Policy {
+    // like `PopulationFit::fit` but also returns the failures tolerated according to the `OnFailure` policy
+    pub fn fit_with_failures<WA>(&self, population: Arc<P::Pop>, exec: &mut P::Exec) -> Result<(P::Fits, Vec<Failure<P::IndivME>>), ErrorP<P>>
+        where WA: WorkAmount, <P::Exec as Executor>::JIB: JobIterBuild<WA>
     {
         let population_size = population.size();
+        let repair = self.repair;
+        let timeout = self.timeout.clone();
+        let on_failure = self.on_failure.clone();
         match exec.try_execute_job(
             WA::new(population_size),
             move |local_context, input_indices| {
@@ -84,15 +217,67 @@ impl<P> PopulationFit for StandardPopulationFit<P> where P: Policy {
                     let mut set_manager = <P::LocalContext as RetrieveFitsManager>::retrieve(local_context);
                     try!(set_manager.make_set(Some(population_size)).map_err(FitnessError::FitsSetManager))
                 };
-                let mut indiv_manager = <P::LocalContext as RetrieveIndividualManager>::retrieve(local_context);
+                let mut failures = Vec::new();
+                let timeout = timeout.as_ref().map(|t| &**t);
+
+                // batches are not cancellable, so they are used only when evaluations are not time bounded
+                let batched = timeout.is_none() &&
+                    <P::LocalContext as RetrieveIndividualManager>::retrieve(local_context).supports_batch();
+                if !batched {
+                    for index in input_indices {
+                        let indiv = try!(population.get(index).map_err(FitnessError::Population));
+                        let outcome = evaluate::<P>(local_context, repair, timeout, index, indiv);
+                        try!(settle::<P>(outcome, index, &on_failure, &mut fitness_results, &mut failures));
+                    }
+                    return Ok((fitness_results, failures));
+                }
+
+                // cache hits are settled right away, the rest of the chunk is evaluated together
+                let mut pending = Vec::new();
                 for index in input_indices {
                     let indiv = try!(population.get(index).map_err(FitnessError::Population));
-                    let fitness = try!(indiv_manager.fitness(indiv).map_err(FitnessError::IndividualManager));
-                    try!(fitness_results.add((fitness, index)).map_err(FitnessError::FitsSet));
+                    let repaired = {
+                        let repair_manager = <P::LocalContext as RetrieveRepairManager>::retrieve(local_context);
+                        try!(repair.evaluate(repair_manager, indiv).map_err(FitnessError::RepairManager))
+                    };
+                    match <P::LocalContext as RetrieveCacheManager>::retrieve(local_context).lookup(repaired.as_ref().unwrap_or(indiv)) {
+                        Some(fitness) => try!(settle::<P>(Ok(fitness), index, &on_failure, &mut fitness_results, &mut failures)),
+                        None => pending.push((index, indiv, repaired)),
+                    }
+                }
+                if pending.is_empty() {
+                    return Ok((fitness_results, failures));
+                }
+
+                let batch = {
+                    let indivs: Vec<_> = pending.iter().map(|&(_, indiv, ref repaired)| repaired.as_ref().unwrap_or(indiv)).collect();
+                    <P::LocalContext as RetrieveIndividualManager>::retrieve(local_context).fitness_batch(&indivs)
+                };
+                match batch {
+                    Ok(fits) => {
+                        if fits.len() != pending.len() {
+                            return Err(FitnessError::BatchSizeMismatch { expected: pending.len(), received: fits.len(), });
+                        }
+                        for ((index, indiv, repaired), fitness) in IntoIterator::into_iter(pending).zip(fits) {
+                            <P::LocalContext as RetrieveCacheManager>::retrieve(local_context).store(repaired.as_ref().unwrap_or(indiv), &fitness);
+                            try!(settle::<P>(Ok(fitness), index, &on_failure, &mut fitness_results, &mut failures));
+                        }
+                    },
+                    Err(e) if is_abort(&on_failure) =>
+                        return Err(FitnessError::IndividualManager(e)),
+                    // failed batch under a tolerant policy: one by one, so that failing individuals are told apart
+                    Err(_) => for (index, indiv, repaired) in pending {
+                        let outcome = compute::<P>(local_context, None, index, repaired.as_ref().unwrap_or(indiv));
+                        try!(settle::<P>(outcome, index, &on_failure, &mut fitness_results, &mut failures));
+                    },
                 }
-                Ok(fitness_results)
+                Ok((fitness_results, failures))
             },
-            move |local_context, fits_a, fits_b| union::union(<P::LocalContext as RetrieveFitsManager>::retrieve(local_context), fits_a, fits_b))
+            move |local_context, (fits_a, mut failures_a), (fits_b, failures_b)| {
+                let fits = try!(union::union(<P::LocalContext as RetrieveFitsManager>::retrieve(local_context), fits_a, fits_b));
+                failures_a.extend(failures_b);
+                Ok((fits, failures_a))
+            })
         {
             Ok(None) => Err(Error::NoOutputFitnessValues),
             Ok(Some(fitness_results)) => Ok(fitness_results),
@@ -101,16 +286,41 @@ impl<P> PopulationFit for StandardPopulationFit<P> where P: Policy {
     }
 }
 
+impl<P> PopulationFit for StandardPopulationFit<P> where P: Policy {
+    type Exec = P::Exec;
+    type Indiv = P::Indiv;
+    type Pop = P::Pop;
+    type Fit = P::Fit;
+    type Fits = P::Fits;
+    type Err = ErrorP<P>;
+
+    // tolerated failures are discarded, see `fit_with_failures`
+    fn fit<WA>(&self, population: Arc<Self::Pop>, exec: &mut Self::Exec) -> Result<Self::Fits, Self::Err>
+        where WA: WorkAmount, <Self::Exec as Executor>::JIB: JobIterBuild<WA>
+    {
+        self.fit_with_failures::<WA>(population, exec).map(|(fits, _)| fits)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use par_exec::Executor;
     use par_exec::par::{ParallelExecutor, Alternately};
     use super::super::super::super::set;
     use super::super::PopulationFit;
-    use super::super::super::individual::IndividualManager;
-    use super::{Policy, StandardPopulationFit, RetrieveFitsManager, RetrieveIndividualManager};
+    use std::time::Duration;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use super::super::super::individual::{IndividualManager, CancelToken};
+    use super::super::super::repair::{Repair, NoRepair, RetrieveRepairManager};
+    use super::super::cache::{CacheManager, NoCache, SharedCache, RetrieveCacheManager};
+    use super::{Policy, StandardPopulationFit, RetrieveFitsManager, RetrieveIndividualManager, Timeout, OnTimeout, OnFailure, Failure};
+
+    // batch capable when it has a counter of batch calls
+    struct IndivManager {
+        batches: Option<Arc<AtomicUsize>>,
+    }
 
-    struct IndivManager;
     impl IndividualManager for IndivManager {
         type I = usize;
         type FI = f64;
@@ -120,17 +330,46 @@ mod tests {
             Ok(index)
         }
 
+        // individuals above 10000 are broken
         fn fitness(&mut self, indiv: &Self::I) -> Result<Self::FI, Self::E> {
-            Ok(1.0 / *indiv as f64)
+            if *indiv > 10000 {
+                Err(())
+            } else {
+                Ok(1.0 / *indiv as f64)
+            }
+        }
+
+        // every individual ending with 7 never finishes on its own
+        fn fitness_cancellable(&mut self, indiv: &Self::I, token: &CancelToken) -> Result<Option<Self::FI>, Self::E> {
+            if *indiv % 10 != 7 {
+                return self.fitness(indiv).map(Some);
+            }
+            while !token.is_cancelled() {
+                ::std::thread::sleep(Duration::from_millis(1));
+            }
+            Ok(None)
+        }
+
+        fn supports_batch(&self) -> bool {
+            self.batches.is_some()
+        }
+
+        fn fitness_batch(&mut self, indivs: &[&Self::I]) -> Result<Vec<Self::FI>, Self::E> {
+            if let Some(ref batches) = self.batches {
+                batches.fetch_add(1, Ordering::SeqCst);
+            }
+            indivs.iter().map(|indiv| self.fitness(indiv)).collect()
         }
     }
 
-    struct LocalContext {
+    struct LocalContext<CM> {
         set_manager: set::vec::Manager<(f64, usize)>,
         indiv_manager: IndivManager,
+        repair_manager: NoRepair<usize>,
+        cache_manager: CM,
     }
 
-    impl RetrieveFitsManager for LocalContext {
+    impl<CM> RetrieveFitsManager for LocalContext<CM> {
         type FitsM = set::vec::Manager<(f64, usize)>;
 
         fn retrieve(&mut self) -> &mut Self::FitsM {
@@ -138,7 +377,7 @@ mod tests {
         }
     }
 
-    impl RetrieveIndividualManager for LocalContext {
+    impl<CM> RetrieveIndividualManager for LocalContext<CM> {
         type IM = IndivManager;
 
         fn retrieve(&mut self) -> &mut Self::IM {
@@ -146,15 +385,34 @@ mod tests {
         }
     }
 
-    struct TestPolicy;
-    impl Policy for TestPolicy {
-        type LocalContext = LocalContext;
-        type Exec = ParallelExecutor<LocalContext>;
+    impl<CM> RetrieveRepairManager for LocalContext<CM> {
+        type RM = NoRepair<usize>;
+
+        fn retrieve(&mut self) -> &mut Self::RM {
+            &mut self.repair_manager
+        }
+    }
+
+    impl<CM> RetrieveCacheManager for LocalContext<CM> {
+        type CM = CM;
+
+        fn retrieve(&mut self) -> &mut Self::CM {
+            &mut self.cache_manager
+        }
+    }
+
+    struct TestPolicy<CM>(::std::marker::PhantomData<CM>);
+    impl<CM> Policy for TestPolicy<CM> where CM: CacheManager<I = usize, FI = f64> + Send + 'static {
+        type LocalContext = LocalContext<CM>;
+        type Exec = ParallelExecutor<LocalContext<CM>>;
 
         type Indiv = usize;
         type Fit = f64;
         type IndivME = ();
         type IndivM = IndivManager;
+        type RepairME = ();
+        type RepairM = NoRepair<usize>;
+        type CacheM = CM;
 
         type PopE = set::vec::Error;
         type Pop = Vec<usize>;
@@ -170,17 +428,141 @@ mod tests {
         let exec: ParallelExecutor<_> = Default::default();
         let mut exec = exec.start(|| LocalContext {
             set_manager: set::vec::Manager::new(),
-            indiv_manager: IndivManager,
+            indiv_manager: IndivManager { batches: None, },
+            repair_manager: NoRepair::new(),
+            cache_manager: NoCache::new(),
         }).unwrap();
 
-        use std::sync::Arc;
         let population = Arc::new((0 .. 1024).collect::<Vec<_>>());
 
-        let fitness_calculator: StandardPopulationFit<TestPolicy> =
+        let fitness_calculator: StandardPopulationFit<TestPolicy<NoCache<usize, f64>>> =
             StandardPopulationFit::new();
         let mut fit_results =
             fitness_calculator.fit::<Alternately>(population.clone(), &mut exec).unwrap();
         fit_results.sort_by_key(|v| v.1);
         assert_eq!(Arc::new(fit_results.into_iter().map(|(_, i)| i).collect::<Vec<_>>()), population);
     }
+
+    fn residue(indiv: &usize) -> usize {
+        *indiv % 16
+    }
+
+    #[test]
+    fn cached_fitness() {
+        // fitness depends on the key only, so duplicates may be served from the cache
+        let cache = SharedCache::new(16, residue);
+        let exec: ParallelExecutor<_> = Default::default();
+        let worker_cache = cache.clone();
+        let mut exec = exec.start(move || LocalContext {
+            set_manager: set::vec::Manager::new(),
+            indiv_manager: IndivManager { batches: None, },
+            repair_manager: NoRepair::new(),
+            cache_manager: worker_cache.clone(),
+        }).unwrap();
+
+        let population = Arc::new((0 .. 1024).map(|i| 1 + i % 16).collect::<Vec<_>>());
+
+        let fitness_calculator: StandardPopulationFit<TestPolicy<SharedCache<usize, usize, f64>>> =
+            StandardPopulationFit::new();
+        let mut fit_results =
+            fitness_calculator.fit::<Alternately>(population.clone(), &mut exec).unwrap();
+        fit_results.sort_by_key(|v| v.1);
+        assert!(fit_results.iter().all(|&(fit, i)| fit == 1.0 / population[i] as f64));
+
+        let stats = cache.stats();
+        assert_eq!(stats.hits + stats.misses, 1024);
+        assert!(stats.misses >= 16 && stats.hits > 0);
+        assert_eq!(stats.entries, 16);
+    }
+
+    #[test]
+    fn timed_out_fitness() {
+        let exec: ParallelExecutor<_> = Default::default();
+        let mut exec = exec.start(|| LocalContext {
+            set_manager: set::vec::Manager::new(),
+            indiv_manager: IndivManager { batches: None, },
+            repair_manager: NoRepair::new(),
+            cache_manager: NoCache::new(),
+        }).unwrap();
+
+        let population = Arc::new((1 .. 65).collect::<Vec<_>>());
+
+        let worst = Timeout { limit: Duration::from_millis(50), on_timeout: OnTimeout::Worst(-1.0), };
+        let fitness_calculator: StandardPopulationFit<TestPolicy<NoCache<usize, f64>>> =
+            StandardPopulationFit::with_timeout(Repair::WriteBack, worst);
+        let fit_results = fitness_calculator.fit::<Alternately>(population.clone(), &mut exec).unwrap();
+        assert_eq!(fit_results.len(), 64);
+        for &(fit, index) in fit_results.iter() {
+            assert_eq!(fit, if population[index] % 10 == 7 { -1.0 } else { 1.0 / population[index] as f64 });
+        }
+
+        let fail = Timeout { limit: Duration::from_millis(50), on_timeout: OnTimeout::Fail, };
+        let fitness_calculator: StandardPopulationFit<TestPolicy<NoCache<usize, f64>>> =
+            StandardPopulationFit::with_timeout(Repair::WriteBack, fail);
+        assert!(fitness_calculator.fit::<Alternately>(population, &mut exec).is_err());
+    }
+
+    #[test]
+    fn tolerated_failures() {
+        let exec: ParallelExecutor<_> = Default::default();
+        let mut exec = exec.start(|| LocalContext {
+            set_manager: set::vec::Manager::new(),
+            indiv_manager: IndivManager { batches: None, },
+            repair_manager: NoRepair::new(),
+            cache_manager: NoCache::new(),
+        }).unwrap();
+
+        let population = Arc::new((1 .. 65).map(|i| if i % 8 == 0 { 10000 + i } else { i }).collect::<Vec<_>>());
+
+        let fitness_calculator: StandardPopulationFit<TestPolicy<NoCache<usize, f64>>> =
+            StandardPopulationFit::with_config(Repair::WriteBack, None, OnFailure::Penalty(-5.0));
+        let (fit_results, failures) = fitness_calculator.fit_with_failures::<Alternately>(population.clone(), &mut exec).unwrap();
+        assert_eq!(fit_results.len(), 64);
+        assert_eq!(fit_results.iter().filter(|&&(fit, _)| fit == -5.0).count(), 8);
+        let mut failed: Vec<_> = failures.into_iter().map(|failure| match failure {
+            Failure::IndividualManager { index, error: (), } => index,
+            Failure::Timeout { .. } => panic!("unexpected timeout"),
+        }).collect();
+        failed.sort();
+        assert_eq!(failed, (0 .. 8).map(|i| i * 8 + 7).collect::<Vec<_>>());
+
+        let fitness_calculator: StandardPopulationFit<TestPolicy<NoCache<usize, f64>>> =
+            StandardPopulationFit::with_config(Repair::WriteBack, None, OnFailure::Drop);
+        let fit_results = fitness_calculator.fit::<Alternately>(population.clone(), &mut exec).unwrap();
+        assert_eq!(fit_results.len(), 56);
+        assert!(fit_results.iter().all(|&(_, index)| population[index] < 10000));
+
+        let fitness_calculator: StandardPopulationFit<TestPolicy<NoCache<usize, f64>>> = StandardPopulationFit::new();
+        assert!(fitness_calculator.fit::<Alternately>(population, &mut exec).is_err());
+    }
+
+    #[test]
+    fn batch_fitness() {
+        let batches = Arc::new(AtomicUsize::new(0));
+        let exec: ParallelExecutor<_> = Default::default();
+        let worker_batches = batches.clone();
+        let mut exec = exec.start(move || LocalContext {
+            set_manager: set::vec::Manager::new(),
+            indiv_manager: IndivManager { batches: Some(worker_batches.clone()), },
+            repair_manager: NoRepair::new(),
+            cache_manager: NoCache::new(),
+        }).unwrap();
+
+        // every worker evaluates its whole chunk with a single call
+        let population = Arc::new((1 .. 1025).collect::<Vec<_>>());
+        let fitness_calculator: StandardPopulationFit<TestPolicy<NoCache<usize, f64>>> = StandardPopulationFit::new();
+        let fit_results = fitness_calculator.fit::<Alternately>(population.clone(), &mut exec).unwrap();
+        assert_eq!(fit_results.len(), 1024);
+        assert!(fit_results.iter().all(|&(fit, i)| fit == 1.0 / population[i] as f64));
+        let calls = batches.load(Ordering::SeqCst);
+        assert!(calls > 0 && calls < 1024);
+
+        // a failed batch is retried one by one to tell the broken individuals apart
+        let population = Arc::new((1 .. 65).map(|i| if i % 8 == 0 { 10000 + i } else { i }).collect::<Vec<_>>());
+        let fitness_calculator: StandardPopulationFit<TestPolicy<NoCache<usize, f64>>> =
+            StandardPopulationFit::with_config(Repair::WriteBack, None, OnFailure::Drop);
+        let (fit_results, failures) = fitness_calculator.fit_with_failures::<Alternately>(population, &mut exec).unwrap();
+        assert_eq!(fit_results.len(), 56);
+        assert_eq!(failures.len(), 8);
+    }
 }
@@ -0,0 +1,392 @@
+use std::cmp::{self, Ordering};
+use std::sync::{Arc, Mutex};
+use par_exec::{Executor, WorkAmount, JobIterBuild, ExecutorJobError, JobExecuteError};
+
+pub mod model;
+
+use self::model::Model;
+use super::PopulationFit;
+use super::standard::{RetrieveFitsManager, RetrieveIndividualManager};
+use super::super::individual::IndividualManager;
+use super::super::super::set::{Set, SetManager};
+use super::super::super::set::union;
+
+// surrogate assisted fitness: smaller values are better throughout
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Estimate {
+    pub value: f64,
+    // computed by the individual manager rather than predicted by the model
+    pub exact: bool,
+}
+
+// evolution control: which individuals get the expensive exact evaluation once the model is trained
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Control {
+    // every generation the `fraction` of the population with the best predictions
+    Individual { fraction: f64, },
+    // the whole population every `period`-th generation, none in between
+    Generation { period: usize, },
+}
+
+impl Control {
+    fn exact(&self, generation: usize, population_size: usize) -> usize {
+        match *self {
+            Control::Individual { fraction } =>
+                cmp::min(population_size, (fraction.max(0.0) * population_size as f64).ceil() as usize),
+            Control::Generation { period } =>
+                if period <= 1 || generation % period == 0 { population_size } else { 0 },
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Params {
+    pub control: Control,
+    // exact samples required before the model is used
+    pub min_samples: usize,
+    // most recent exact samples the model is trained on
+    pub max_samples: usize,
+}
+
+// model quality measured on the exact evaluations of a generation which also had a prediction
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Accuracy {
+    pub generation: usize,
+    pub samples: usize,
+    pub mean_absolute_error: f64,
+    // fraction of sample pairs ranked the same way by the predictions and the exact values
+    pub rank_agreement: f64,
+}
+
+impl Accuracy {
+    // `pairs` are (predicted, exact) values
+    pub fn measure(generation: usize, pairs: &[(f64, f64)]) -> Option<Accuracy> {
+        if pairs.is_empty() {
+            return None;
+        }
+        let error = pairs.iter().map(|&(predicted, exact)| (predicted - exact).abs()).sum::<f64>();
+        let (mut agreed, mut compared) = (0, 0);
+        for i in 0 .. pairs.len() {
+            for j in i + 1 .. pairs.len() {
+                compared += 1;
+                if (pairs[i].0 < pairs[j].0) == (pairs[i].1 < pairs[j].1) {
+                    agreed += 1;
+                }
+            }
+        }
+        Some(Accuracy {
+            generation: generation,
+            samples: pairs.len(),
+            mean_absolute_error: error / pairs.len() as f64,
+            rank_agreement: if compared == 0 { 1.0 } else { agreed as f64 / compared as f64 },
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum PrescreenError<PE, ME> {
+    Population(PE),
+    Model(ME),
+}
+
+// (index, predicted fitness) of every candidate, best predictions first
+pub fn prescreen<M, S>(model: &mut M, candidates: &S) -> Result<Vec<(usize, f64)>, PrescreenError<S::E, M::E>> where
+    M: Model,
+    S: Set<T = M::I>
+{
+    let mut predictions = Vec::with_capacity(candidates.size());
+    for index in 0 .. candidates.size() {
+        let indiv = try!(candidates.get(index).map_err(PrescreenError::Population));
+        predictions.push((index, try!(model.predict(indiv).map_err(PrescreenError::Model))));
+    }
+    predictions.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal));
+    Ok(predictions)
+}
+
+pub trait Policy {
+    type LocalContext: RetrieveFitsManager<FitsM = Self::FitsM> + RetrieveIndividualManager<IM = Self::IndivM>;
+    type Exec: Executor<LC = Self::LocalContext>;
+
+    type Indiv: Clone;
+    type IndivME: Send + 'static;
+    type IndivM: IndividualManager<I = Self::Indiv, FI = f64, E = Self::IndivME>;
+
+    type ModelE;
+    type Model: Model<I = Self::Indiv, E = Self::ModelE>;
+
+    type PopE: Send + 'static;
+    type Pop: Set<T = Self::Indiv, E = Self::PopE> + Sync + Send + 'static;
+
+    type FitsE: Send + 'static;
+    type Fits: Set<T = (Estimate, usize), E = Self::FitsE> + Send + 'static;
+    type FitsME: Send + 'static;
+    type FitsM: SetManager<S = Self::Fits, E = Self::FitsME>;
+}
+
+#[derive(Debug)]
+pub enum FitnessError<PE, FE, FME, IME> {
+    Population(PE),
+    FitsSet(FE),
+    FitsSetManager(FME),
+    IndividualManager(IME),
+}
+
+#[derive(Debug)]
+pub enum Error<ExecE, PopE, FitsE, FitsME, IndivME, ModelE> {
+    NoOutputFitnessValues,
+    Population(PopE),
+    FitsSet(FitsE),
+    Prescreen(PrescreenError<PopE, ModelE>),
+    Executor(ExecutorJobError<ExecE, JobExecuteError<FitnessError<PopE, FitsE, FitsME, IndivME>, union::Error<FitsE, FitsME>>>),
+}
+
+pub type ErrorP<P> where P: Policy = Error<<P::Exec as Executor>::E, P::PopE, P::FitsE, P::FitsME, P::IndivME, P::ModelE>;
+
+struct State<P> where P: Policy {
+    model: P::Model,
+    trained: bool,
+    archive: Vec<(P::Indiv, f64)>,
+    generation: usize,
+    history: Vec<Accuracy>,
+}
+
+// Every `fit` call is a generation: the population is pre-screened by the model, the individuals chosen by
+// the `Control` strategy are evaluated exactly and the others keep their predictions. Exact values are
+// archived and the model is retrained on them for the next generation.
+pub struct SurrogatePopulationFit<P> where P: Policy {
+    params: Params,
+    state: Mutex<State<P>>,
+}
+
+impl<P> SurrogatePopulationFit<P> where P: Policy {
+    pub fn new(model: P::Model, params: Params) -> SurrogatePopulationFit<P> {
+        SurrogatePopulationFit {
+            params: params,
+            state: Mutex::new(State {
+                model: model,
+                trained: false,
+                archive: Vec::new(),
+                generation: 0,
+                history: Vec::new(),
+            }),
+        }
+    }
+
+    // one entry per generation in which the model was checked against exact evaluations
+    pub fn accuracy_history(&self) -> Vec<Accuracy> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).history.clone()
+    }
+
+    // `predicted[index]` is used as is, individuals without a prediction are evaluated exactly
+    fn evaluate<WA>(&self, population: Arc<P::Pop>, predicted: Arc<Vec<Option<f64>>>, exec: &mut P::Exec) ->
+        Result<P::Fits, ErrorP<P>> where WA: WorkAmount, <P::Exec as Executor>::JIB: JobIterBuild<WA>
+    {
+        let population_size = population.size();
+        match exec.try_execute_job(
+            WA::new(population_size),
+            move |local_context, input_indices| {
+                let mut fitness_results = {
+                    let set_manager = <P::LocalContext as RetrieveFitsManager>::retrieve(local_context);
+                    try!(set_manager.make_set(Some(population_size)).map_err(FitnessError::FitsSetManager))
+                };
+                for index in input_indices {
+                    let estimate = match predicted[index] {
+                        Some(value) =>
+                            Estimate { value: value, exact: false, },
+                        None => {
+                            let indiv = try!(population.get(index).map_err(FitnessError::Population));
+                            let indiv_manager = <P::LocalContext as RetrieveIndividualManager>::retrieve(local_context);
+                            Estimate { value: try!(indiv_manager.fitness(indiv).map_err(FitnessError::IndividualManager)), exact: true, }
+                        },
+                    };
+                    try!(fitness_results.add((estimate, index)).map_err(FitnessError::FitsSet));
+                }
+                Ok(fitness_results)
+            },
+            move |local_context, fits_a, fits_b| union::union(<P::LocalContext as RetrieveFitsManager>::retrieve(local_context), fits_a, fits_b))
+        {
+            Ok(None) => Err(Error::NoOutputFitnessValues),
+            Ok(Some(fitness_results)) => Ok(fitness_results),
+            Err(e) => Err(Error::Executor(e)),
+        }
+    }
+}
+
+impl<P> PopulationFit for SurrogatePopulationFit<P> where P: Policy {
+    type Exec = P::Exec;
+    type Indiv = P::Indiv;
+    type Pop = P::Pop;
+    type Fit = Estimate;
+    type Fits = P::Fits;
+    type Err = ErrorP<P>;
+
+    fn fit<WA>(&self, population: Arc<Self::Pop>, exec: &mut Self::Exec) -> Result<Self::Fits, Self::Err>
+        where WA: WorkAmount, <Self::Exec as Executor>::JIB: JobIterBuild<WA>
+    {
+        let mut state = self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let generation = state.generation;
+        state.generation += 1;
+
+        // until the model is trained everything is evaluated exactly
+        let population_size = population.size();
+        let mut predictions = vec![None; population_size];
+        let mut predicted = vec![None; population_size];
+        if state.trained {
+            let ranking = try!(prescreen(&mut state.model, &*population).map_err(Error::Prescreen));
+            let exact = self.params.control.exact(generation, population_size);
+            for (rank, &(index, value)) in ranking.iter().enumerate() {
+                predictions[index] = Some(value);
+                if rank >= exact {
+                    predicted[index] = Some(value);
+                }
+            }
+        }
+        let fits = try!(self.evaluate::<WA>(population.clone(), Arc::new(predicted), exec));
+
+        let mut pairs = Vec::new();
+        let mut fresh = 0;
+        for fit_index in 0 .. fits.size() {
+            let &(estimate, index) = try!(fits.get(fit_index).map_err(Error::FitsSet));
+            if !estimate.exact {
+                continue;
+            }
+            if let Some(prediction) = predictions[index] {
+                pairs.push((prediction, estimate.value));
+            }
+            let indiv = try!(population.get(index).map_err(Error::Population));
+            state.archive.push((indiv.clone(), estimate.value));
+            fresh += 1;
+        }
+        if let Some(accuracy) = Accuracy::measure(generation, &pairs) {
+            state.history.push(accuracy);
+        }
+
+        let excess = state.archive.len().saturating_sub(cmp::max(self.params.max_samples, self.params.min_samples));
+        state.archive.drain(0 .. excess);
+        if fresh > 0 && state.archive.len() >= self.params.min_samples {
+            // a model which cannot be trained keeps its previous state and the next generation is evaluated exactly
+            let State { ref mut model, ref archive, .. } = *state;
+            state.trained = model.train(archive).is_ok();
+        }
+        Ok(fits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use par_exec::Executor;
+    use par_exec::par::{ParallelExecutor, Alternately};
+    use super::super::super::super::set;
+    use super::super::PopulationFit;
+    use super::super::super::individual::IndividualManager;
+    use super::super::super::niching::DistanceManager;
+    use super::super::standard::{RetrieveFitsManager, RetrieveIndividualManager};
+    use super::model::KNearest;
+    use super::{Policy, SurrogatePopulationFit, Estimate, Control, Params};
+
+    struct IndivManager;
+    impl IndividualManager for IndivManager {
+        type I = f64;
+        type FI = f64;
+        type E = ();
+
+        fn generate(&mut self, index: usize) -> Result<Self::I, Self::E> {
+            Ok(index as f64)
+        }
+
+        fn fitness(&mut self, indiv: &Self::I) -> Result<Self::FI, Self::E> {
+            Ok((*indiv - 3.0) * (*indiv - 3.0))
+        }
+    }
+
+    struct Line;
+    impl DistanceManager for Line {
+        type I = f64;
+        type E = ();
+
+        fn distance(&mut self, indiv_a: &f64, indiv_b: &f64) -> Result<f64, ()> {
+            Ok((indiv_a - indiv_b).abs())
+        }
+    }
+
+    struct LocalContext {
+        fits_manager: set::vec::Manager<(Estimate, usize)>,
+        indiv_manager: IndivManager,
+    }
+
+    impl RetrieveFitsManager for LocalContext {
+        type FitsM = set::vec::Manager<(Estimate, usize)>;
+
+        fn retrieve(&mut self) -> &mut Self::FitsM {
+            &mut self.fits_manager
+        }
+    }
+
+    impl RetrieveIndividualManager for LocalContext {
+        type IM = IndivManager;
+
+        fn retrieve(&mut self) -> &mut Self::IM {
+            &mut self.indiv_manager
+        }
+    }
+
+    struct TestPolicy;
+    impl Policy for TestPolicy {
+        type LocalContext = LocalContext;
+        type Exec = ParallelExecutor<LocalContext>;
+
+        type Indiv = f64;
+        type IndivME = ();
+        type IndivM = IndivManager;
+
+        type ModelE = super::model::Error<()>;
+        type Model = KNearest<Line>;
+
+        type PopE = set::vec::Error;
+        type Pop = Vec<f64>;
+
+        type FitsE = set::vec::Error;
+        type Fits = Vec<(Estimate, usize)>;
+        type FitsME = ();
+        type FitsM = set::vec::Manager<(Estimate, usize)>;
+    }
+
+    fn exact_counts(control: Control) -> (Vec<usize>, SurrogatePopulationFit<TestPolicy>) {
+        let exec: ParallelExecutor<_> = Default::default();
+        let mut exec = exec.start(|| LocalContext {
+            fits_manager: set::vec::Manager::new(),
+            indiv_manager: IndivManager,
+        }).unwrap();
+
+        let params = Params { control: control, min_samples: 16, max_samples: 64, };
+        let fitness_calculator: SurrogatePopulationFit<TestPolicy> = SurrogatePopulationFit::new(KNearest::new(3, Line), params);
+        let mut counts = Vec::new();
+        for generation in 0 .. 5 {
+            let population = Arc::new((0 .. 32).map(|i| generation as f64 * 0.25 + i as f64 * 0.5 - 4.0).collect::<Vec<_>>());
+            let fits = fitness_calculator.fit::<Alternately>(population.clone(), &mut exec).unwrap();
+            assert_eq!(fits.len(), 32);
+            for &(estimate, index) in fits.iter() {
+                if estimate.exact {
+                    assert_eq!(estimate.value, (population[index] - 3.0) * (population[index] - 3.0));
+                }
+            }
+            counts.push(fits.iter().filter(|&&(estimate, _)| estimate.exact).count());
+        }
+        (counts, fitness_calculator)
+    }
+
+    #[test]
+    fn evolution_control() {
+        let (counts, fitness_calculator) = exact_counts(Control::Individual { fraction: 0.25, });
+        assert_eq!(counts, vec![32, 8, 8, 8, 8]);
+        // the model is checked from the first generation it was trained for
+        let history = fitness_calculator.accuracy_history();
+        assert_eq!(history.iter().map(|accuracy| accuracy.generation).collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+        assert!(history.iter().all(|accuracy| accuracy.samples == 8 && accuracy.rank_agreement > 0.5));
+
+        let (counts, _) = exact_counts(Control::Generation { period: 2, });
+        assert_eq!(counts, vec![32, 0, 32, 0, 32]);
+    }
+}
@@ -0,0 +1,212 @@
+use std::cmp::Ordering;
+
+use super::super::super::niching::DistanceManager;
+
+// cheap regression model approximating an expensive fitness function
+pub trait Model {
+    type I;
+    type E;
+
+    // replaces whatever the model has learnt so far with the given (individual, true fitness) samples,
+    // a failed training leaves the previous model in place
+    fn train(&mut self, samples: &[(Self::I, f64)]) -> Result<(), Self::E>;
+    fn predict(&mut self, indiv: &Self::I) -> Result<f64, Self::E>;
+}
+
+#[derive(PartialEq, Debug)]
+pub enum Error<DME> {
+    DistanceManager(DME),
+    Untrained,
+    Singular,
+}
+
+// inverse distance weighted mean of the `k` nearest training samples
+pub struct KNearest<DM> where DM: DistanceManager {
+    k: usize,
+    distance_manager: DM,
+    samples: Vec<(DM::I, f64)>,
+}
+
+impl<DM> KNearest<DM> where DM: DistanceManager {
+    pub fn new(k: usize, distance_manager: DM) -> KNearest<DM> {
+        KNearest {
+            k: if k == 0 { 1 } else { k },
+            distance_manager: distance_manager,
+            samples: Vec::new(),
+        }
+    }
+}
+
+impl<DM> Model for KNearest<DM> where DM: DistanceManager, DM::I: Clone {
+    type I = DM::I;
+    type E = Error<DM::E>;
+
+    fn train(&mut self, samples: &[(Self::I, f64)]) -> Result<(), Self::E> {
+        self.samples = samples.to_vec();
+        Ok(())
+    }
+
+    fn predict(&mut self, indiv: &Self::I) -> Result<f64, Self::E> {
+        if self.samples.is_empty() {
+            return Err(Error::Untrained);
+        }
+        let mut nearest = Vec::with_capacity(self.samples.len());
+        for &(ref sample, value) in self.samples.iter() {
+            let distance = try!(self.distance_manager.distance(indiv, sample).map_err(Error::DistanceManager));
+            if distance <= 0.0 {
+                return Ok(value);
+            }
+            nearest.push((distance, value));
+        }
+        nearest.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+        nearest.truncate(self.k);
+        let (weighted, weights) = nearest.iter()
+            .fold((0.0, 0.0), |(weighted, weights), &(distance, value)| (weighted + value / distance, weights + 1.0 / distance));
+        Ok(weighted / weights)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Kernel {
+    Gaussian { width: f64, },
+    Multiquadric { width: f64, },
+}
+
+impl Kernel {
+    pub fn apply(&self, distance: f64) -> f64 {
+        match *self {
+            Kernel::Gaussian { width } => (-(distance / width) * (distance / width)).exp(),
+            Kernel::Multiquadric { width } => (1.0 + (distance / width) * (distance / width)).sqrt(),
+        }
+    }
+}
+
+// radial basis function interpolation, `ridge` is added to the diagonal to keep the system well conditioned
+pub struct RadialBasis<DM> where DM: DistanceManager {
+    kernel: Kernel,
+    ridge: f64,
+    distance_manager: DM,
+    centers: Vec<DM::I>,
+    weights: Vec<f64>,
+}
+
+impl<DM> RadialBasis<DM> where DM: DistanceManager {
+    pub fn new(kernel: Kernel, ridge: f64, distance_manager: DM) -> RadialBasis<DM> {
+        RadialBasis {
+            kernel: kernel,
+            ridge: ridge,
+            distance_manager: distance_manager,
+            centers: Vec::new(),
+            weights: Vec::new(),
+        }
+    }
+}
+
+impl<DM> Model for RadialBasis<DM> where DM: DistanceManager, DM::I: Clone {
+    type I = DM::I;
+    type E = Error<DM::E>;
+
+    fn train(&mut self, samples: &[(Self::I, f64)]) -> Result<(), Self::E> {
+        // repeated individuals (e.g. re-evaluated elites) would make the system singular, the first one is kept
+        let mut centers: Vec<usize> = Vec::with_capacity(samples.len());
+        let mut distances: Vec<Vec<f64>> = Vec::with_capacity(samples.len());
+        for i in 0 .. samples.len() {
+            let mut row = Vec::with_capacity(centers.len());
+            for &j in centers.iter() {
+                row.push(try!(self.distance_manager.distance(&samples[i].0, &samples[j].0).map_err(Error::DistanceManager)));
+            }
+            if row.iter().all(|&distance| distance > 0.0) {
+                centers.push(i);
+                distances.push(row);
+            }
+        }
+        let size = centers.len();
+        let mut matrix = vec![vec![0.0; size]; size];
+        for i in 0 .. size {
+            matrix[i][i] = self.kernel.apply(0.0) + self.ridge;
+            for j in 0 .. i {
+                matrix[i][j] = self.kernel.apply(distances[i][j]);
+                matrix[j][i] = matrix[i][j];
+            }
+        }
+        let values = centers.iter().map(|&i| samples[i].1).collect();
+        self.weights = try!(solve(matrix, values).ok_or(Error::Singular));
+        self.centers = centers.iter().map(|&i| samples[i].0.clone()).collect();
+        Ok(())
+    }
+
+    fn predict(&mut self, indiv: &Self::I) -> Result<f64, Self::E> {
+        if self.centers.is_empty() {
+            return Err(Error::Untrained);
+        }
+        let mut value = 0.0;
+        for (center, weight) in self.centers.iter().zip(self.weights.iter()) {
+            let distance = try!(self.distance_manager.distance(indiv, center).map_err(Error::DistanceManager));
+            value += weight * self.kernel.apply(distance);
+        }
+        Ok(value)
+    }
+}
+
+// gaussian elimination with partial pivoting, `None` for a singular system
+fn solve(mut matrix: Vec<Vec<f64>>, mut values: Vec<f64>) -> Option<Vec<f64>> {
+    let size = values.len();
+    for column in 0 .. size {
+        let pivot = (column .. size)
+            .max_by(|&a, &b| matrix[a][column].abs().partial_cmp(&matrix[b][column].abs()).unwrap_or(Ordering::Equal))
+            .unwrap_or(column);
+        if matrix[pivot][column].abs().partial_cmp(&1e-12) != Some(Ordering::Greater) {
+            return None;
+        }
+        matrix.swap(column, pivot);
+        values.swap(column, pivot);
+        for row in column + 1 .. size {
+            let factor = matrix[row][column] / matrix[column][column];
+            for k in column .. size {
+                matrix[row][k] -= factor * matrix[column][k];
+            }
+            values[row] -= factor * values[column];
+        }
+    }
+    let mut solution = vec![0.0; size];
+    for row in (0 .. size).rev() {
+        let tail: f64 = (row + 1 .. size).map(|k| matrix[row][k] * solution[k]).sum();
+        solution[row] = (values[row] - tail) / matrix[row][row];
+    }
+    Some(solution)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::super::niching::DistanceManager;
+    use super::{Model, KNearest, RadialBasis, Kernel, Error};
+
+    struct Line;
+    impl DistanceManager for Line {
+        type I = f64;
+        type E = ();
+
+        fn distance(&mut self, indiv_a: &f64, indiv_b: &f64) -> Result<f64, ()> {
+            Ok((indiv_a - indiv_b).abs())
+        }
+    }
+
+    #[test]
+    fn models_interpolate() {
+        let samples: Vec<_> = (0 .. 11).map(|i| i as f64).map(|x| (x, x * x)).collect();
+
+        let mut knn = KNearest::new(2, Line);
+        assert_eq!(knn.predict(&1.0), Err(Error::Untrained));
+        knn.train(&samples).unwrap();
+        assert_eq!(knn.predict(&3.0), Ok(9.0));
+        assert_eq!(knn.predict(&3.5), Ok(12.5));
+
+        // duplicated samples do not make the interpolation singular
+        let mut duplicated = samples.clone();
+        duplicated.push((4.0, 16.0));
+        let mut rbf = RadialBasis::new(Kernel::Gaussian { width: 1.5, }, 0.0, Line);
+        rbf.train(&duplicated).unwrap();
+        assert!((rbf.predict(&4.0).unwrap() - 16.0).abs() < 1e-6);
+        assert!((rbf.predict(&4.5).unwrap() - 20.25).abs() < 0.5);
+    }
+}
@@ -1,4 +1,6 @@
-
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
 
 pub trait IndividualManager {
     type I;
@@ -7,4 +9,65 @@ pub trait IndividualManager {
 
     fn generate(&mut self, index: usize) -> Result<Self::I, Self::E>;
     fn fitness(&mut self, indiv: &Self::I) -> Result<Self::FI, Self::E>;
+
+    // cooperative version of `fitness`: long evaluations should poll `token` and give up with `Ok(None)`
+    // once it is cancelled, by default the evaluation just runs to completion
+    fn fitness_cancellable(&mut self, indiv: &Self::I, _token: &CancelToken) -> Result<Option<Self::FI>, Self::E> {
+        self.fitness(indiv).map(Some)
+    }
+
+    // amount of fidelity levels, from 0 (cheapest, e.g. a short simulation) up to `fidelities() - 1`
+    fn fidelities(&self) -> usize {
+        1
+    }
+
+    // fitness at a given fidelity level, by default every level is the full `fitness`
+    fn fitness_at(&mut self, indiv: &Self::I, _fidelity: usize) -> Result<Self::FI, Self::E> {
+        self.fitness(indiv)
+    }
+
+    // whether `fitness_batch` evaluates a whole chunk at once, e.g. with a single vectorised model call
+    fn supports_batch(&self) -> bool {
+        false
+    }
+
+    // fitness values of a chunk of individuals in the same order, by default evaluated one by one
+    fn fitness_batch(&mut self, indivs: &[&Self::I]) -> Result<Vec<Self::FI>, Self::E> {
+        indivs.iter().map(|indiv| self.fitness(indiv)).collect()
+    }
+}
+
+// cancelled either explicitly or when its deadline passes
+#[derive(Clone, Debug)]
+pub struct CancelToken {
+    deadline: Option<Instant>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+    pub fn new() -> CancelToken {
+        CancelToken {
+            deadline: None,
+            cancelled: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn with_deadline(deadline: Instant) -> CancelToken {
+        CancelToken {
+            deadline: Some(deadline),
+            cancelled: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst)
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst) || self.deadline.map(|deadline| Instant::now() >= deadline).unwrap_or(false)
+    }
 }
@@ -3,6 +3,7 @@ use par_exec::{Executor, WorkAmount, JobIterBuild, ExecutorJobError, JobExecuteE
 
 use super::PopulationInit;
 use super::super::individual::IndividualManager;
+use super::super::repair::{Repair, RepairManager, RetrieveRepairManager};
 use super::super::super::set::{Set, SetManager};
 use super::super::super::set::union;
 
@@ -19,11 +20,13 @@ pub trait RetrieveIndividualManager {
 }
 
 pub trait Policy {
-    type LocalContext: RetrievePopulationManager<PopM = Self::PopSM> + RetrieveIndividualManager<IM = Self::IndivM>;
+    type LocalContext: RetrievePopulationManager<PopM = Self::PopSM> + RetrieveIndividualManager<IM = Self::IndivM> + RetrieveRepairManager<RM = Self::RepairM>;
     type Exec: Executor<LC = Self::LocalContext>;
     type Indiv;
     type IndivME: Send + 'static;
     type IndivM: IndividualManager<I = Self::Indiv, E = Self::IndivME>;
+    type RepairME: Send + 'static;
+    type RepairM: RepairManager<I = Self::Indiv, E = Self::RepairME>;
     type PopE: Send + 'static;
     type Pop: Set<T = Self::Indiv, E = Self::PopE> + Send + 'static;
     type PopSME: Send + 'static;
@@ -32,32 +35,39 @@ pub trait Policy {
 
 pub struct LimitedPopulationInit<P> where P: Policy {
     limit: usize,
+    repair: Repair,
     _marker: PhantomData<P>,
 }
 
 impl<P> LimitedPopulationInit<P> where P: Policy {
     pub fn new(limit: usize) -> LimitedPopulationInit<P> {
+        LimitedPopulationInit::with_repair(limit, Repair::WriteBack)
+    }
+
+    pub fn with_repair(limit: usize, repair: Repair) -> LimitedPopulationInit<P> {
         LimitedPopulationInit {
             limit: limit,
+            repair: repair,
             _marker: PhantomData,
         }
     }
 }
 
 #[derive(Debug)]
-pub enum GenerateError<SE, SME, IME> {
+pub enum GenerateError<SE, SME, IME, RME> {
     Set(SE),
     SetManager(SME),
     IndividualManager(IME),
+    RepairManager(RME),
 }
 
 #[derive(Debug)]
-pub enum Error<ExecE, PopE, PopSME, IndivME> {
+pub enum Error<ExecE, PopE, PopSME, IndivME, RepairME> {
     NoOutputPopulation,
-    Executor(ExecutorJobError<ExecE, JobExecuteError<GenerateError<PopE, PopSME, IndivME>, union::Error<PopE, PopSME>>>),
+    Executor(ExecutorJobError<ExecE, JobExecuteError<GenerateError<PopE, PopSME, IndivME, RepairME>, union::Error<PopE, PopSME>>>),
 }
 
-pub type ErrorP<P> where P: Policy = Error<<P::Exec as Executor>::E, P::PopE, P::PopSME, P::IndivME>;
+pub type ErrorP<P> where P: Policy = Error<<P::Exec as Executor>::E, P::PopE, P::PopSME, P::IndivME, P::RepairME>;
 
 impl<P> PopulationInit for LimitedPopulationInit<P> where P: Policy {
     type Exec = P::Exec;
@@ -68,6 +78,7 @@ impl<P> PopulationInit for LimitedPopulationInit<P> where P: Policy {
     fn init<WA>(&self, exec: &mut Self::Exec) -> Result<Self::Pop, Self::Err>
         where WA: WorkAmount, <Self::Exec as Executor>::JIB: JobIterBuild<WA>
     {
+        let repair = self.repair;
         match exec.try_execute_job(
             WA::new(self.limit),
             move |local_context, input_indices| {
@@ -75,9 +86,15 @@ impl<P> PopulationInit for LimitedPopulationInit<P> where P: Policy {
                     let mut set_manager = <P::LocalContext as RetrievePopulationManager>::retrieve(local_context);
                     try!(set_manager.make_set(None).map_err(GenerateError::SetManager))
                 };
-                let mut indiv_manager = <P::LocalContext as RetrieveIndividualManager>::retrieve(local_context);
                 for index in input_indices {
-                    let indiv = try!(indiv_manager.generate(index).map_err(GenerateError::IndividualManager));
+                    let indiv = {
+                        let indiv_manager = <P::LocalContext as RetrieveIndividualManager>::retrieve(local_context);
+                        try!(indiv_manager.generate(index).map_err(GenerateError::IndividualManager))
+                    };
+                    let indiv = {
+                        let repair_manager = <P::LocalContext as RetrieveRepairManager>::retrieve(local_context);
+                        try!(repair.produce(repair_manager, indiv).map_err(GenerateError::RepairManager))
+                    };
                     try!(population.add(indiv).map_err(GenerateError::Set));
                 }
                 Ok(population)
@@ -98,6 +115,7 @@ mod tests {
     use super::super::super::super::set;
     use super::super::PopulationInit;
     use super::super::super::individual::IndividualManager;
+    use super::super::super::repair::{Repair, RepairManager, RetrieveRepairManager};
     use super::{Policy, LimitedPopulationInit, RetrievePopulationManager, RetrieveIndividualManager};
 
     struct IndivManager;
@@ -115,9 +133,21 @@ mod tests {
         }
     }
 
+    // odd individuals are infeasible when repair is enabled
+    struct EvenRepair(bool);
+    impl RepairManager for EvenRepair {
+        type I = usize;
+        type E = ();
+
+        fn repair(&mut self, indiv: &Self::I) -> Result<Option<Self::I>, Self::E> {
+            Ok(if self.0 && *indiv % 2 == 1 { Some(*indiv - 1) } else { None })
+        }
+    }
+
     struct LocalContext {
         set_manager: set::vec::Manager<usize>,
         indiv_manager: IndivManager,
+        repair_manager: EvenRepair,
     }
 
     impl RetrievePopulationManager for LocalContext {
@@ -136,6 +166,14 @@ mod tests {
         }
     }
 
+    impl RetrieveRepairManager for LocalContext {
+        type RM = EvenRepair;
+
+        fn retrieve(&mut self) -> &mut Self::RM {
+            &mut self.repair_manager
+        }
+    }
+
     struct TestPolicy;
     impl Policy for TestPolicy {
         type LocalContext = LocalContext;
@@ -143,6 +181,8 @@ mod tests {
         type Indiv = usize;
         type IndivME = ();
         type IndivM = IndivManager;
+        type RepairME = ();
+        type RepairM = EvenRepair;
         type PopE = set::vec::Error;
         type Pop = Vec<usize>;
         type PopSME = ();
@@ -155,6 +195,7 @@ mod tests {
         let mut exec = exec.start(|| LocalContext {
             set_manager: set::vec::Manager::new(),
             indiv_manager: IndivManager,
+            repair_manager: EvenRepair(false),
         }).unwrap();
 
         let initializer: LimitedPopulationInit<TestPolicy> =
@@ -163,4 +204,26 @@ mod tests {
         population.sort();
         assert_eq!(population, (0 .. 1024).collect::<Vec<_>>());
     }
+
+    #[test]
+    fn repaired_generator() {
+        let exec: ParallelExecutor<_> = Default::default();
+        let mut exec = exec.start(|| LocalContext {
+            set_manager: set::vec::Manager::new(),
+            indiv_manager: IndivManager,
+            repair_manager: EvenRepair(true),
+        }).unwrap();
+
+        let initializer: LimitedPopulationInit<TestPolicy> =
+            LimitedPopulationInit::with_repair(1024, Repair::WriteBack);
+        let mut population = initializer.init::<Alternately>(&mut exec).unwrap();
+        population.sort();
+        assert_eq!(population, (0 .. 1024).map(|i| i & !1).collect::<Vec<_>>());
+
+        let initializer: LimitedPopulationInit<TestPolicy> =
+            LimitedPopulationInit::with_repair(1024, Repair::EvaluateOnly);
+        let mut population = initializer.init::<Alternately>(&mut exec).unwrap();
+        population.sort();
+        assert_eq!(population, (0 .. 1024).collect::<Vec<_>>());
+    }
 }
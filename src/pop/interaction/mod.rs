@@ -0,0 +1,11 @@
+
+pub trait InteractionManager {
+    type I;
+    type FI;
+    type E;
+
+    // returns fitness of `participants[focal]` resulting from its interaction with the rest of participants
+    fn interact(&mut self, focal: usize, participants: &[&Self::I]) -> Result<Self::FI, Self::E>;
+    // accumulates outcomes when an individual takes part in several interactions
+    fn combine(&mut self, total: Self::FI, outcome: Self::FI) -> Result<Self::FI, Self::E>;
+}
@@ -0,0 +1,185 @@
+use std::marker::PhantomData;
+
+use super::{LocalSearchManager, SearchError};
+use super::super::individual::IndividualManager;
+use super::super::neighbour::NeighbourManager;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Limits {
+    pub max_steps: usize,
+    pub max_neighbours: usize,
+}
+
+// steepest ascent: every step moves to the best neighbour of the current individual
+pub struct HillClimbing<N, FI, F> where N: NeighbourManager, F: Fn(&FI, &FI) -> bool {
+    neighbour_manager: N,
+    better: F,
+    limits: Limits,
+    _marker: PhantomData<FI>,
+}
+
+impl<N, FI, F> HillClimbing<N, FI, F> where N: NeighbourManager, F: Fn(&FI, &FI) -> bool {
+    pub fn new(neighbour_manager: N, better: F, limits: Limits) -> HillClimbing<N, FI, F> {
+        HillClimbing {
+            neighbour_manager: neighbour_manager,
+            better: better,
+            limits: limits,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<N, FI, F> LocalSearchManager for HillClimbing<N, FI, F> where N: NeighbourManager, F: Fn(&FI, &FI) -> bool {
+    type I = N::I;
+    type FI = FI;
+    type E = N::E;
+
+    fn search<IM>(&mut self, indiv: &Self::I, fitness: &Self::FI, indiv_manager: &mut IM) ->
+        Result<Option<(Self::I, Self::FI)>, SearchError<Self::E, IM::E>>
+        where IM: IndividualManager<I = Self::I, FI = Self::FI>
+    {
+        climb(&mut self.neighbour_manager, &self.better, self.limits, false, indiv, fitness, indiv_manager)
+    }
+}
+
+// first improvement: every step moves to the first neighbour better than the current individual
+pub struct FirstImprovement<N, FI, F> where N: NeighbourManager, F: Fn(&FI, &FI) -> bool {
+    neighbour_manager: N,
+    better: F,
+    limits: Limits,
+    _marker: PhantomData<FI>,
+}
+
+impl<N, FI, F> FirstImprovement<N, FI, F> where N: NeighbourManager, F: Fn(&FI, &FI) -> bool {
+    pub fn new(neighbour_manager: N, better: F, limits: Limits) -> FirstImprovement<N, FI, F> {
+        FirstImprovement {
+            neighbour_manager: neighbour_manager,
+            better: better,
+            limits: limits,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<N, FI, F> LocalSearchManager for FirstImprovement<N, FI, F> where N: NeighbourManager, F: Fn(&FI, &FI) -> bool {
+    type I = N::I;
+    type FI = FI;
+    type E = N::E;
+
+    fn search<IM>(&mut self, indiv: &Self::I, fitness: &Self::FI, indiv_manager: &mut IM) ->
+        Result<Option<(Self::I, Self::FI)>, SearchError<Self::E, IM::E>>
+        where IM: IndividualManager<I = Self::I, FI = Self::FI>
+    {
+        climb(&mut self.neighbour_manager, &self.better, self.limits, true, indiv, fitness, indiv_manager)
+    }
+}
+
+fn climb<N, FI, F, IM>(
+    neighbour_manager: &mut N,
+    better: &F,
+    limits: Limits,
+    first_improvement: bool,
+    indiv: &N::I,
+    fitness: &FI,
+    indiv_manager: &mut IM)
+    -> Result<Option<(N::I, FI)>, SearchError<N::E, IM::E>>
+    where N: NeighbourManager, F: Fn(&FI, &FI) -> bool, IM: IndividualManager<I = N::I, FI = FI>
+{
+    let mut current: Option<(N::I, FI)> = None;
+    for _ in 0 .. limits.max_steps {
+        let mut step_best: Option<(N::I, FI)> = None;
+        {
+            let (current_indiv, current_fitness) = match current {
+                Some((ref current_indiv, ref current_fitness)) => (current_indiv, current_fitness),
+                None => (indiv, fitness),
+            };
+            for attempt in 0 .. limits.max_neighbours {
+                let candidate = match try!(neighbour_manager.neighbour(current_indiv, attempt).map_err(SearchError::LocalSearch)) {
+                    Some(candidate) => candidate,
+                    None => break,
+                };
+                let candidate_fitness = try!(indiv_manager.fitness(&candidate).map_err(SearchError::IndividualManager));
+                let improves = match step_best {
+                    Some((_, ref best_fitness)) => better(&candidate_fitness, best_fitness),
+                    None => better(&candidate_fitness, current_fitness),
+                };
+                if improves {
+                    step_best = Some((candidate, candidate_fitness));
+                    if first_improvement {
+                        break;
+                    }
+                }
+            }
+        }
+
+        match step_best {
+            Some(best) => current = Some(best),
+            None => break,
+        }
+    }
+    Ok(current)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::individual::IndividualManager;
+    use super::super::super::neighbour::NeighbourManager;
+    use super::super::LocalSearchManager;
+    use super::{Limits, HillClimbing, FirstImprovement};
+
+    struct IndivManager {
+        evaluations: usize,
+    }
+
+    impl IndividualManager for IndivManager {
+        type I = i64;
+        type FI = i64;
+        type E = ();
+
+        fn generate(&mut self, index: usize) -> Result<Self::I, Self::E> {
+            Ok(index as i64)
+        }
+
+        fn fitness(&mut self, indiv: &Self::I) -> Result<Self::FI, Self::E> {
+            self.evaluations += 1;
+            Ok((*indiv - 42).abs())
+        }
+    }
+
+    struct Steps;
+    impl NeighbourManager for Steps {
+        type I = i64;
+        type E = ();
+
+        fn neighbour(&mut self, indiv: &Self::I, attempt: usize) -> Result<Option<Self::I>, Self::E> {
+            Ok(match attempt {
+                0 => Some(*indiv + 1),
+                1 => Some(*indiv - 1),
+                2 => Some(*indiv + 5),
+                3 => Some(*indiv - 5),
+                _ => None,
+            })
+        }
+    }
+
+    const LIMITS: Limits = Limits { max_steps: 100, max_neighbours: 16, };
+
+    #[test]
+    fn hill_climbing() {
+        let mut indiv_manager = IndivManager { evaluations: 0, };
+        let mut search = HillClimbing::new(Steps, |a: &i64, b: &i64| a < b, LIMITS);
+        assert_eq!(search.search(&0, &42, &mut indiv_manager), Ok(Some((42, 0))));
+        assert_eq!(indiv_manager.evaluations, 4 * 11);
+        assert_eq!(search.search(&42, &0, &mut indiv_manager), Ok(None));
+    }
+
+    #[test]
+    fn first_improvement() {
+        let mut indiv_manager = IndivManager { evaluations: 0, };
+        let mut search = FirstImprovement::new(Steps, |a: &i64, b: &i64| a < b, LIMITS);
+        assert_eq!(search.search(&0, &42, &mut indiv_manager), Ok(Some((42, 0))));
+        assert_eq!(indiv_manager.evaluations, 42 + 4);
+        let mut short_search = FirstImprovement::new(Steps, |a: &i64, b: &i64| a < b, Limits { max_steps: 3, ..LIMITS });
+        assert_eq!(short_search.search(&0, &42, &mut indiv_manager), Ok(Some((3, 39))));
+    }
+}
@@ -0,0 +1,20 @@
+use super::individual::IndividualManager;
+
+pub mod climbing;
+
+#[derive(PartialEq, Debug)]
+pub enum SearchError<LSE, IME> {
+    LocalSearch(LSE),
+    IndividualManager(IME),
+}
+
+pub trait LocalSearchManager {
+    type I;
+    type FI;
+    type E;
+
+    // returns improved individual with its fitness or `None` if no improvement was found
+    fn search<IM>(&mut self, indiv: &Self::I, fitness: &Self::FI, indiv_manager: &mut IM) ->
+        Result<Option<(Self::I, Self::FI)>, SearchError<Self::E, IM::E>>
+        where IM: IndividualManager<I = Self::I, FI = Self::FI>;
+}
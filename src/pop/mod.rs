@@ -1,4 +1,15 @@
 
 pub mod individual;
+pub mod neighbour;
+pub mod interaction;
+pub mod breed;
+pub mod local_search;
+pub mod constraint;
+pub mod repair;
+pub mod niching;
+pub mod diversity;
+pub mod elite;
+pub mod stats;
+pub mod external;
 pub mod init;
 pub mod fit;
@@ -0,0 +1,8 @@
+
+pub trait NeighbourManager {
+    type I;
+    type E;
+
+    // returns `attempt`-th neighbour of `indiv` or `None` when neighbourhood is exhausted
+    fn neighbour(&mut self, indiv: &Self::I, attempt: usize) -> Result<Option<Self::I>, Self::E>;
+}
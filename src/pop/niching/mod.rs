@@ -0,0 +1,373 @@
+use std::sync::Arc;
+use rand::{self, Rng};
+use par_exec::{Executor, WorkAmount, JobIterBuild, ExecutorJobError, JobExecuteError};
+
+use super::super::set::Set;
+
+// all niching procedures here treat fitness as a non-negative value to be maximized
+
+pub trait DistanceManager {
+    type I;
+    type E;
+
+    fn distance(&mut self, indiv_a: &Self::I, indiv_b: &Self::I) -> Result<f64, Self::E>;
+}
+
+pub trait RetrieveDistanceManager {
+    type DM;
+
+    fn retrieve(&mut self) -> &mut Self::DM;
+}
+
+pub trait Policy {
+    type LocalContext: RetrieveDistanceManager<DM = Self::DistM>;
+    type Exec: Executor<LC = Self::LocalContext>;
+
+    type Indiv;
+    type PopE: Send + 'static;
+    type Pop: Set<T = Self::Indiv, E = Self::PopE> + Sync + Send + 'static;
+
+    type DistME: Send + 'static;
+    type DistM: DistanceManager<I = Self::Indiv, E = Self::DistME>;
+}
+
+#[derive(Debug)]
+pub enum DistanceError<PE, DME> {
+    Population(PE),
+    DistanceManager(DME),
+}
+
+#[derive(Debug)]
+pub enum Error<ExecE, PopE, DistME> {
+    NoOutputDistances,
+    Executor(ExecutorJobError<ExecE, JobExecuteError<DistanceError<PopE, DistME>, ()>>),
+}
+
+pub type ErrorP<P> where P: Policy = Error<<P::Exec as Executor>::E, P::PopE, P::DistME>;
+
+#[derive(PartialEq, Debug)]
+pub enum FitsError<FE> {
+    Set(FE),
+    MissingFitness { index: usize, },
+}
+
+// fitness values from a fits set (as produced by `StandardPopulationFit`) laid out by population index
+pub fn fitness_by_index<S, F>(fits: S, population_size: usize) -> Result<Vec<f64>, FitsError<S::E>> where
+    S: Set<T = (F, usize)>,
+    F: Into<f64>
+{
+    let mut values = vec![None; population_size];
+    for maybe_fit in fits.into_iter() {
+        let (fitness, index) = try!(maybe_fit.map_err(FitsError::Set));
+        if index < population_size {
+            values[index] = Some(fitness.into());
+        }
+    }
+    let mut fitness = Vec::with_capacity(population_size);
+    for (index, value) in IntoIterator::into_iter(values).enumerate() {
+        fitness.push(try!(value.ok_or(FitsError::MissingFitness { index: index, })));
+    }
+    Ok(fitness)
+}
+
+// symmetric matrix of pairwise distances between population members
+#[derive(Clone, PartialEq, Debug)]
+pub struct Distances {
+    size: usize,
+    values: Vec<f64>,
+}
+
+impl Distances {
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn get(&self, index_a: usize, index_b: usize) -> f64 {
+        self.values[index_a * self.size + index_b]
+    }
+}
+
+// computes all pairwise distances, rows are distributed among the executor workers
+pub fn distance_matrix<P, WA>(population: Arc<P::Pop>, exec: &mut P::Exec) -> Result<Distances, ErrorP<P>> where
+    P: Policy,
+    WA: WorkAmount,
+    <P::Exec as Executor>::JIB: JobIterBuild<WA>
+{
+    let size = population.size();
+    if size == 0 {
+        return Ok(Distances { size: 0, values: Vec::new(), });
+    }
+    let maybe_rows = exec.try_execute_job(
+        WA::new(size),
+        move |local_context, input_indices| {
+            let distance_manager = <P::LocalContext as RetrieveDistanceManager>::retrieve(local_context);
+            let mut rows = Vec::new();
+            for row in input_indices {
+                let indiv_a = try!(population.get(row).map_err(DistanceError::Population));
+                // only the upper triangle is computed, the matrix is mirrored afterwards
+                let mut values = Vec::with_capacity(size - row - 1);
+                for column in row + 1 .. size {
+                    let indiv_b = try!(population.get(column).map_err(DistanceError::Population));
+                    values.push(try!(distance_manager.distance(indiv_a, indiv_b).map_err(DistanceError::DistanceManager)));
+                }
+                rows.push((row, values));
+            }
+            Ok(rows)
+        },
+        |_local_context, mut rows_a, rows_b| { rows_a.extend(rows_b); Ok(rows_a) });
+    let rows = match maybe_rows {
+        Ok(None) => return Err(Error::NoOutputDistances),
+        Ok(Some(rows)) => rows,
+        Err(e) => return Err(Error::Executor(e)),
+    };
+    let mut values = vec![0.0; size * size];
+    for (row, row_values) in rows {
+        for (offset, value) in IntoIterator::into_iter(row_values).enumerate() {
+            let column = row + 1 + offset;
+            values[row * size + column] = value;
+            values[column * size + row] = value;
+        }
+    }
+    Ok(Distances { size: size, values: values, })
+}
+
+// niche count m(i) = sum of sh(d(i, j)) with sh(d) = 1 - (d / sigma) ^ alpha for d < sigma
+pub fn niche_counts(distances: &Distances, sigma_share: f64, alpha: f64) -> Vec<f64> {
+    (0 .. distances.size).map(|a| {
+        (0 .. distances.size)
+            .map(|b| distances.get(a, b))
+            .filter(|&d| d < sigma_share)
+            .map(|d| 1.0 - (d / sigma_share).powf(alpha))
+            .sum()
+    }).collect()
+}
+
+// shared fitness f(i) / m(i)
+pub fn fitness_sharing(distances: &Distances, fitness: &[f64], sigma_share: f64, alpha: f64) -> Vec<f64> {
+    IntoIterator::into_iter(niche_counts(distances, sigma_share, alpha))
+        .zip(fitness.iter())
+        .map(|(count, &value)| value / count)
+        .collect()
+}
+
+fn best_first(fitness: &[f64]) -> Vec<usize> {
+    use std::cmp::Ordering;
+    let mut order: Vec<usize> = (0 .. fitness.len()).collect();
+    order.sort_by(|&a, &b| fitness[b].partial_cmp(&fitness[a]).unwrap_or(Ordering::Equal));
+    order
+}
+
+// clearing: only `capacity` best individuals of every niche of `radius` keep their fitness, the rest get zero
+pub fn clearing(distances: &Distances, fitness: &[f64], radius: f64, capacity: usize) -> Vec<f64> {
+    let order = best_first(fitness);
+    let mut cleared = fitness.to_vec();
+    let mut done = vec![false; fitness.len()];
+    for (position, &winner) in order.iter().enumerate() {
+        if done[winner] {
+            continue;
+        }
+        done[winner] = true;
+        let mut winners = 1;
+        for &other in order[position + 1 ..].iter() {
+            if !done[other] && distances.get(winner, other) < radius {
+                done[other] = true;
+                if winners < capacity {
+                    winners += 1;
+                } else {
+                    cleared[other] = 0.0;
+                }
+            }
+        }
+        if capacity == 0 {
+            cleared[winner] = 0.0;
+        }
+    }
+    cleared
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Species {
+    // best individual of the species
+    pub seed: usize,
+    // population indices, best first, including the seed
+    pub members: Vec<usize>,
+}
+
+// speciation by radius: individuals processed best first either join the first species with a seed
+// closer than `radius` or found a new species
+pub fn speciation(distances: &Distances, fitness: &[f64], radius: f64) -> Vec<Species> {
+    let mut species: Vec<Species> = Vec::new();
+    for index in best_first(fitness) {
+        match species.iter().position(|s| distances.get(s.seed, index) < radius) {
+            Some(position) => species[position].members.push(index),
+            None => species.push(Species { seed: index, members: vec![index], }),
+        }
+    }
+    species
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Crowding {
+    // offspring replaces its closest parent when it is better
+    Deterministic,
+    // offspring replaces its closest parent with probability f(offspring) / (f(offspring) + f(parent))
+    Probabilistic,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Survivor {
+    Parent(usize),
+    Offspring(usize),
+}
+
+// crowding replacement: parents `2k` and `2k + 1` produced offspring `2k` and `2k + 1`, every offspring
+// competes against the parent it is paired with by distance, an odd last offspring competes against the parent
+// in its own slot; returns the survivor for every slot
+pub fn crowding<P, WA>(parents: Arc<P::Pop>,
+                       parents_fitness: Arc<Vec<f64>>,
+                       offspring: Arc<P::Pop>,
+                       offspring_fitness: Arc<Vec<f64>>,
+                       replacement: Crowding,
+                       exec: &mut P::Exec)
+                       -> Result<Vec<Survivor>, ErrorP<P>> where
+    P: Policy,
+    WA: WorkAmount,
+    <P::Exec as Executor>::JIB: JobIterBuild<WA>
+{
+    let slots = parents.size().min(offspring.size());
+    if slots == 0 {
+        return Ok(Vec::new());
+    }
+    let maybe_survivors = exec.try_execute_job(
+        WA::new((slots + 1) / 2),
+        move |local_context, input_indices| {
+            let distance_manager = <P::LocalContext as RetrieveDistanceManager>::retrieve(local_context);
+            let mut rng = rand::thread_rng();
+            let mut survivors = Vec::new();
+            for pair in input_indices {
+                let (pa, pb) = (2 * pair, 2 * pair + 1);
+                let matches = if pb >= slots {
+                    vec![(pa, pa)]
+                } else {
+                    let parent_a = try!(parents.get(pa).map_err(DistanceError::Population));
+                    let parent_b = try!(parents.get(pb).map_err(DistanceError::Population));
+                    let child_a = try!(offspring.get(pa).map_err(DistanceError::Population));
+                    let child_b = try!(offspring.get(pb).map_err(DistanceError::Population));
+                    let mut distance = |x, y| distance_manager.distance(x, y).map_err(DistanceError::DistanceManager);
+                    let straight = try!(distance(parent_a, child_a)) + try!(distance(parent_b, child_b));
+                    let crossed = try!(distance(parent_a, child_b)) + try!(distance(parent_b, child_a));
+                    if straight <= crossed { vec![(pa, pa), (pb, pb)] } else { vec![(pa, pb), (pb, pa)] }
+                };
+                for &(parent, child) in matches.iter() {
+                    let (fp, fc) = (parents_fitness[parent], offspring_fitness[child]);
+                    let replace = match replacement {
+                        Crowding::Deterministic => fc > fp,
+                        Crowding::Probabilistic => if fp + fc > 0.0 { rng.gen::<f64>() * (fp + fc) < fc } else { rng.gen() },
+                    };
+                    survivors.push((parent, if replace { Survivor::Offspring(child) } else { Survivor::Parent(parent) }));
+                }
+            }
+            Ok(survivors)
+        },
+        |_local_context, mut survivors_a, survivors_b| { survivors_a.extend(survivors_b); Ok(survivors_a) });
+    match maybe_survivors {
+        Ok(None) => Err(Error::NoOutputDistances),
+        Ok(Some(mut survivors)) => {
+            survivors.sort_by_key(|s| s.0);
+            Ok(IntoIterator::into_iter(survivors).map(|s| s.1).collect())
+        },
+        Err(e) => Err(Error::Executor(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use par_exec::Executor;
+    use par_exec::par::{ParallelExecutor, Alternately};
+    use super::super::super::set;
+    use super::{Policy, DistanceManager, RetrieveDistanceManager, Crowding, Survivor, Species};
+    use super::{distance_matrix, niche_counts, fitness_sharing, clearing, speciation, crowding, fitness_by_index};
+
+    struct LineDistance;
+    impl DistanceManager for LineDistance {
+        type I = f64;
+        type E = ();
+
+        fn distance(&mut self, indiv_a: &Self::I, indiv_b: &Self::I) -> Result<f64, Self::E> {
+            Ok((indiv_a - indiv_b).abs())
+        }
+    }
+
+    struct LocalContext(LineDistance);
+
+    impl RetrieveDistanceManager for LocalContext {
+        type DM = LineDistance;
+
+        fn retrieve(&mut self) -> &mut Self::DM {
+            &mut self.0
+        }
+    }
+
+    struct TestPolicy;
+    impl Policy for TestPolicy {
+        type LocalContext = LocalContext;
+        type Exec = ParallelExecutor<LocalContext>;
+        type Indiv = f64;
+        type PopE = set::vec::Error;
+        type Pop = Vec<f64>;
+        type DistME = ();
+        type DistM = LineDistance;
+    }
+
+    #[test]
+    fn sharing_clearing_speciation() {
+        let exec: ParallelExecutor<_> = Default::default();
+        let mut exec = exec.start(|| LocalContext(LineDistance)).unwrap();
+
+        // two clusters around 0 and 10
+        let population = Arc::new(vec![0.0, 0.5, 1.0, 10.0, 10.5]);
+        let distances = distance_matrix::<TestPolicy, Alternately>(population.clone(), &mut exec).unwrap();
+        assert_eq!(distances.get(1, 4), 10.0);
+        assert_eq!(distances.get(4, 1), 10.0);
+
+        assert_eq!(niche_counts(&distances, 2.0, 1.0), vec![2.25, 2.5, 2.25, 1.75, 1.75]);
+        let fitness = fitness_by_index(vec![(4.0, 2), (5.0, 0), (3.5, 4), (9.0, 1), (7.0, 3)], 5).unwrap();
+        assert_eq!(fitness, vec![5.0, 9.0, 4.0, 7.0, 3.5]);
+        assert_eq!(fitness_sharing(&distances, &fitness, 2.0, 1.0), vec![5.0 / 2.25, 9.0 / 2.5, 4.0 / 2.25, 4.0, 2.0]);
+
+        assert_eq!(clearing(&distances, &fitness, 2.0, 1), vec![0.0, 9.0, 0.0, 7.0, 0.0]);
+        assert_eq!(clearing(&distances, &fitness, 2.0, 2), vec![5.0, 9.0, 0.0, 7.0, 3.5]);
+        assert_eq!(speciation(&distances, &fitness, 2.0), vec![
+            Species { seed: 1, members: vec![1, 0, 2], },
+            Species { seed: 3, members: vec![3, 4], },
+        ]);
+    }
+
+    #[test]
+    fn deterministic_crowding() {
+        let exec: ParallelExecutor<_> = Default::default();
+        let mut exec = exec.start(|| LocalContext(LineDistance)).unwrap();
+
+        let parents = Arc::new(vec![0.0, 10.0, 20.0, 30.0]);
+        let offspring = Arc::new(vec![9.0, 1.0, 21.0, 29.0]);
+        let survivors = crowding::<TestPolicy, Alternately>(
+            parents,
+            Arc::new(vec![1.0, 5.0, 3.0, 3.0]),
+            offspring,
+            Arc::new(vec![4.0, 2.0, 2.0, 4.0]),
+            Crowding::Deterministic,
+            &mut exec).unwrap();
+        assert_eq!(survivors, vec![Survivor::Offspring(1), Survivor::Parent(1), Survivor::Parent(2), Survivor::Offspring(3)]);
+
+        // the odd last offspring competes against the parent in its own slot
+        let survivors = crowding::<TestPolicy, Alternately>(
+            Arc::new(vec![0.0, 10.0, 20.0]),
+            Arc::new(vec![1.0, 5.0, 3.0]),
+            Arc::new(vec![9.0, 1.0, 21.0]),
+            Arc::new(vec![4.0, 2.0, 4.0]),
+            Crowding::Deterministic,
+            &mut exec).unwrap();
+        assert_eq!(survivors, vec![Survivor::Offspring(1), Survivor::Parent(1), Survivor::Offspring(2)]);
+    }
+}
@@ -0,0 +1,64 @@
+use std::marker::PhantomData;
+
+pub trait RepairManager {
+    type I;
+    type E;
+
+    // returns repaired copy of `indiv` or `None` when it needs no repair
+    fn repair(&mut self, indiv: &Self::I) -> Result<Option<Self::I>, Self::E>;
+}
+
+pub trait RetrieveRepairManager {
+    type RM;
+
+    fn retrieve(&mut self) -> &mut Self::RM;
+}
+
+// what happens with the repaired genome
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Repair {
+    // freshly generated or bred individuals are replaced by their repaired versions
+    WriteBack,
+    // individuals are kept as is, repaired copy is used for fitness evaluation only
+    EvaluateOnly,
+}
+
+impl Repair {
+    // applied to every freshly generated or bred individual before it is stored
+    pub fn produce<RM>(&self, repair_manager: &mut RM, indiv: RM::I) -> Result<RM::I, RM::E> where RM: RepairManager {
+        match *self {
+            Repair::WriteBack => Ok(try!(repair_manager.repair(&indiv)).unwrap_or(indiv)),
+            Repair::EvaluateOnly => Ok(indiv),
+        }
+    }
+
+    // applied before evaluation, returns the substitute to evaluate instead of `indiv` if any
+    pub fn evaluate<RM>(&self, repair_manager: &mut RM, indiv: &RM::I) -> Result<Option<RM::I>, RM::E> where RM: RepairManager {
+        match *self {
+            Repair::WriteBack => Ok(None),
+            Repair::EvaluateOnly => repair_manager.repair(indiv),
+        }
+    }
+}
+
+// repair manager for unconstrained problems
+pub struct NoRepair<I> {
+    _marker: PhantomData<I>,
+}
+
+impl<I> NoRepair<I> {
+    pub fn new() -> NoRepair<I> {
+        NoRepair {
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<I> RepairManager for NoRepair<I> {
+    type I = I;
+    type E = ();
+
+    fn repair(&mut self, _indiv: &Self::I) -> Result<Option<Self::I>, Self::E> {
+        Ok(None)
+    }
+}
@@ -0,0 +1,236 @@
+use std::sync::Arc;
+use std::time::Duration;
+use std::cmp::Ordering;
+use par_exec::{Executor, WorkAmount, JobIterBuild, ExecutorJobError, JobExecuteError};
+
+use super::super::set::Set;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Objective {
+    Minimize,
+    Maximize,
+}
+
+impl Objective {
+    fn best(&self, a: f64, b: f64) -> f64 {
+        match *self {
+            Objective::Minimize => a.min(b),
+            Objective::Maximize => a.max(b),
+        }
+    }
+}
+
+// fitness distribution of a single fits set
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Summary {
+    pub count: usize,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub median: f64,
+    // population standard deviation
+    pub std: f64,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Generation {
+    pub generation: usize,
+    pub fitness: Summary,
+    pub best_so_far: f64,
+    // cumulative since the start of the run
+    pub evaluations: usize,
+    // wall time spent evaluating this generation
+    pub eval_time: Duration,
+    // distance of the generation best from the mean in standard deviations (selection intensity)
+    pub selection_pressure: f64,
+}
+
+#[derive(Debug)]
+pub enum Error<ExecE, FitsE> {
+    Executor(ExecutorJobError<ExecE, JobExecuteError<FitsE, ()>>),
+}
+
+// partial sums of a chunk of the fits set
+struct Moments {
+    sum: f64,
+    sum_sq: f64,
+    min: f64,
+    max: f64,
+    values: Vec<f64>,
+}
+
+impl Moments {
+    fn new() -> Moments {
+        Moments {
+            sum: 0.0,
+            sum_sq: 0.0,
+            min: ::std::f64::INFINITY,
+            max: ::std::f64::NEG_INFINITY,
+            values: Vec::new(),
+        }
+    }
+
+    fn add(&mut self, value: f64) {
+        self.sum += value;
+        self.sum_sq += value * value;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.values.push(value);
+    }
+
+    fn merge(mut self, other: Moments) -> Moments {
+        self.sum += other.sum;
+        self.sum_sq += other.sum_sq;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        self.values.extend(other.values);
+        self
+    }
+}
+
+// fitness distribution computed in parallel over the fits set, `None` for an empty set;
+// the median is found by gathering and sorting all the values on the calling thread
+pub fn summarize<WA, Exec, S, F>(fits: Arc<S>, value: fn(&F) -> f64, exec: &mut Exec) -> Result<Option<Summary>, Error<Exec::E, S::E>> where
+    WA: WorkAmount,
+    Exec: Executor,
+    Exec::JIB: JobIterBuild<WA>,
+    S: Set<T = (F, usize)> + Sync + Send + 'static,
+    S::E: Send + 'static,
+    F: 'static
+{
+    let fits_size = fits.size();
+    if fits_size == 0 {
+        return Ok(None);
+    }
+    let maybe_moments = try!(exec.try_execute_job(
+        WA::new(fits_size),
+        move |_local_context, input_indices| {
+            let mut moments = Moments::new();
+            for index in input_indices {
+                let &(ref fitness, _) = try!(fits.get(index));
+                moments.add(value(fitness));
+            }
+            Ok(moments)
+        },
+        |_local_context, moments_a, moments_b| Ok(moments_a.merge(moments_b)))
+        .map_err(Error::Executor));
+
+    Ok(maybe_moments.map(|mut moments| {
+        let count = moments.values.len();
+        let mean = moments.sum / count as f64;
+        moments.values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+        let median = if count % 2 == 1 {
+            moments.values[count / 2]
+        } else {
+            (moments.values[count / 2 - 1] + moments.values[count / 2]) / 2.0
+        };
+        Summary {
+            count: count,
+            min: moments.min,
+            max: moments.max,
+            mean: mean,
+            median: median,
+            std: (moments.sum_sq / count as f64 - mean * mean).max(0.0).sqrt(),
+        }
+    }))
+}
+
+// accumulates the per generation time series of a run
+pub struct Collector {
+    objective: Objective,
+    evaluations: usize,
+    best_so_far: Option<f64>,
+    series: Vec<Generation>,
+}
+
+impl Collector {
+    pub fn new(objective: Objective) -> Collector {
+        Collector {
+            objective: objective,
+            evaluations: 0,
+            best_so_far: None,
+            series: Vec::new(),
+        }
+    }
+
+    // `evaluations` and `eval_time` are the ones spent on this generation only, empty fits sets are skipped
+    pub fn record<WA, Exec, S, F>(&mut self,
+                                  fits: Arc<S>,
+                                  value: fn(&F) -> f64,
+                                  evaluations: usize,
+                                  eval_time: Duration,
+                                  exec: &mut Exec)
+                                  -> Result<(), Error<Exec::E, S::E>> where
+        WA: WorkAmount,
+        Exec: Executor,
+        Exec::JIB: JobIterBuild<WA>,
+        S: Set<T = (F, usize)> + Sync + Send + 'static,
+        S::E: Send + 'static,
+        F: 'static
+    {
+        self.evaluations += evaluations;
+        let summary = match try!(summarize::<WA, _, _, _>(fits, value, exec)) {
+            Some(summary) => summary,
+            None => return Ok(()),
+        };
+        let generation_best = match self.objective {
+            Objective::Minimize => summary.min,
+            Objective::Maximize => summary.max,
+        };
+        let best_so_far = match self.best_so_far {
+            Some(best) => self.objective.best(best, generation_best),
+            None => generation_best,
+        };
+        self.best_so_far = Some(best_so_far);
+        self.series.push(Generation {
+            generation: self.series.len(),
+            fitness: summary,
+            best_so_far: best_so_far,
+            evaluations: self.evaluations,
+            eval_time: eval_time,
+            selection_pressure: if summary.std > 0.0 { (generation_best - summary.mean).abs() / summary.std } else { 0.0 },
+        });
+        Ok(())
+    }
+
+    pub fn series(&self) -> &[Generation] {
+        &self.series
+    }
+
+    pub fn into_series(self) -> Vec<Generation> {
+        self.series
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+    use par_exec::Executor;
+    use par_exec::par::{ParallelExecutor, Alternately};
+    use super::{Collector, Objective, summarize};
+
+    fn value(fitness: &i64) -> f64 {
+        *fitness as f64
+    }
+
+    #[test]
+    fn generations() {
+        let exec: ParallelExecutor<_> = Default::default();
+        let mut exec = exec.start(|| ()).unwrap();
+
+        let fits = Arc::new(vec![(4, 0), (1, 1), (3, 2), (2, 3), (10, 4)]);
+        let summary = summarize::<Alternately, _, _, _>(fits.clone(), value, &mut exec).unwrap().unwrap();
+        assert_eq!((summary.count, summary.min, summary.max, summary.mean, summary.median), (5, 1.0, 10.0, 4.0, 3.0));
+        assert!((summary.std - 10.0f64.sqrt()).abs() < 1e-9);
+        assert_eq!(summarize::<Alternately, _, _, _>(Arc::new(Vec::<(i64, usize)>::new()), value, &mut exec).unwrap(), None);
+
+        let mut collector = Collector::new(Objective::Minimize);
+        collector.record::<Alternately, _, _, _>(fits, value, 5, Duration::from_millis(3), &mut exec).unwrap();
+        collector.record::<Alternately, _, _, _>(Arc::new(vec![(6, 0), (2, 1), (8, 2), (4, 3)]), value, 4, Duration::from_millis(2), &mut exec).unwrap();
+        let series = collector.into_series();
+        assert_eq!(series.iter().map(|g| (g.generation, g.best_so_far, g.evaluations)).collect::<Vec<_>>(), vec![(0, 1.0, 5), (1, 1.0, 9)]);
+        assert!((series[0].selection_pressure - 3.0 / 10.0f64.sqrt()).abs() < 1e-9);
+        assert_eq!(series[1].eval_time, Duration::from_millis(2));
+    }
+}
@@ -0,0 +1,599 @@
+use std::{cmp, fmt};
+use std::vec::IntoIter;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use rand::{self, Rng};
+
+use super::super::pop::individual::IndividualManager;
+use super::super::pop::breed::BreedManager;
+use super::super::set::{Set, SetManager};
+
+const WORD_BITS: usize = 64;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Error {
+    LengthMismatch { len_a: usize, len_b: usize, },
+    IndexOutOfRange { index: usize, total: usize, },
+}
+
+fn words_for(len: usize) -> usize {
+    (len + WORD_BITS - 1) / WORD_BITS
+}
+
+// mask of bits [lo, hi) within a single word
+fn word_mask(lo: usize, hi: usize) -> u64 {
+    if hi - lo == WORD_BITS { !0 } else { ((1u64 << (hi - lo)) - 1) << lo }
+}
+
+// packed bitstring, bits beyond `len` in the last word are always kept zero
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct BitString {
+    len: usize,
+    words: Vec<u64>,
+}
+
+impl BitString {
+    pub fn new(len: usize) -> BitString {
+        BitString {
+            len: len,
+            words: vec![0; words_for(len)],
+        }
+    }
+
+    pub fn random<R>(len: usize, rng: &mut R) -> BitString where R: Rng {
+        let mut bits = BitString {
+            len: len,
+            words: (0 .. words_for(len)).map(|_| rng.gen()).collect(),
+        };
+        bits.mask_tail();
+        bits
+    }
+
+    pub fn from_bools(values: &[bool]) -> BitString {
+        let mut bits = BitString::new(values.len());
+        for (index, &value) in values.iter().enumerate() {
+            bits.set(index, value);
+        }
+        bits
+    }
+
+    fn mask_tail(&mut self) {
+        let tail = self.len % WORD_BITS;
+        if tail != 0 {
+            if let Some(last) = self.words.last_mut() {
+                *last &= word_mask(0, tail);
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+
+    pub fn get(&self, index: usize) -> bool {
+        assert!(index < self.len);
+        self.words[index / WORD_BITS] & (1 << (index % WORD_BITS)) != 0
+    }
+
+    pub fn set(&mut self, index: usize, value: bool) {
+        assert!(index < self.len);
+        let mask = 1 << (index % WORD_BITS);
+        if value {
+            self.words[index / WORD_BITS] |= mask;
+        } else {
+            self.words[index / WORD_BITS] &= !mask;
+        }
+    }
+
+    pub fn flip(&mut self, index: usize) {
+        assert!(index < self.len);
+        self.words[index / WORD_BITS] ^= 1 << (index % WORD_BITS);
+    }
+
+    pub fn count_ones(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    pub fn hamming_distance(&self, other: &BitString) -> Result<usize, Error> {
+        try!(check_lengths(self, other));
+        Ok(self.words.iter().zip(other.words.iter()).map(|(a, b)| (a ^ b).count_ones() as usize).sum())
+    }
+}
+
+fn check_lengths(parent_a: &BitString, parent_b: &BitString) -> Result<usize, Error> {
+    if parent_a.len == parent_b.len {
+        Ok(parent_a.len)
+    } else {
+        Err(Error::LengthMismatch { len_a: parent_a.len, len_b: parent_b.len, })
+    }
+}
+
+// copy of `base` with bits [from, to) taken from `other`
+fn blend_range(base: &BitString, other: &BitString, from: usize, to: usize) -> BitString {
+    let mut child = base.clone();
+    if from < to {
+        for word in from / WORD_BITS .. words_for(to) {
+            let lo = from.max(word * WORD_BITS) - word * WORD_BITS;
+            let hi = to.min((word + 1) * WORD_BITS) - word * WORD_BITS;
+            let mask = word_mask(lo, hi);
+            child.words[word] = (base.words[word] & !mask) | (other.words[word] & mask);
+        }
+    }
+    child
+}
+
+// bits [0, point) are inherited from `parent_a`, the rest from `parent_b`
+pub fn one_point_crossover_with(parent_a: &BitString, parent_b: &BitString, point: usize) -> Result<BitString, Error> {
+    let len = try!(check_lengths(parent_a, parent_b));
+    Ok(blend_range(parent_a, parent_b, point.min(len), len))
+}
+
+pub fn one_point_crossover<R>(parent_a: &BitString, parent_b: &BitString, rng: &mut R) -> Result<BitString, Error> where R: Rng {
+    let len = try!(check_lengths(parent_a, parent_b));
+    one_point_crossover_with(parent_a, parent_b, rng.gen_range(0, len + 1))
+}
+
+// bits [from, to) are inherited from `parent_b`, the rest from `parent_a`
+pub fn two_point_crossover_with(parent_a: &BitString, parent_b: &BitString, from: usize, to: usize) -> Result<BitString, Error> {
+    let len = try!(check_lengths(parent_a, parent_b));
+    Ok(blend_range(parent_a, parent_b, from.min(len), to.min(len)))
+}
+
+pub fn two_point_crossover<R>(parent_a: &BitString, parent_b: &BitString, rng: &mut R) -> Result<BitString, Error> where R: Rng {
+    let len = try!(check_lengths(parent_a, parent_b));
+    let (a, b) = (rng.gen_range(0, len + 1), rng.gen_range(0, len + 1));
+    two_point_crossover_with(parent_a, parent_b, a.min(b), a.max(b))
+}
+
+pub fn uniform_crossover<R>(parent_a: &BitString, parent_b: &BitString, rng: &mut R) -> Result<BitString, Error> where R: Rng {
+    try!(check_lengths(parent_a, parent_b));
+    let mut child = parent_a.clone();
+    for (word, (&a, &b)) in child.words.iter_mut().zip(parent_a.words.iter().zip(parent_b.words.iter())) {
+        let mask: u64 = rng.gen();
+        *word = (a & !mask) | (b & mask);
+    }
+    Ok(child)
+}
+
+// HUX: exactly half of the differing bits are taken from `parent_b`
+pub fn half_uniform_crossover<R>(parent_a: &BitString, parent_b: &BitString, rng: &mut R) -> Result<BitString, Error> where R: Rng {
+    try!(check_lengths(parent_a, parent_b));
+    let mut differing = Vec::new();
+    for (word, (&a, &b)) in parent_a.words.iter().zip(parent_b.words.iter()).enumerate() {
+        let mut diff = a ^ b;
+        while diff != 0 {
+            differing.push(word * WORD_BITS + diff.trailing_zeros() as usize);
+            diff &= diff - 1;
+        }
+    }
+    rng.shuffle(&mut differing);
+    let mut child = parent_a.clone();
+    for &index in differing.iter().take(differing.len() / 2) {
+        child.flip(index);
+    }
+    Ok(child)
+}
+
+// flips every bit independently with probability `rate`, returns the number of flipped bits
+pub fn bit_flip_mutation<R>(bits: &mut BitString, rate: f64, rng: &mut R) -> usize where R: Rng {
+    let mut flipped = 0;
+    for index in 0 .. bits.len {
+        if rng.gen::<f64>() < rate {
+            bits.flip(index);
+            flipped += 1;
+        }
+    }
+    flipped
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Crossover {
+    OnePoint,
+    TwoPoint,
+    Uniform,
+    HalfUniform,
+}
+
+impl Crossover {
+    pub fn apply<R>(&self, parent_a: &BitString, parent_b: &BitString, rng: &mut R) -> Result<BitString, Error> where R: Rng {
+        match *self {
+            Crossover::OnePoint => one_point_crossover(parent_a, parent_b, rng),
+            Crossover::TwoPoint => two_point_crossover(parent_a, parent_b, rng),
+            Crossover::Uniform => uniform_crossover(parent_a, parent_b, rng),
+            Crossover::HalfUniform => half_uniform_crossover(parent_a, parent_b, rng),
+        }
+    }
+}
+
+// generates random bitstrings of the given length and evaluates them with `fitness`
+pub struct BitStringManager<F, FI> where F: FnMut(&BitString) -> FI {
+    len: usize,
+    fitness: F,
+}
+
+impl<F, FI> BitStringManager<F, FI> where F: FnMut(&BitString) -> FI {
+    pub fn new(len: usize, fitness: F) -> BitStringManager<F, FI> {
+        BitStringManager {
+            len: len,
+            fitness: fitness,
+        }
+    }
+}
+
+impl<F, FI> IndividualManager for BitStringManager<F, FI> where F: FnMut(&BitString) -> FI {
+    type I = BitString;
+    type FI = FI;
+    type E = Error;
+
+    fn generate(&mut self, _index: usize) -> Result<Self::I, Self::E> {
+        Ok(BitString::random(self.len, &mut rand::thread_rng()))
+    }
+
+    fn fitness(&mut self, indiv: &Self::I) -> Result<Self::FI, Self::E> {
+        Ok((self.fitness)(indiv))
+    }
+}
+
+pub struct BitStringBreeder {
+    crossover: Crossover,
+    mutation_rate: f64,
+}
+
+impl BitStringBreeder {
+    pub fn new(crossover: Crossover, mutation_rate: f64) -> BitStringBreeder {
+        BitStringBreeder {
+            crossover: crossover,
+            mutation_rate: mutation_rate,
+        }
+    }
+}
+
+impl BreedManager for BitStringBreeder {
+    type I = BitString;
+    type E = Error;
+
+    fn breed(&mut self, parent_a: &Self::I, parent_b: &Self::I) -> Result<Self::I, Self::E> {
+        let mut rng = rand::thread_rng();
+        let mut child = try!(self.crossover.apply(parent_a, parent_b, &mut rng));
+        bit_flip_mutation(&mut child, self.mutation_rate, &mut rng);
+        Ok(child)
+    }
+}
+
+// bitstring stored in a shared block of packed words, cloning it does not copy the words
+#[derive(Clone)]
+pub struct PackedBits {
+    len: usize,
+    offset: usize,
+    block: Arc<Vec<AtomicU64>>,
+}
+
+impl PackedBits {
+    pub fn from_bit_string(bits: &BitString) -> PackedBits {
+        PackedBits {
+            len: bits.len,
+            offset: 0,
+            block: Arc::new(bits.words.iter().map(|&word| AtomicU64::new(word)).collect()),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn word(&self, index: usize) -> u64 {
+        assert!(index < words_for(self.len));
+        self.block[self.offset + index].load(Ordering::Relaxed)
+    }
+
+    pub fn get(&self, index: usize) -> bool {
+        assert!(index < self.len);
+        self.word(index / WORD_BITS) & (1 << (index % WORD_BITS)) != 0
+    }
+
+    pub fn count_ones(&self) -> usize {
+        (0 .. words_for(self.len)).map(|i| self.word(i).count_ones() as usize).sum()
+    }
+
+    pub fn to_bit_string(&self) -> BitString {
+        BitString {
+            len: self.len,
+            words: (0 .. words_for(self.len)).map(|i| self.word(i)).collect(),
+        }
+    }
+}
+
+impl PartialEq for PackedBits {
+    fn eq(&self, other: &PackedBits) -> bool {
+        self.len == other.len && (0 .. words_for(self.len)).all(|i| self.word(i) == other.word(i))
+    }
+}
+
+impl fmt::Debug for PackedBits {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PackedBits")
+            .field("len", &self.len)
+            .field("words", &self.to_bit_string().words)
+            .finish()
+    }
+}
+
+// set of equally sized bitstrings packed contiguously into blocks of words: items are written in place
+// after the previous ones and the set moves to a new twice as large block only when the current one is full
+pub struct BitStringSet {
+    len: usize,
+    block: Arc<Vec<AtomicU64>>,
+    used: usize,
+    items: Vec<PackedBits>,
+}
+
+fn make_block(words: usize) -> Arc<Vec<AtomicU64>> {
+    Arc::new((0 .. words).map(|_| AtomicU64::new(0)).collect())
+}
+
+impl BitStringSet {
+    pub fn new(len: usize) -> BitStringSet {
+        BitStringSet::with_capacity(len, 0)
+    }
+
+    pub fn with_capacity(len: usize, capacity: usize) -> BitStringSet {
+        BitStringSet {
+            len: len,
+            block: make_block(capacity * words_for(len)),
+            used: 0,
+            items: Vec::with_capacity(capacity),
+        }
+    }
+
+    pub fn bits_len(&self) -> usize {
+        self.len
+    }
+
+    pub fn reserve(&mut self, additional: usize) {
+        let stride = words_for(self.len);
+        if self.used + additional * stride > self.block.len() {
+            self.block = make_block(additional * stride);
+            self.used = 0;
+        }
+        self.items.reserve(additional);
+    }
+}
+
+pub struct BitStringSetIter {
+    items: IntoIter<PackedBits>,
+}
+
+impl Iterator for BitStringSetIter {
+    type Item = Result<PackedBits, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.items.next().map(Ok)
+    }
+}
+
+impl Set for BitStringSet {
+    type T = PackedBits;
+    type E = Error;
+    type I = BitStringSetIter;
+
+    fn size(&self) -> usize {
+        self.items.len()
+    }
+
+    fn get(&self, index: usize) -> Result<&Self::T, Self::E> {
+        let slice: &[PackedBits] = &self.items;
+        slice.get(index).ok_or(Error::IndexOutOfRange { index: index, total: self.items.len(), })
+    }
+
+    fn add(&mut self, item: Self::T) -> Result<(), Self::E> {
+        if item.len != self.len {
+            return Err(Error::LengthMismatch { len_a: self.len, len_b: item.len, });
+        }
+        let stride = words_for(self.len);
+        if self.used + stride > self.block.len() {
+            let grown = cmp::max(self.block.len() * 2, stride);
+            self.block = make_block(grown);
+            self.used = 0;
+        }
+        for i in 0 .. stride {
+            self.block[self.used + i].store(item.word(i), Ordering::Relaxed);
+        }
+        self.items.push(PackedBits {
+            len: self.len,
+            offset: self.used,
+            block: self.block.clone(),
+        });
+        self.used += stride;
+        Ok(())
+    }
+
+    fn into_iter(self) -> Self::I {
+        BitStringSetIter {
+            items: IntoIterator::into_iter(self.items),
+        }
+    }
+}
+
+pub struct BitStringSetManager {
+    len: usize,
+}
+
+impl BitStringSetManager {
+    pub fn new(len: usize) -> BitStringSetManager {
+        BitStringSetManager {
+            len: len,
+        }
+    }
+}
+
+impl SetManager for BitStringSetManager {
+    type S = BitStringSet;
+    type E = ();
+
+    fn make_set(&mut self, size_hint: Option<usize>) -> Result<Self::S, Self::E> {
+        Ok(BitStringSet::with_capacity(self.len, size_hint.unwrap_or(0)))
+    }
+
+    fn reserve(&mut self, set: &mut Self::S, additional: usize) -> Result<(), Self::E> {
+        Ok(set.reserve(additional))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use rand;
+    use par_exec::Executor;
+    use par_exec::par::{ParallelExecutor, Alternately};
+    use super::super::super::set::Set;
+    use super::super::super::pop::individual::IndividualManager;
+    use super::super::super::pop::init::PopulationInit;
+    use super::super::super::pop::init::limited::{self, LimitedPopulationInit};
+    use super::super::super::pop::repair::{NoRepair, RetrieveRepairManager};
+    use super::{Error, BitString, BitStringSet, BitStringSetManager, PackedBits, Crossover, bit_flip_mutation};
+    use super::{one_point_crossover_with, two_point_crossover_with, half_uniform_crossover};
+
+    #[test]
+    fn known_offspring() {
+        let zeros = BitString::new(130);
+        let ones = BitString::from_bools(&[true; 130]);
+        let child = one_point_crossover_with(&zeros, &ones, 70).unwrap();
+        assert_eq!(child.count_ones(), 60);
+        assert!(!child.get(69) && child.get(70) && child.get(129));
+        let child = two_point_crossover_with(&zeros, &ones, 60, 128).unwrap();
+        assert_eq!(child.count_ones(), 68);
+        assert!(!child.get(59) && child.get(60) && child.get(127) && !child.get(128));
+        assert_eq!(one_point_crossover_with(&zeros, &BitString::new(3), 1), Err(Error::LengthMismatch { len_a: 130, len_b: 3, }));
+    }
+
+    #[test]
+    fn operators() {
+        let mut rng = rand::thread_rng();
+        for len in 0 .. 200 {
+            let (parent_a, parent_b) = (BitString::random(len, &mut rng), BitString::random(len, &mut rng));
+            let distance = parent_a.hamming_distance(&parent_b).unwrap();
+            let child = half_uniform_crossover(&parent_a, &parent_b, &mut rng).unwrap();
+            assert_eq!(child.hamming_distance(&parent_a), Ok(distance / 2));
+            for crossover in [Crossover::OnePoint, Crossover::TwoPoint, Crossover::Uniform, Crossover::HalfUniform].iter() {
+                let child = crossover.apply(&parent_a, &parent_b, &mut rng).unwrap();
+                assert!((0 .. len).all(|i| child.get(i) == parent_a.get(i) || child.get(i) == parent_b.get(i)));
+                assert!(child.count_ones() <= len);
+            }
+            let mut mutant = parent_a.clone();
+            assert_eq!(bit_flip_mutation(&mut mutant, 0.0, &mut rng), 0);
+            assert_eq!(bit_flip_mutation(&mut mutant, 1.0, &mut rng), len);
+            assert_eq!(mutant.count_ones(), len - parent_a.count_ones());
+        }
+    }
+
+    #[test]
+    fn contiguous_set() {
+        let mut rng = rand::thread_rng();
+        let items: Vec<_> = (0 .. 5).map(|_| BitString::random(100, &mut rng)).collect();
+        let mut set = BitStringSet::with_capacity(100, 5);
+        for item in items.iter() {
+            assert_eq!(set.add(PackedBits::from_bit_string(item)), Ok(()));
+        }
+        assert_eq!(set.add(PackedBits::from_bit_string(&BitString::new(99))), Err(Error::LengthMismatch { len_a: 100, len_b: 99, }));
+        assert_eq!(set.size(), 5);
+        assert_eq!(set.get(3).map(|packed| packed.to_bit_string()), Ok(items[3].clone()));
+        assert_eq!(set.get(2).map(|packed| packed.count_ones()), Ok(items[2].count_ones()));
+        assert_eq!(set.get(5), Err(Error::IndexOutOfRange { index: 5, total: 5, }));
+        // items follow each other within the preallocated block
+        let (third, fourth) = (set.get(2).unwrap(), set.get(3).unwrap());
+        assert!(Arc::ptr_eq(&third.block, &fourth.block));
+        assert_eq!(fourth.offset, third.offset + 2);
+        assert_eq!(set.into_iter().map(|packed| packed.unwrap().to_bit_string()).collect::<Vec<_>>(), items);
+    }
+
+    struct PackedManager;
+    impl IndividualManager for PackedManager {
+        type I = PackedBits;
+        type FI = usize;
+        type E = ();
+
+        fn generate(&mut self, _index: usize) -> Result<Self::I, Self::E> {
+            Ok(PackedBits::from_bit_string(&BitString::random(70, &mut rand::thread_rng())))
+        }
+
+        fn fitness(&mut self, indiv: &Self::I) -> Result<Self::FI, Self::E> {
+            Ok(indiv.count_ones())
+        }
+    }
+
+    struct LocalContext {
+        set_manager: BitStringSetManager,
+        indiv_manager: PackedManager,
+        repair_manager: NoRepair<PackedBits>,
+    }
+
+    impl limited::RetrievePopulationManager for LocalContext {
+        type PopM = BitStringSetManager;
+
+        fn retrieve(&mut self) -> &mut Self::PopM {
+            &mut self.set_manager
+        }
+    }
+
+    impl limited::RetrieveIndividualManager for LocalContext {
+        type IM = PackedManager;
+
+        fn retrieve(&mut self) -> &mut Self::IM {
+            &mut self.indiv_manager
+        }
+    }
+
+    impl RetrieveRepairManager for LocalContext {
+        type RM = NoRepair<PackedBits>;
+
+        fn retrieve(&mut self) -> &mut Self::RM {
+            &mut self.repair_manager
+        }
+    }
+
+    struct TestPolicy;
+    impl limited::Policy for TestPolicy {
+        type LocalContext = LocalContext;
+        type Exec = ParallelExecutor<LocalContext>;
+        type Indiv = PackedBits;
+        type IndivME = ();
+        type IndivM = PackedManager;
+        type RepairME = ();
+        type RepairM = NoRepair<PackedBits>;
+        type PopE = Error;
+        type Pop = BitStringSet;
+        type PopSME = ();
+        type PopSM = BitStringSetManager;
+    }
+
+    #[test]
+    fn packed_population() {
+        let exec: ParallelExecutor<_> = Default::default();
+        let mut exec = exec.start(|| LocalContext {
+            set_manager: BitStringSetManager::new(70),
+            indiv_manager: PackedManager,
+            repair_manager: NoRepair::new(),
+        }).unwrap();
+
+        let initializer: LimitedPopulationInit<TestPolicy> = LimitedPopulationInit::new(256);
+        let population = initializer.init::<Alternately>(&mut exec).unwrap();
+        assert_eq!(population.size(), 256);
+        assert_eq!(population.bits_len(), 70);
+        let first = population.get(0).unwrap().clone();
+        let items: Vec<_> = population.into_iter().map(|packed| packed.unwrap()).collect();
+        assert_eq!(items[0], first);
+        assert!(items.iter().all(|packed| packed.len() == 70 && packed.count_ones() <= 70));
+    }
+}
@@ -0,0 +1,280 @@
+use rand::{self, Rng};
+use rand::distributions::normal::StandardNormal;
+
+use super::super::pop::individual::IndividualManager;
+use super::super::pop::breed::BreedManager;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Error {
+    LengthMismatch { len_a: usize, len_b: usize, },
+    InvalidDomain { index: usize, },
+    OutOfDomain { index: usize, },
+}
+
+// domain of a single gene, bounds are inclusive
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Domain {
+    Integer { lower: i64, upper: i64, },
+    Categorical { size: usize, },
+    Real { lower: f64, upper: f64, },
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Gene {
+    Integer(i64),
+    Categorical(usize),
+    Real(f64),
+}
+
+impl Domain {
+    fn is_valid(&self) -> bool {
+        match *self {
+            Domain::Integer { lower, upper, } => lower <= upper,
+            Domain::Categorical { size, } => size > 0,
+            Domain::Real { lower, upper, } => lower <= upper && lower.is_finite() && upper.is_finite(),
+        }
+    }
+
+    pub fn contains(&self, gene: &Gene) -> bool {
+        match (*self, *gene) {
+            (Domain::Integer { lower, upper, }, Gene::Integer(value)) => value >= lower && value <= upper,
+            (Domain::Categorical { size, }, Gene::Categorical(value)) => value < size,
+            (Domain::Real { lower, upper, }, Gene::Real(value)) => value >= lower && value <= upper,
+            _ => false,
+        }
+    }
+
+    pub fn sample<R>(&self, rng: &mut R) -> Gene where R: Rng {
+        match *self {
+            Domain::Integer { lower, upper, } => {
+                let span = upper as f64 - lower as f64 + 1.0;
+                let offset = (rng.gen::<f64>() * span).floor().min(span - 1.0) as i64;
+                Gene::Integer(lower.saturating_add(offset).min(upper))
+            },
+            Domain::Categorical { size, } =>
+                Gene::Categorical(rng.gen_range(0, size)),
+            Domain::Real { lower, upper, } =>
+                Gene::Real(lower + rng.gen::<f64>() * (upper - lower)),
+        }
+    }
+}
+
+// chromosome layout: one domain per gene position
+#[derive(Clone, PartialEq, Debug)]
+pub struct Genome {
+    domains: Vec<Domain>,
+}
+
+impl Genome {
+    pub fn new(domains: Vec<Domain>) -> Result<Genome, Error> {
+        if let Some(index) = domains.iter().position(|d| !d.is_valid()) {
+            return Err(Error::InvalidDomain { index: index, });
+        }
+        Ok(Genome {
+            domains: domains,
+        })
+    }
+
+    pub fn domains(&self) -> &[Domain] {
+        &self.domains
+    }
+
+    pub fn len(&self) -> usize {
+        self.domains.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.domains.is_empty()
+    }
+
+    pub fn random<R>(&self, rng: &mut R) -> Vec<Gene> where R: Rng {
+        self.domains.iter().map(|domain| domain.sample(rng)).collect()
+    }
+
+    pub fn check(&self, genes: &[Gene]) -> Result<(), Error> {
+        if genes.len() != self.domains.len() {
+            return Err(Error::LengthMismatch { len_a: self.domains.len(), len_b: genes.len(), });
+        }
+        match self.domains.iter().zip(genes.iter()).position(|(domain, gene)| !domain.contains(gene)) {
+            Some(index) => Err(Error::OutOfDomain { index: index, }),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Crossover {
+    // every gene is taken from either parent
+    Uniform,
+    // numeric genes are drawn BLX-α style and clipped to the domain, categoricals are taken from either parent
+    Blend { alpha: f64, },
+}
+
+impl Crossover {
+    pub fn apply<R>(&self, genome: &Genome, parent_a: &[Gene], parent_b: &[Gene], rng: &mut R) -> Result<Vec<Gene>, Error> where R: Rng {
+        try!(genome.check(parent_a));
+        try!(genome.check(parent_b));
+        Ok(genome.domains.iter().zip(parent_a.iter().zip(parent_b.iter())).map(|(domain, (&a, &b))| {
+            let alpha = match *self {
+                Crossover::Uniform => return if rng.gen() { a } else { b },
+                Crossover::Blend { alpha, } => alpha,
+            };
+            match (*domain, a, b) {
+                (Domain::Integer { lower, upper, }, Gene::Integer(a), Gene::Integer(b)) => {
+                    let (lo, hi) = (a.min(b) as f64, a.max(b) as f64);
+                    let extent = alpha * (hi - lo);
+                    let value = (lo - extent + rng.gen::<f64>() * (hi - lo + 2.0 * extent)).round() as i64;
+                    Gene::Integer(value.max(lower).min(upper))
+                },
+                (Domain::Real { lower, upper, }, Gene::Real(a), Gene::Real(b)) => {
+                    let (lo, hi) = (a.min(b), a.max(b));
+                    let extent = alpha * (hi - lo);
+                    Gene::Real((lo - extent + rng.gen::<f64>() * (hi - lo + 2.0 * extent)).max(lower).min(upper))
+                },
+                _ =>
+                    if rng.gen() { a } else { b },
+            }
+        }).collect())
+    }
+}
+
+// every gene mutates independently with probability `rate`: reals get gaussian noise of `real_sigma`
+// times the domain width, integers move by up to `integer_step` and categoricals switch to another value
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Mutation {
+    pub rate: f64,
+    pub real_sigma: f64,
+    pub integer_step: i64,
+}
+
+impl Mutation {
+    pub fn apply<R>(&self, genome: &Genome, genes: &mut [Gene], rng: &mut R) -> Result<(), Error> where R: Rng {
+        try!(genome.check(genes));
+        for (domain, gene) in genome.domains.iter().zip(genes.iter_mut()) {
+            if rng.gen::<f64>() >= self.rate {
+                continue;
+            }
+            *gene = match (*domain, *gene) {
+                (Domain::Integer { lower, upper, }, Gene::Integer(value)) => {
+                    let step = rng.gen_range(1, self.integer_step.max(1) + 1);
+                    let value = if rng.gen() { value.saturating_add(step) } else { value.saturating_sub(step) };
+                    Gene::Integer(value.max(lower).min(upper))
+                },
+                (Domain::Categorical { size, }, Gene::Categorical(value)) if size > 1 => {
+                    let other = rng.gen_range(0, size - 1);
+                    Gene::Categorical(if other >= value { other + 1 } else { other })
+                },
+                (Domain::Real { lower, upper, }, Gene::Real(value)) => {
+                    let StandardNormal(step) = rng.gen::<StandardNormal>();
+                    Gene::Real((value + self.real_sigma * (upper - lower) * step).max(lower).min(upper))
+                },
+                (_, unchanged) =>
+                    unchanged,
+            };
+        }
+        Ok(())
+    }
+}
+
+// generates random in-domain chromosomes and evaluates them with `fitness`
+pub struct MixedManager<F, FI> where F: FnMut(&[Gene]) -> FI {
+    genome: Genome,
+    fitness: F,
+}
+
+impl<F, FI> MixedManager<F, FI> where F: FnMut(&[Gene]) -> FI {
+    pub fn new(genome: Genome, fitness: F) -> MixedManager<F, FI> {
+        MixedManager {
+            genome: genome,
+            fitness: fitness,
+        }
+    }
+}
+
+impl<F, FI> IndividualManager for MixedManager<F, FI> where F: FnMut(&[Gene]) -> FI {
+    type I = Vec<Gene>;
+    type FI = FI;
+    type E = Error;
+
+    fn generate(&mut self, _index: usize) -> Result<Self::I, Self::E> {
+        Ok(self.genome.random(&mut rand::thread_rng()))
+    }
+
+    fn fitness(&mut self, indiv: &Self::I) -> Result<Self::FI, Self::E> {
+        try!(self.genome.check(indiv));
+        Ok((self.fitness)(indiv))
+    }
+}
+
+pub struct MixedBreeder {
+    genome: Genome,
+    crossover: Crossover,
+    mutation: Mutation,
+}
+
+impl MixedBreeder {
+    pub fn new(genome: Genome, crossover: Crossover, mutation: Mutation) -> MixedBreeder {
+        MixedBreeder {
+            genome: genome,
+            crossover: crossover,
+            mutation: mutation,
+        }
+    }
+}
+
+impl BreedManager for MixedBreeder {
+    type I = Vec<Gene>;
+    type E = Error;
+
+    fn breed(&mut self, parent_a: &Self::I, parent_b: &Self::I) -> Result<Self::I, Self::E> {
+        let mut rng = rand::thread_rng();
+        let mut child = try!(self.crossover.apply(&self.genome, parent_a, parent_b, &mut rng));
+        try!(self.mutation.apply(&self.genome, &mut child, &mut rng));
+        Ok(child)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand;
+    use super::{Error, Domain, Gene, Genome, Crossover, Mutation, MixedBreeder};
+    use super::super::super::pop::breed::BreedManager;
+
+    fn make_genome() -> Genome {
+        Genome::new(vec![
+            Domain::Integer { lower: -3, upper: 3, },
+            Domain::Categorical { size: 4, },
+            Domain::Real { lower: 0.0, upper: 1.0, },
+            Domain::Integer { lower: 7, upper: 7, },
+            Domain::Categorical { size: 1, },
+        ]).unwrap()
+    }
+
+    #[test]
+    fn domains() {
+        assert_eq!(Genome::new(vec![Domain::Real { lower: 0.0, upper: 1.0, }, Domain::Categorical { size: 0, }]),
+                   Err(Error::InvalidDomain { index: 1, }));
+        let genome = make_genome();
+        assert_eq!(genome.check(&[Gene::Integer(0)]), Err(Error::LengthMismatch { len_a: 5, len_b: 1, }));
+        let genes = [Gene::Integer(0), Gene::Real(0.5), Gene::Real(0.5), Gene::Integer(7), Gene::Categorical(0)];
+        assert_eq!(genome.check(&genes), Err(Error::OutOfDomain { index: 1, }));
+        let genes = [Gene::Integer(0), Gene::Categorical(3), Gene::Real(0.5), Gene::Integer(8), Gene::Categorical(0)];
+        assert_eq!(genome.check(&genes), Err(Error::OutOfDomain { index: 3, }));
+    }
+
+    #[test]
+    fn operators_stay_in_domain() {
+        let mut rng = rand::thread_rng();
+        let genome = make_genome();
+        let mutation = Mutation { rate: 1.0, real_sigma: 2.0, integer_step: 10, };
+        for crossover in [Crossover::Uniform, Crossover::Blend { alpha: 1.0, }].iter() {
+            let mut breeder = MixedBreeder::new(genome.clone(), *crossover, mutation);
+            for _ in 0 .. 256 {
+                let (parent_a, parent_b) = (genome.random(&mut rng), genome.random(&mut rng));
+                assert_eq!(genome.check(&parent_a), Ok(()));
+                let child = breeder.breed(&parent_a, &parent_b).unwrap();
+                assert_eq!(genome.check(&child), Ok(()), "{:?} produced {:?}", crossover, child);
+            }
+        }
+    }
+}
@@ -0,0 +1,4 @@
+pub mod permutation;
+pub mod bitstring;
+pub mod real;
+pub mod mixed;
@@ -0,0 +1,472 @@
+use std::marker::PhantomData;
+use rand::{self, Rng};
+
+use super::super::pop::individual::IndividualManager;
+use super::super::pop::neighbour::NeighbourManager;
+use super::super::pop::breed::BreedManager;
+use super::super::pop::local_search::{LocalSearchManager, SearchError};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Error {
+    LengthMismatch { len_a: usize, len_b: usize, },
+    NotPermutation,
+}
+
+// crossovers index by gene, so both parents have to be permutations of the same length
+fn check_parents(parent_a: &[usize], parent_b: &[usize]) -> Result<usize, Error> {
+    if parent_a.len() != parent_b.len() {
+        Err(Error::LengthMismatch { len_a: parent_a.len(), len_b: parent_b.len(), })
+    } else if !is_valid(parent_a) || !is_valid(parent_b) {
+        Err(Error::NotPermutation)
+    } else {
+        Ok(parent_a.len())
+    }
+}
+
+// random segment [from, to] with from <= to
+fn segment<R>(len: usize, rng: &mut R) -> (usize, usize) where R: Rng {
+    let (a, b) = (rng.gen_range(0, len), rng.gen_range(0, len));
+    if a <= b { (a, b) } else { (b, a) }
+}
+
+fn positions(perm: &[usize]) -> Vec<usize> {
+    let mut positions = vec![0; perm.len()];
+    for (position, &gene) in perm.iter().enumerate() {
+        positions[gene] = position;
+    }
+    positions
+}
+
+pub fn random<R>(len: usize, rng: &mut R) -> Vec<usize> where R: Rng {
+    let mut perm: Vec<usize> = (0 .. len).collect();
+    rng.shuffle(&mut perm);
+    perm
+}
+
+pub fn is_valid(perm: &[usize]) -> bool {
+    let mut seen = vec![false; perm.len()];
+    for &gene in perm {
+        if gene >= perm.len() || seen[gene] {
+            return false;
+        }
+        seen[gene] = true;
+    }
+    true
+}
+
+// OX: segment is inherited from `parent_a`, the rest is filled in order of `parent_b` starting after the segment
+pub fn order_crossover_with(parent_a: &[usize], parent_b: &[usize], from: usize, to: usize) -> Result<Vec<usize>, Error> {
+    let len = try!(check_parents(parent_a, parent_b));
+    if len == 0 {
+        return Ok(vec![]);
+    }
+    let mut used = vec![false; len];
+    let mut child = vec![0; len];
+    for i in from .. to + 1 {
+        child[i] = parent_a[i];
+        used[parent_a[i]] = true;
+    }
+    let mut target = (to + 1) % len;
+    for k in 0 .. len {
+        let gene = parent_b[(to + 1 + k) % len];
+        if !used[gene] {
+            child[target] = gene;
+            used[gene] = true;
+            target = (target + 1) % len;
+        }
+    }
+    Ok(child)
+}
+
+pub fn order_crossover<R>(parent_a: &[usize], parent_b: &[usize], rng: &mut R) -> Result<Vec<usize>, Error> where R: Rng {
+    let len = try!(check_parents(parent_a, parent_b));
+    if len == 0 {
+        return Ok(vec![]);
+    }
+    let (from, to) = segment(len, rng);
+    order_crossover_with(parent_a, parent_b, from, to)
+}
+
+// PMX: segment is inherited from `parent_a`, the rest of `parent_b` genes are placed following the segment mapping
+pub fn partially_mapped_crossover_with(parent_a: &[usize], parent_b: &[usize], from: usize, to: usize) -> Result<Vec<usize>, Error> {
+    let len = try!(check_parents(parent_a, parent_b));
+    if len == 0 {
+        return Ok(vec![]);
+    }
+    let mut child: Vec<Option<usize>> = vec![None; len];
+    let mut in_segment = vec![false; len];
+    for i in from .. to + 1 {
+        child[i] = Some(parent_a[i]);
+        in_segment[parent_a[i]] = true;
+    }
+    let positions_b = positions(parent_b);
+    for i in from .. to + 1 {
+        let gene = parent_b[i];
+        if in_segment[gene] {
+            continue;
+        }
+        let mut position = i;
+        while position >= from && position <= to {
+            position = positions_b[parent_a[position]];
+        }
+        child[position] = Some(gene);
+    }
+    Ok(child.into_iter()
+       .enumerate()
+       .map(|(i, gene)| gene.unwrap_or(parent_b[i]))
+       .collect())
+}
+
+pub fn partially_mapped_crossover<R>(parent_a: &[usize], parent_b: &[usize], rng: &mut R) -> Result<Vec<usize>, Error> where R: Rng {
+    let len = try!(check_parents(parent_a, parent_b));
+    if len == 0 {
+        return Ok(vec![]);
+    }
+    let (from, to) = segment(len, rng);
+    partially_mapped_crossover_with(parent_a, parent_b, from, to)
+}
+
+// CX: positions are split into cycles inherited alternately from `parent_a` and `parent_b`
+pub fn cycle_crossover(parent_a: &[usize], parent_b: &[usize]) -> Result<Vec<usize>, Error> {
+    let len = try!(check_parents(parent_a, parent_b));
+    let positions_a = positions(parent_a);
+    let mut child: Vec<Option<usize>> = vec![None; len];
+    let mut from_a = true;
+    for start in 0 .. len {
+        if child[start].is_some() {
+            continue;
+        }
+        let mut position = start;
+        loop {
+            child[position] = Some(if from_a { parent_a[position] } else { parent_b[position] });
+            position = positions_a[parent_b[position]];
+            if position == start {
+                break;
+            }
+        }
+        from_a = !from_a;
+    }
+    Ok(child.into_iter().map(|gene| gene.unwrap_or(0)).collect())
+}
+
+// ERX: builds a tour preferring edges present in both parents and visiting cities with fewer remaining edges first
+pub fn edge_recombination<R>(parent_a: &[usize], parent_b: &[usize], rng: &mut R) -> Result<Vec<usize>, Error> where R: Rng {
+    let len = try!(check_parents(parent_a, parent_b));
+    if len == 0 {
+        return Ok(vec![]);
+    }
+    let mut edges: Vec<Vec<usize>> = vec![Vec::with_capacity(4); len];
+    for parent in &[parent_a, parent_b] {
+        for i in 0 .. len {
+            let (gene, next) = (parent[i], parent[(i + 1) % len]);
+            if gene == next {
+                continue;
+            }
+            if !edges[gene].contains(&next) {
+                edges[gene].push(next);
+            }
+            if !edges[next].contains(&gene) {
+                edges[next].push(gene);
+            }
+        }
+    }
+
+    let mut visited = vec![false; len];
+    let mut child = Vec::with_capacity(len);
+    let mut current = parent_a[0];
+    loop {
+        child.push(current);
+        visited[current] = true;
+        if child.len() == len {
+            break;
+        }
+        for neighbours in edges.iter_mut() {
+            neighbours.retain(|&g| g != current);
+        }
+        let candidates = ::std::mem::replace(&mut edges[current], Vec::new());
+        current = if candidates.is_empty() {
+            let unvisited: Vec<usize> = (0 .. len).filter(|&g| !visited[g]).collect();
+            unvisited[rng.gen_range(0, unvisited.len())]
+        } else {
+            let fewest = candidates.iter().map(|&g| edges[g].len()).min().unwrap_or(0);
+            let best: Vec<usize> = candidates.into_iter().filter(|&g| edges[g].len() == fewest).collect();
+            best[rng.gen_range(0, best.len())]
+        };
+    }
+    Ok(child)
+}
+
+pub fn swap_mutation<R>(perm: &mut [usize], rng: &mut R) where R: Rng {
+    if perm.len() > 1 {
+        let (a, b) = (rng.gen_range(0, perm.len()), rng.gen_range(0, perm.len()));
+        perm.swap(a, b);
+    }
+}
+
+pub fn insertion_mutation<R>(perm: &mut Vec<usize>, rng: &mut R) where R: Rng {
+    if perm.len() > 1 {
+        let gene = perm.remove(rng.gen_range(0, perm.len()));
+        let position = rng.gen_range(0, perm.len() + 1);
+        perm.insert(position, gene);
+    }
+}
+
+pub fn inversion_mutation<R>(perm: &mut [usize], rng: &mut R) where R: Rng {
+    if perm.len() > 1 {
+        let (from, to) = segment(perm.len(), rng);
+        perm[from .. to + 1].reverse();
+    }
+}
+
+pub fn scramble_mutation<R>(perm: &mut [usize], rng: &mut R) where R: Rng {
+    if perm.len() > 1 {
+        let (from, to) = segment(perm.len(), rng);
+        rng.shuffle(&mut perm[from .. to + 1]);
+    }
+}
+
+// 2-opt: reverses tour segments while it shortens the closed tour, returns true if the tour was improved
+pub fn two_opt<D>(tour: &mut [usize], distance: &D, max_passes: usize) -> bool where D: Fn(usize, usize) -> f64 {
+    let len = tour.len();
+    if len < 4 {
+        return false;
+    }
+    let mut improved = false;
+    for _ in 0 .. max_passes {
+        let mut pass_improved = false;
+        for i in 0 .. len - 1 {
+            for j in i + 2 .. len {
+                if i == 0 && j == len - 1 {
+                    continue;
+                }
+                let (a, b, c, d) = (tour[i], tour[i + 1], tour[j], tour[(j + 1) % len]);
+                let delta = distance(a, c) + distance(b, d) - distance(a, b) - distance(c, d);
+                if delta < -1e-10 {
+                    tour[i + 1 .. j + 1].reverse();
+                    pass_improved = true;
+                }
+            }
+        }
+        if !pass_improved {
+            break;
+        }
+        improved = true;
+    }
+    improved
+}
+
+pub fn tour_length<D>(tour: &[usize], distance: &D) -> f64 where D: Fn(usize, usize) -> f64 {
+    let len = tour.len();
+    (0 .. len).map(|i| distance(tour[i], tour[(i + 1) % len])).sum()
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Crossover {
+    Order,
+    PartiallyMapped,
+    Cycle,
+    EdgeRecombination,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Mutation {
+    Swap,
+    Insertion,
+    Inversion,
+    Scramble,
+}
+
+impl Crossover {
+    pub fn apply<R>(&self, parent_a: &[usize], parent_b: &[usize], rng: &mut R) -> Result<Vec<usize>, Error> where R: Rng {
+        match *self {
+            Crossover::Order => order_crossover(parent_a, parent_b, rng),
+            Crossover::PartiallyMapped => partially_mapped_crossover(parent_a, parent_b, rng),
+            Crossover::Cycle => cycle_crossover(parent_a, parent_b),
+            Crossover::EdgeRecombination => edge_recombination(parent_a, parent_b, rng),
+        }
+    }
+}
+
+impl Mutation {
+    pub fn apply<R>(&self, perm: &mut Vec<usize>, rng: &mut R) where R: Rng {
+        match *self {
+            Mutation::Swap => swap_mutation(perm, rng),
+            Mutation::Insertion => insertion_mutation(perm, rng),
+            Mutation::Inversion => inversion_mutation(perm, rng),
+            Mutation::Scramble => scramble_mutation(perm, rng),
+        }
+    }
+}
+
+// generates random permutations of the given length and evaluates them with `fitness`
+pub struct PermutationManager<F, FI> where F: FnMut(&[usize]) -> FI {
+    len: usize,
+    fitness: F,
+}
+
+impl<F, FI> PermutationManager<F, FI> where F: FnMut(&[usize]) -> FI {
+    pub fn new(len: usize, fitness: F) -> PermutationManager<F, FI> {
+        PermutationManager {
+            len: len,
+            fitness: fitness,
+        }
+    }
+}
+
+impl<F, FI> IndividualManager for PermutationManager<F, FI> where F: FnMut(&[usize]) -> FI {
+    type I = Vec<usize>;
+    type FI = FI;
+    type E = Error;
+
+    fn generate(&mut self, _index: usize) -> Result<Self::I, Self::E> {
+        Ok(random(self.len, &mut rand::thread_rng()))
+    }
+
+    fn fitness(&mut self, indiv: &Self::I) -> Result<Self::FI, Self::E> {
+        Ok((self.fitness)(indiv))
+    }
+}
+
+pub struct PermutationBreeder {
+    crossover: Crossover,
+    mutation: Mutation,
+    mutation_probability: f64,
+}
+
+impl PermutationBreeder {
+    pub fn new(crossover: Crossover, mutation: Mutation, mutation_probability: f64) -> PermutationBreeder {
+        PermutationBreeder {
+            crossover: crossover,
+            mutation: mutation,
+            mutation_probability: mutation_probability,
+        }
+    }
+}
+
+impl BreedManager for PermutationBreeder {
+    type I = Vec<usize>;
+    type E = Error;
+
+    fn breed(&mut self, parent_a: &Self::I, parent_b: &Self::I) -> Result<Self::I, Self::E> {
+        let mut rng = rand::thread_rng();
+        let mut child = try!(self.crossover.apply(parent_a, parent_b, &mut rng));
+        if rng.gen::<f64>() < self.mutation_probability {
+            self.mutation.apply(&mut child, &mut rng);
+        }
+        Ok(child)
+    }
+}
+
+// random neighbour produced by the given mutation
+pub struct PermutationNeighbour {
+    mutation: Mutation,
+}
+
+impl PermutationNeighbour {
+    pub fn new(mutation: Mutation) -> PermutationNeighbour {
+        PermutationNeighbour {
+            mutation: mutation,
+        }
+    }
+}
+
+impl NeighbourManager for PermutationNeighbour {
+    type I = Vec<usize>;
+    type E = Error;
+
+    fn neighbour(&mut self, indiv: &Self::I, _attempt: usize) -> Result<Option<Self::I>, Self::E> {
+        let mut candidate = indiv.clone();
+        self.mutation.apply(&mut candidate, &mut rand::thread_rng());
+        Ok(Some(candidate))
+    }
+}
+
+// 2-opt local improvement for memetic algorithms
+pub struct TwoOpt<D, FI> where D: Fn(usize, usize) -> f64 {
+    distance: D,
+    max_passes: usize,
+    _marker: PhantomData<FI>,
+}
+
+impl<D, FI> TwoOpt<D, FI> where D: Fn(usize, usize) -> f64 {
+    pub fn new(distance: D, max_passes: usize) -> TwoOpt<D, FI> {
+        TwoOpt {
+            distance: distance,
+            max_passes: max_passes,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<D, FI> LocalSearchManager for TwoOpt<D, FI> where D: Fn(usize, usize) -> f64 {
+    type I = Vec<usize>;
+    type FI = FI;
+    type E = Error;
+
+    fn search<IM>(&mut self, indiv: &Self::I, _fitness: &Self::FI, indiv_manager: &mut IM) ->
+        Result<Option<(Self::I, Self::FI)>, SearchError<Self::E, IM::E>>
+        where IM: IndividualManager<I = Self::I, FI = Self::FI>
+    {
+        let mut tour = indiv.clone();
+        if two_opt(&mut tour, &self.distance, self.max_passes) {
+            let fitness = try!(indiv_manager.fitness(&tour).map_err(SearchError::IndividualManager));
+            Ok(Some((tour, fitness)))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand;
+    use super::{Error, Crossover, Mutation, random, is_valid, two_opt, tour_length};
+    use super::{order_crossover_with, partially_mapped_crossover_with, cycle_crossover};
+
+    #[test]
+    fn known_offspring() {
+        let parent_a = vec![0, 1, 2, 3, 4, 5, 6, 7, 8];
+        let parent_b = vec![8, 2, 6, 7, 1, 5, 4, 0, 3];
+        assert_eq!(order_crossover_with(&parent_a, &parent_b, 3, 5), Ok(vec![6, 7, 1, 3, 4, 5, 0, 8, 2]));
+        assert_eq!(partially_mapped_crossover_with(&parent_a, &parent_b, 3, 5), Ok(vec![8, 2, 6, 3, 4, 5, 1, 0, 7]));
+        assert_eq!(cycle_crossover(&parent_a, &parent_b), Ok(vec![0, 2, 6, 3, 1, 5, 4, 7, 8]));
+        assert_eq!(cycle_crossover(&parent_a, &parent_b[.. 8]), Err(Error::LengthMismatch { len_a: 9, len_b: 8, }));
+        let repeated = vec![8, 2, 6, 7, 1, 5, 4, 0, 8];
+        assert_eq!(order_crossover_with(&parent_a, &repeated, 3, 5), Err(Error::NotPermutation));
+        assert_eq!(partially_mapped_crossover_with(&repeated, &parent_b, 3, 5), Err(Error::NotPermutation));
+        assert_eq!(cycle_crossover(&parent_a, &[0, 1, 2, 3, 4, 5, 6, 7, 9]), Err(Error::NotPermutation));
+    }
+
+    #[test]
+    fn operators_keep_permutations_valid() {
+        let mut rng = rand::thread_rng();
+        let crossovers = [Crossover::Order, Crossover::PartiallyMapped, Crossover::Cycle, Crossover::EdgeRecombination];
+        let mutations = [Mutation::Swap, Mutation::Insertion, Mutation::Inversion, Mutation::Scramble];
+        for len in 1 .. 32 {
+            for _ in 0 .. 16 {
+                let (parent_a, parent_b) = (random(len, &mut rng), random(len, &mut rng));
+                for crossover in crossovers.iter() {
+                    let mut child = crossover.apply(&parent_a, &parent_b, &mut rng).unwrap();
+                    assert!(is_valid(&child), "{:?} produced {:?}", crossover, child);
+                    for mutation in mutations.iter() {
+                        mutation.apply(&mut child, &mut rng);
+                        assert!(is_valid(&child), "{:?} produced {:?}", mutation, child);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn two_opt_uncrosses_square() {
+        let coords = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)];
+        let distance = |a: usize, b: usize| {
+            let (dx, dy): (f64, f64) = (coords[a].0 - coords[b].0, coords[a].1 - coords[b].1);
+            (dx * dx + dy * dy).sqrt()
+        };
+        let mut tour = vec![0, 2, 1, 3];
+        assert!(two_opt(&mut tour, &distance, 16));
+        assert!((tour_length(&tour, &distance) - 4.0).abs() < 1e-9);
+        assert!(!two_opt(&mut tour, &distance, 16));
+    }
+}
@@ -0,0 +1,400 @@
+use std::f64::consts::PI;
+use rand::{self, Rng};
+use rand::distributions::normal::StandardNormal;
+
+use super::super::pop::individual::IndividualManager;
+use super::super::pop::neighbour::NeighbourManager;
+use super::super::pop::breed::BreedManager;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Error {
+    LengthMismatch { len_a: usize, len_b: usize, },
+    InvalidBounds { index: usize, lower: f64, upper: f64, },
+}
+
+fn check_lengths(parent_a: &[f64], parent_b: &[f64]) -> Result<usize, Error> {
+    if parent_a.len() == parent_b.len() {
+        Ok(parent_a.len())
+    } else {
+        Err(Error::LengthMismatch { len_a: parent_a.len(), len_b: parent_b.len(), })
+    }
+}
+
+// per coordinate box constraints lower[i] <= x[i] <= upper[i]
+#[derive(Clone, PartialEq, Debug)]
+pub struct Bounds {
+    lower: Vec<f64>,
+    upper: Vec<f64>,
+}
+
+impl Bounds {
+    pub fn new(lower: Vec<f64>, upper: Vec<f64>) -> Result<Bounds, Error> {
+        try!(check_lengths(&lower, &upper));
+        for (index, (&lo, &hi)) in lower.iter().zip(upper.iter()).enumerate() {
+            if !lo.is_finite() || !hi.is_finite() || lo > hi {
+                return Err(Error::InvalidBounds { index: index, lower: lo, upper: hi, });
+            }
+        }
+        Ok(Bounds {
+            lower: lower,
+            upper: upper,
+        })
+    }
+
+    pub fn uniform(dimension: usize, lower: f64, upper: f64) -> Result<Bounds, Error> {
+        Bounds::new(vec![lower; dimension], vec![upper; dimension])
+    }
+
+    pub fn dimension(&self) -> usize {
+        self.lower.len()
+    }
+
+    pub fn lower(&self) -> &[f64] {
+        &self.lower
+    }
+
+    pub fn upper(&self) -> &[f64] {
+        &self.upper
+    }
+
+    pub fn width(&self, index: usize) -> f64 {
+        self.upper[index] - self.lower[index]
+    }
+
+    pub fn contains(&self, x: &[f64]) -> bool {
+        x.len() == self.dimension() && x.iter().enumerate().all(|(i, &v)| v >= self.lower[i] && v <= self.upper[i])
+    }
+
+    pub fn sample_coordinate<R>(&self, index: usize, rng: &mut R) -> f64 where R: Rng {
+        self.lower[index] + rng.gen::<f64>() * self.width(index)
+    }
+
+    pub fn random<R>(&self, rng: &mut R) -> Vec<f64> where R: Rng {
+        (0 .. self.dimension()).map(|index| self.sample_coordinate(index, rng)).collect()
+    }
+}
+
+// how to bring an out of bounds coordinate back into the box
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BoundHandling {
+    Clamp,
+    Reflect,
+    Wrap,
+    Resample,
+}
+
+impl BoundHandling {
+    pub fn apply<R>(&self, x: &mut [f64], bounds: &Bounds, rng: &mut R) where R: Rng {
+        for (index, value) in x.iter_mut().enumerate().take(bounds.dimension()) {
+            let (lo, hi, width) = (bounds.lower[index], bounds.upper[index], bounds.width(index));
+            if *value >= lo && *value <= hi {
+                continue;
+            }
+            *value = if width == 0.0 || !value.is_finite() {
+                match *self {
+                    BoundHandling::Resample => bounds.sample_coordinate(index, rng),
+                    _ => lo.max(hi.min(if value.is_nan() { lo } else { *value })),
+                }
+            } else {
+                match *self {
+                    BoundHandling::Clamp =>
+                        lo.max(hi.min(*value)),
+                    BoundHandling::Reflect => {
+                        let offset = (*value - lo) % (2.0 * width);
+                        let offset = if offset < 0.0 { offset + 2.0 * width } else { offset };
+                        lo + if offset > width { 2.0 * width - offset } else { offset }
+                    },
+                    BoundHandling::Wrap => {
+                        let offset = (*value - lo) % width;
+                        lo + if offset < 0.0 { offset + width } else { offset }
+                    },
+                    BoundHandling::Resample =>
+                        bounds.sample_coordinate(index, rng),
+                }
+            };
+        }
+    }
+}
+
+// SBX with distribution index `eta`, each gene is taken from either of the two spread offspring
+pub fn sbx_crossover<R>(parent_a: &[f64], parent_b: &[f64], eta: f64, rng: &mut R) -> Result<Vec<f64>, Error> where R: Rng {
+    try!(check_lengths(parent_a, parent_b));
+    Ok(parent_a.iter().zip(parent_b.iter()).map(|(&a, &b)| {
+        if (a - b).abs() < 1e-14 {
+            a
+        } else {
+            let u = rng.gen::<f64>();
+            let beta = if u <= 0.5 {
+                (2.0 * u).powf(1.0 / (eta + 1.0))
+            } else {
+                (1.0 / (2.0 * (1.0 - u))).powf(1.0 / (eta + 1.0))
+            };
+            let spread = if rng.gen() { beta } else { -beta };
+            0.5 * ((a + b) + spread * (a - b))
+        }
+    }).collect())
+}
+
+// BLX-α: every gene is uniform in the parents' interval extended by `alpha` times its length on both sides
+pub fn blx_alpha_crossover<R>(parent_a: &[f64], parent_b: &[f64], alpha: f64, rng: &mut R) -> Result<Vec<f64>, Error> where R: Rng {
+    try!(check_lengths(parent_a, parent_b));
+    Ok(parent_a.iter().zip(parent_b.iter()).map(|(&a, &b)| {
+        let (lo, hi) = (a.min(b), a.max(b));
+        let extent = alpha * (hi - lo);
+        (lo - extent) + rng.gen::<f64>() * (hi - lo + 2.0 * extent)
+    }).collect())
+}
+
+// weight * a + (1 - weight) * b for every gene
+pub fn arithmetic_crossover(parent_a: &[f64], parent_b: &[f64], weight: f64) -> Result<Vec<f64>, Error> {
+    try!(check_lengths(parent_a, parent_b));
+    Ok(parent_a.iter().zip(parent_b.iter()).map(|(&a, &b)| weight * a + (1.0 - weight) * b).collect())
+}
+
+// extended intermediate recombination: independent weight per gene drawn from [-extension, 1 + extension]
+pub fn intermediate_crossover<R>(parent_a: &[f64], parent_b: &[f64], extension: f64, rng: &mut R) -> Result<Vec<f64>, Error> where R: Rng {
+    try!(check_lengths(parent_a, parent_b));
+    Ok(parent_a.iter().zip(parent_b.iter()).map(|(&a, &b)| {
+        let weight = -extension + rng.gen::<f64>() * (1.0 + 2.0 * extension);
+        weight * a + (1.0 - weight) * b
+    }).collect())
+}
+
+// mutation step sizes are relative to the bounds width of the coordinate
+pub fn gaussian_mutation<R>(x: &mut [f64], bounds: &Bounds, sigma: f64, rate: f64, rng: &mut R) where R: Rng {
+    for (index, value) in x.iter_mut().enumerate().take(bounds.dimension()) {
+        if rng.gen::<f64>() < rate {
+            let StandardNormal(step) = rng.gen::<StandardNormal>();
+            *value += sigma * bounds.width(index) * step;
+        }
+    }
+}
+
+pub fn cauchy_mutation<R>(x: &mut [f64], bounds: &Bounds, scale: f64, rate: f64, rng: &mut R) where R: Rng {
+    for (index, value) in x.iter_mut().enumerate().take(bounds.dimension()) {
+        if rng.gen::<f64>() < rate {
+            let step = (PI * (rng.gen::<f64>() - 0.5)).tan();
+            *value += scale * bounds.width(index) * step;
+        }
+    }
+}
+
+// Deb's bounded polynomial mutation with distribution index `eta`
+pub fn polynomial_mutation<R>(x: &mut [f64], bounds: &Bounds, eta: f64, rate: f64, rng: &mut R) where R: Rng {
+    let power = 1.0 / (eta + 1.0);
+    for (index, value) in x.iter_mut().enumerate().take(bounds.dimension()) {
+        let (lo, hi, width) = (bounds.lower[index], bounds.upper[index], bounds.width(index));
+        if width == 0.0 || rng.gen::<f64>() >= rate {
+            continue;
+        }
+        let u = rng.gen::<f64>();
+        let delta = if u < 0.5 {
+            let xy = 1.0 - (*value - lo) / width;
+            (2.0 * u + (1.0 - 2.0 * u) * xy.powf(eta + 1.0)).powf(power) - 1.0
+        } else {
+            let xy = 1.0 - (hi - *value) / width;
+            1.0 - (2.0 * (1.0 - u) + 2.0 * (u - 0.5) * xy.powf(eta + 1.0)).powf(power)
+        };
+        *value = lo.max(hi.min(*value + delta * width));
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Crossover {
+    Sbx { eta: f64, },
+    BlxAlpha { alpha: f64, },
+    Arithmetic { weight: f64, },
+    Intermediate { extension: f64, },
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Mutation {
+    Gaussian { sigma: f64, rate: f64, },
+    Cauchy { scale: f64, rate: f64, },
+    Polynomial { eta: f64, rate: f64, },
+}
+
+impl Crossover {
+    pub fn apply<R>(&self, parent_a: &[f64], parent_b: &[f64], rng: &mut R) -> Result<Vec<f64>, Error> where R: Rng {
+        match *self {
+            Crossover::Sbx { eta, } => sbx_crossover(parent_a, parent_b, eta, rng),
+            Crossover::BlxAlpha { alpha, } => blx_alpha_crossover(parent_a, parent_b, alpha, rng),
+            Crossover::Arithmetic { weight, } => arithmetic_crossover(parent_a, parent_b, weight),
+            Crossover::Intermediate { extension, } => intermediate_crossover(parent_a, parent_b, extension, rng),
+        }
+    }
+}
+
+impl Mutation {
+    pub fn apply<R>(&self, x: &mut [f64], bounds: &Bounds, rng: &mut R) where R: Rng {
+        match *self {
+            Mutation::Gaussian { sigma, rate, } => gaussian_mutation(x, bounds, sigma, rate, rng),
+            Mutation::Cauchy { scale, rate, } => cauchy_mutation(x, bounds, scale, rate, rng),
+            Mutation::Polynomial { eta, rate, } => polynomial_mutation(x, bounds, eta, rate, rng),
+        }
+    }
+}
+
+// generates uniformly random vectors within `bounds` and evaluates them with `fitness`
+pub struct RealVectorManager<F, FI> where F: FnMut(&[f64]) -> FI {
+    bounds: Bounds,
+    fitness: F,
+}
+
+impl<F, FI> RealVectorManager<F, FI> where F: FnMut(&[f64]) -> FI {
+    pub fn new(bounds: Bounds, fitness: F) -> RealVectorManager<F, FI> {
+        RealVectorManager {
+            bounds: bounds,
+            fitness: fitness,
+        }
+    }
+}
+
+impl<F, FI> IndividualManager for RealVectorManager<F, FI> where F: FnMut(&[f64]) -> FI {
+    type I = Vec<f64>;
+    type FI = FI;
+    type E = Error;
+
+    fn generate(&mut self, _index: usize) -> Result<Self::I, Self::E> {
+        Ok(self.bounds.random(&mut rand::thread_rng()))
+    }
+
+    fn fitness(&mut self, indiv: &Self::I) -> Result<Self::FI, Self::E> {
+        if indiv.len() != self.bounds.dimension() {
+            return Err(Error::LengthMismatch { len_a: self.bounds.dimension(), len_b: indiv.len(), });
+        }
+        Ok((self.fitness)(indiv))
+    }
+}
+
+pub struct RealVectorBreeder {
+    bounds: Bounds,
+    crossover: Crossover,
+    mutation: Mutation,
+    handling: BoundHandling,
+}
+
+impl RealVectorBreeder {
+    pub fn new(bounds: Bounds, crossover: Crossover, mutation: Mutation, handling: BoundHandling) -> RealVectorBreeder {
+        RealVectorBreeder {
+            bounds: bounds,
+            crossover: crossover,
+            mutation: mutation,
+            handling: handling,
+        }
+    }
+}
+
+impl BreedManager for RealVectorBreeder {
+    type I = Vec<f64>;
+    type E = Error;
+
+    fn breed(&mut self, parent_a: &Self::I, parent_b: &Self::I) -> Result<Self::I, Self::E> {
+        let mut rng = rand::thread_rng();
+        let mut child = try!(self.crossover.apply(parent_a, parent_b, &mut rng));
+        self.mutation.apply(&mut child, &self.bounds, &mut rng);
+        self.handling.apply(&mut child, &self.bounds, &mut rng);
+        Ok(child)
+    }
+}
+
+// random neighbour produced by the given mutation, kept within bounds
+pub struct RealVectorNeighbour {
+    bounds: Bounds,
+    mutation: Mutation,
+    handling: BoundHandling,
+}
+
+impl RealVectorNeighbour {
+    pub fn new(bounds: Bounds, mutation: Mutation, handling: BoundHandling) -> RealVectorNeighbour {
+        RealVectorNeighbour {
+            bounds: bounds,
+            mutation: mutation,
+            handling: handling,
+        }
+    }
+}
+
+impl NeighbourManager for RealVectorNeighbour {
+    type I = Vec<f64>;
+    type E = Error;
+
+    fn neighbour(&mut self, indiv: &Self::I, _attempt: usize) -> Result<Option<Self::I>, Self::E> {
+        let mut rng = rand::thread_rng();
+        let mut candidate = indiv.clone();
+        self.mutation.apply(&mut candidate, &self.bounds, &mut rng);
+        self.handling.apply(&mut candidate, &self.bounds, &mut rng);
+        Ok(Some(candidate))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand;
+    use super::{Error, Bounds, BoundHandling, Crossover, Mutation, RealVectorManager};
+    use super::{arithmetic_crossover, sbx_crossover};
+    use super::super::super::pop::individual::IndividualManager;
+
+    #[test]
+    fn bound_handling() {
+        let mut rng = rand::thread_rng();
+        let bounds = Bounds::uniform(4, 0.0, 10.0).unwrap();
+        let outside = [-3.0, 12.0, 27.0, 5.0];
+        let mut x = outside.to_vec();
+        BoundHandling::Clamp.apply(&mut x, &bounds, &mut rng);
+        assert_eq!(x, vec![0.0, 10.0, 10.0, 5.0]);
+        let mut x = outside.to_vec();
+        BoundHandling::Reflect.apply(&mut x, &bounds, &mut rng);
+        assert_eq!(x, vec![3.0, 8.0, 7.0, 5.0]);
+        let mut x = outside.to_vec();
+        BoundHandling::Wrap.apply(&mut x, &bounds, &mut rng);
+        assert_eq!(x, vec![7.0, 2.0, 7.0, 5.0]);
+        let mut x = outside.to_vec();
+        BoundHandling::Resample.apply(&mut x, &bounds, &mut rng);
+        assert!(bounds.contains(&x) && x[3] == 5.0);
+        assert_eq!(Bounds::new(vec![1.0], vec![0.0]), Err(Error::InvalidBounds { index: 0, lower: 1.0, upper: 0.0, }));
+    }
+
+    #[test]
+    fn operators() {
+        let mut rng = rand::thread_rng();
+        let bounds = Bounds::uniform(8, -5.0, 5.0).unwrap();
+        let (parent_a, parent_b) = (bounds.random(&mut rng), bounds.random(&mut rng));
+        assert_eq!(sbx_crossover(&parent_a, &parent_a, 15.0, &mut rng), Ok(parent_a.clone()));
+        assert_eq!(arithmetic_crossover(&[0.0, 2.0], &[4.0, 6.0], 0.25), Ok(vec![3.0, 5.0]));
+        assert_eq!(arithmetic_crossover(&[0.0], &[4.0, 6.0], 0.25), Err(Error::LengthMismatch { len_a: 1, len_b: 2, }));
+        let crossovers = [
+            Crossover::Sbx { eta: 2.0, },
+            Crossover::BlxAlpha { alpha: 0.5, },
+            Crossover::Arithmetic { weight: 0.3, },
+            Crossover::Intermediate { extension: 0.25, },
+        ];
+        let mutations = [
+            Mutation::Gaussian { sigma: 0.5, rate: 1.0, },
+            Mutation::Cauchy { scale: 0.5, rate: 1.0, },
+            Mutation::Polynomial { eta: 20.0, rate: 1.0, },
+        ];
+        for _ in 0 .. 64 {
+            for crossover in crossovers.iter() {
+                for mutation in mutations.iter() {
+                    let mut child = crossover.apply(&parent_a, &parent_b, &mut rng).unwrap();
+                    mutation.apply(&mut child, &bounds, &mut rng);
+                    BoundHandling::Reflect.apply(&mut child, &bounds, &mut rng);
+                    assert!(bounds.contains(&child), "{:?} + {:?} produced {:?}", crossover, mutation, child);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn manager() {
+        let bounds = Bounds::new(vec![-1.0, 2.0], vec![1.0, 3.0]).unwrap();
+        let mut manager = RealVectorManager::new(bounds.clone(), |x: &[f64]| x.iter().map(|v| v * v).sum::<f64>());
+        for index in 0 .. 32 {
+            let indiv = manager.generate(index).unwrap();
+            assert!(bounds.contains(&indiv));
+            assert!(manager.fitness(&indiv).unwrap() >= 4.0);
+        }
+        assert_eq!(manager.fitness(&vec![0.0]), Err(Error::LengthMismatch { len_a: 2, len_b: 1, }));
+    }
+}